@@ -1,5 +1,6 @@
 mod string_builder;
 mod usize_builder;
+mod vec_builder;
 
 pub trait FromCharSequence<T> {
     type Error;
@@ -12,4 +13,12 @@ pub trait FromCharSequenceBuilder<T>: Default {
 
     fn append(&mut self, char: T);
     fn build(&self) -> Result<Self::Type, Self::Error>;
+
+    // collectの対象に入るたびに呼ばれる。既定では何もせず、続けて文字を受け取る。
+    fn start(&mut self) {}
+
+    // 一度collectの対象から外れた後、再び対象に入ったときに呼ばれる。既定では新しいビルダーに置き換える。
+    fn restart(&mut self) {
+        *self = Self::default();
+    }
 }
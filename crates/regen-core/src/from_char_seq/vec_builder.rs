@@ -0,0 +1,46 @@
+use crate::{FromCharSequence, FromCharSequenceBuilder};
+use std::marker::PhantomData;
+
+impl<T, E: FromCharSequence<T>> FromCharSequence<T> for Vec<E> {
+    type Error = E::Error;
+    type Builder = VecBuilder<T, E>;
+}
+
+// collectの対象に入るたびに要素を一つ追加し、以降の文字はその要素に渡す。
+pub struct VecBuilder<T, E: FromCharSequence<T>> {
+    items: Vec<E::Builder>,
+    marker: PhantomData<fn(T)>,
+}
+
+impl<T, E: FromCharSequence<T>> Default for VecBuilder<T, E> {
+    fn default() -> Self {
+        Self {
+            items: Vec::new(),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T, E: FromCharSequence<T>> FromCharSequenceBuilder<T> for VecBuilder<T, E> {
+    type Type = Vec<<E::Builder as FromCharSequenceBuilder<T>>::Type>;
+    type Error = E::Error;
+
+    fn append(&mut self, char: T) {
+        if self.items.is_empty() {
+            self.items.push(Default::default());
+        }
+
+        self.items.last_mut().unwrap().append(char);
+    }
+
+    fn build(&self) -> Result<Self::Type, Self::Error> {
+        self.items.iter().map(|e| e.build()).collect()
+    }
+
+    fn start(&mut self) {
+        self.items.push(Default::default());
+    }
+
+    // 要素は `start` で区切るため、それまでの要素を残す。
+    fn restart(&mut self) {}
+}
@@ -1,6 +1,12 @@
 mod from_char_seq;
+mod sub_builder;
+mod sub_pattern;
+mod validate;
 
 pub use from_char_seq::{FromCharSequence, FromCharSequenceBuilder};
+pub use sub_builder::SubBuilder;
+pub use sub_pattern::{ElementSet, SubPattern};
+pub use validate::Validation;
 
 pub trait Parse<T>: Sized {
     type Error;
@@ -36,6 +42,17 @@ pub enum MatchError<E = Box<dyn std::error::Error>> {
     Collect(E),
 }
 
+impl<E: std::fmt::Display> std::fmt::Display for MatchError<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MatchError::NotMatched => write!(f, "not matched"),
+            MatchError::Collect(e) => e.fmt(f),
+        }
+    }
+}
+
+impl<E: std::fmt::Debug + std::fmt::Display> std::error::Error for MatchError<E> {}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum NeverError {}
 
@@ -0,0 +1,71 @@
+use crate::{AdvanceResult, FromCharSequenceBuilder, Parse, StateMachine};
+
+// `sub!` で参照された列挙型の状態機械に文字を渡す。
+// 値は状態機械から組み立てる。状態機械は `Error` を返すと状態を失うため、その時だけ受け取った文字を
+// 新しい状態機械に流し直す。
+pub struct SubBuilder<T, P: Parse<T>> {
+    machine: P::StateMachine,
+    // 流し直すために、`Error` を返すまでに受け取った文字を持つ。
+    buf: Vec<T>,
+    matched: bool,
+}
+
+impl<T, P: Parse<T>> SubBuilder<T, P> {
+    pub fn advance(&mut self, c: T) -> AdvanceResult
+    where
+        T: Copy,
+    {
+        let result = self.machine.advance(c);
+        match result {
+            // マッチした後に続かない文字を受け取った場合は、マッチした時点の状態を作り直す。
+            // 委譲元はこれ以降文字を渡さないため、流し直すのは一度だけになる。
+            AdvanceResult::Error => {
+                if self.matched {
+                    let mut machine = P::StateMachine::default();
+                    for c in std::mem::take(&mut self.buf) {
+                        machine.advance(c);
+                    }
+                    self.machine = machine;
+                }
+            }
+            AdvanceResult::Match(_) => {
+                self.buf.push(c);
+                self.matched = true;
+            }
+            AdvanceResult::Partial(_) | AdvanceResult::Rewind(_) => {
+                self.buf.push(c);
+                self.matched = false;
+            }
+        }
+        result
+    }
+
+    pub fn is_matched(&self) -> bool {
+        self.matched
+    }
+}
+
+impl<T, P: Parse<T>> Default for SubBuilder<T, P> {
+    fn default() -> Self {
+        let machine = P::StateMachine::default();
+        let matched = machine.current().is_ok();
+        Self {
+            machine,
+            buf: Vec::new(),
+            matched,
+        }
+    }
+}
+
+impl<T: Copy, P: Parse<T>> FromCharSequenceBuilder<T> for SubBuilder<T, P> {
+    type Type = P;
+    type Error = P::Error;
+
+    fn append(&mut self, char: T) {
+        self.advance(char);
+    }
+
+    fn build(&self) -> Result<Self::Type, Self::Error> {
+        self.machine.current()
+    }
+}
@@ -0,0 +1,39 @@
+// 要素を `u64` に直した、両端を含む範囲の集まり。
+// `sub!` の曖昧さを定数の評価で検査するため、他の列挙型の集合を参照して組み立てられるようにする。
+#[derive(Debug, Clone, Copy)]
+pub enum ElementSet {
+    Range(u64, u64),
+    Union(&'static [ElementSet]),
+}
+
+impl ElementSet {
+    pub const EMPTY: Self = Self::Union(&[]);
+
+    pub const fn overlaps(self, other: Self) -> bool {
+        match (self, other) {
+            (Self::Union(sets), other) | (other, Self::Union(sets)) => {
+                let mut i = 0;
+                while i < sets.len() {
+                    if sets[i].overlaps(other) {
+                        return true;
+                    }
+                    i += 1;
+                }
+                false
+            }
+            (Self::Range(s0, e0), Self::Range(s1, e1)) => {
+                s0 <= e0 && s1 <= e1 && s0 <= e1 && s1 <= e0
+            }
+        }
+    }
+}
+
+// `sub!` で参照される列挙型が読む要素。委譲する側が、続くパターンと曖昧にならないことを確かめる。
+pub trait SubPattern<T> {
+    // 初期状態から読みうる要素。
+    const FIRST: ElementSet;
+    // 空列にマッチするかどうか。
+    const NULLABLE: bool;
+    // マッチした後に、さらに読み進めうる要素。
+    const CONTINUATIONS: ElementSet;
+}
@@ -0,0 +1,23 @@
+use crate::MatchError;
+
+// `#[validate]` に指定した関数の戻り値。
+// `false` はマッチしなかったものとし、`Err` は `MatchError::Collect` として返す。
+pub trait Validation<E> {
+    fn into_result(self) -> Result<(), MatchError<E>>;
+}
+
+impl<E> Validation<E> for bool {
+    fn into_result(self) -> Result<(), MatchError<E>> {
+        if self {
+            Ok(())
+        } else {
+            Err(MatchError::NotMatched)
+        }
+    }
+}
+
+impl<E, F: Into<E>> Validation<E> for Result<(), F> {
+    fn into_result(self) -> Result<(), MatchError<E>> {
+        self.map_err(|e| MatchError::Collect(e.into()))
+    }
+}
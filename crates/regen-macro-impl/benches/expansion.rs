@@ -0,0 +1,138 @@
+// 大きなパターンの展開にかかる時間を測る。
+// `cargo bench -p regen-macro-impl --features unicode -- <名前の一部>` で対象を絞れる。
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use std::time::{Duration, Instant};
+
+const MIN_RUNS: usize = 3;
+const MIN_TIME: Duration = Duration::from_secs(2);
+
+struct Case {
+    name: &'static str,
+    attr: TokenStream,
+    body: TokenStream,
+}
+
+fn cases() -> Vec<Case> {
+    let mut cases = vec![
+        Case {
+            name: "bounded_repeats",
+            attr: quote! { char },
+            body: quote! {
+                enum Bench {
+                    #[pattern = [("ab" | "cd"); 0..=40] + ';']
+                    Pairs,
+                    #[pattern = [(alpha + digit) | "--"; 1..=24] + '.' + [digit; 0..=8]]
+                    Mixed,
+                }
+            },
+        },
+        Case {
+            name: "regex",
+            attr: quote! { char },
+            body: quote! {
+                enum Bench {
+                    #[regex = r"(?<_user>[\w.+-]+)@(?<_host>[\w-]+(\.[\w-]+){1,6})"]
+                    Mail { _user: String, _host: String },
+                    #[regex = r"(?<_year>\d{4})-(?<_month>\d{2})-(?<_day>\d{2})(T\d{2}:\d{2}(:\d{2})?)?"]
+                    Date { _year: String, _month: String, _day: String },
+                }
+            },
+        },
+        Case {
+            name: "keywords",
+            attr: quote! { char },
+            body: keywords(300),
+        },
+    ];
+
+    if cfg!(feature = "unicode") {
+        cases.push(Case {
+            name: "unicode_classes",
+            attr: quote! { char },
+            body: quote! {
+                #[allow_conflict]
+                enum Bench {
+                    #[pattern = (L | '_') + [L | Nd | '_'; ..]]
+                    Ident,
+                    #[pattern = [Greek | Cyrillic; 1..] + opt!(' ' + [Han | Hiragana | Katakana; 1..=4])]
+                    Words,
+                    #[pattern = [Nd; 1..] + opt!('.' + [Nd; 1..])]
+                    Number,
+                    #[pattern = [Zs; 1..]]
+                    Space,
+                    #[pattern = !(L | Nd | Zs | '_')]
+                    Symbol,
+                }
+            },
+        });
+        cases.push(Case {
+            name: "unicode_ignore_case",
+            attr: quote! { char },
+            body: quote! {
+                #[ignore_case]
+                enum Bench {
+                    #[pattern = "straße" + [Latin; ..=6]]
+                    Street,
+                    #[pattern = [Greek; 1..=8] + "σ"]
+                    Greek,
+                    #[pattern = [Cyrillic | Armenian; 1..]]
+                    Other,
+                }
+            },
+        });
+    }
+
+    cases
+}
+
+// 共通の接頭辞を持つ多数のキーワード。
+fn keywords(count: usize) -> TokenStream {
+    let variants = (0..count).map(|i| {
+        let name = format_ident!("K{i}");
+        let keyword = format!("keyword_{}_{}", i % 17, i);
+        quote! {
+            #[pattern = #keyword]
+            #name,
+        }
+    });
+
+    quote! {
+        enum Bench {
+            #(#variants)*
+        }
+    }
+}
+
+fn main() {
+    let filter = std::env::args().skip(1).find(|a| !a.starts_with('-'));
+    for case in cases() {
+        if filter.as_ref().is_some_and(|f| !case.name.contains(f.as_str())) {
+            continue;
+        }
+
+        let mut times = Vec::new();
+        let started = Instant::now();
+        while times.len() < MIN_RUNS || started.elapsed() < MIN_TIME {
+            let start = Instant::now();
+            let tokens = regen_macro_impl::regen(case.attr.clone(), case.body.clone());
+            times.push(start.elapsed());
+
+            let tokens = tokens.to_string();
+            if let Some(i) = tokens.find("compile_error") {
+                let end = tokens[i..].find(';').map_or(tokens.len(), |e| i + e);
+                panic!("`{}` failed to expand: {}", case.name, &tokens[i..end]);
+            }
+        }
+
+        times.sort();
+        let median = times[times.len() / 2];
+        println!(
+            "{:<24}{:>10.2?} (min {:.2?}, {} runs)",
+            case.name,
+            median,
+            times[0],
+            times.len()
+        );
+    }
+}
@@ -0,0 +1,28 @@
+#!/usr/bin/env perl
+# Generates src/unicode/case_folding.rs from the Unicode database bundled with perl.
+#
+#     perl scripts/generate_case_folding.pl > src/unicode/case_folding.rs
+use strict;
+use warnings;
+use Unicode::UCD qw(all_casefolds);
+
+my $folds = all_casefolds();
+my @pairs;
+for my $code (sort { $a <=> $b } keys %$folds) {
+    my $fold = $folds->{$code};
+    # 単純なケースフォールディング（C と S）のみを使う。
+    next unless $fold->{status} eq "C" || $fold->{status} eq "S";
+    push @pairs, [$code, hex $fold->{mapping}];
+}
+
+my $version = Unicode::UCD::UnicodeVersion();
+
+print "// This file is generated by scripts/generate_case_folding.pl. Do not edit.\n";
+print "// Unicode $version\n\n";
+
+print "pub const CASE_FOLDING: &[(char, char)] = &[\n";
+my @entries = map { sprintf "('\\u{%X}', '\\u{%X}')", @$_ } @pairs;
+while (my @line = splice(@entries, 0, 4)) {
+    print "    ", join(", ", @line), ",\n";
+}
+print "];\n";
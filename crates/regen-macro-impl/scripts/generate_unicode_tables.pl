@@ -0,0 +1,81 @@
+#!/usr/bin/env perl
+# Generates src/unicode/tables.rs from the Unicode database bundled with perl.
+#
+#     perl scripts/generate_unicode_tables.pl > src/unicode/tables.rs
+use strict;
+use warnings;
+use Unicode::UCD qw(prop_invlist prop_values prop_value_aliases);
+
+my @properties = qw(
+    Alphabetic Lowercase Uppercase White_Space
+    ID_Start ID_Continue XID_Start XID_Continue
+);
+
+my %tables;
+my %names;
+
+sub ranges {
+    my ($prop) = @_;
+    my @invlist = prop_invlist($prop);
+    push @invlist, 0x110000 if @invlist % 2;
+
+    my @ranges;
+    while (my ($start, $end) = splice(@invlist, 0, 2)) {
+        $end -= 1;
+        # サロゲートは char として表現できない。
+        if ($start <= 0xDFFF && $end >= 0xD800) {
+            push @ranges, [$start, 0xD7FF] if $start < 0xD800;
+            push @ranges, [0xE000, $end] if $end > 0xDFFF;
+        } else {
+            push @ranges, [$start, $end];
+        }
+    }
+    return \@ranges;
+}
+
+sub add {
+    my ($table, $prop, @names) = @_;
+    my $ranges = ranges($prop);
+    return unless @$ranges;
+
+    $tables{$table} = $ranges;
+    for my $name (@names) {
+        die "duplicated name: $name" if exists $names{$name} && $names{$name} ne $table;
+        $names{$name} = $table;
+    }
+}
+
+for my $prop (@properties) {
+    add("PROP_\U$prop", $prop, $prop);
+}
+
+for my $gc (prop_values("General_Category")) {
+    next if $gc eq "Cs";
+    my @aliases = prop_value_aliases("General_Category", $gc);
+    add("GC_\U$gc", "General_Category=$gc", @aliases);
+}
+
+for my $sc (prop_values("Script")) {
+    my @aliases = prop_value_aliases("Script", $sc);
+    add("SC_\U$sc", "Script=$sc", @aliases);
+}
+
+my $version = Unicode::UCD::UnicodeVersion();
+
+print "// This file is generated by scripts/generate_unicode_tables.pl. Do not edit.\n";
+print "// Unicode $version\n\n";
+
+print "pub const CLASSES: &[(&str, &[(char, char)])] = &[\n";
+for my $name (sort keys %names) {
+    print "    (\"$name\", $names{$name}),\n";
+}
+print "];\n";
+
+for my $table (sort keys %tables) {
+    print "\nconst $table: &[(char, char)] = &[\n";
+    my @ranges = map { sprintf "('\\u{%X}', '\\u{%X}')", @$_ } @{ $tables{$table} };
+    while (my @line = splice(@ranges, 0, 4)) {
+        print "    ", join(", ", @line), ",\n";
+    }
+    print "];\n";
+}
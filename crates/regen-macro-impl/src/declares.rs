@@ -3,40 +3,117 @@ use syn::{punctuated::Punctuated, spanned::Spanned};
 
 #[derive(Debug, Clone)]
 pub struct Declares<T: PatternChar> {
-    variables: Vec<(String, Pattern<T>)>,
+    variables: Vec<(syn::Ident, Declare<T>)>,
+    rules: Vec<(syn::Ident, Pattern<T>)>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Declare<T: PatternChar> {
+    Pattern(Pattern<T>),
+    // 引数を取る宣言。使われる箇所で引数を束縛して展開する。
+    Function {
+        params: Vec<syn::Ident>,
+        body: Pattern<T>,
+    },
 }
 
 impl<T: PatternChar> Declares<T> {
     pub fn new() -> Self {
         Self {
             variables: Vec::new(),
+            rules: Vec::new(),
         }
     }
 
-    pub fn variables(&self) -> &[(String, Pattern<T>)] {
+    pub fn variables(&self) -> &[(syn::Ident, Declare<T>)] {
         &self.variables
     }
 
+    // `rec` を付けて宣言された、自身や他の `rec` 宣言を参照できるパターン。
+    pub fn rules(&self) -> &[(syn::Ident, Pattern<T>)] {
+        &self.rules
+    }
+
     pub fn append(&mut self, mut other: Declares<T>) {
         self.variables.append(&mut other.variables);
+        self.rules.append(&mut other.rules);
     }
 }
 
 impl<T: PatternChar> syn::parse::Parse for Declares<T> {
     fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
-        let assigns = Punctuated::<syn::ExprAssign, syn::Token![;]>::parse_terminated(input)?;
-        let variables = assigns
-            .into_iter()
-            .map(|e| {
-                let ident = match *e.left {
-                    syn::Expr::Path(e) => e.path.require_ident()?.to_string(),
-                    _ => return Err(syn::Error::new(e.span(), "Ident was expected.")),
-                };
-                let pattern = Pattern::<T>::new(&e.right)?;
-                Ok((ident, pattern))
-            })
-            .collect::<Result<_, _>>()?;
-
-        Ok(Self { variables })
+        let assigns = parse_entries(input)?;
+
+        let mut variables = Vec::new();
+        let mut rules = Vec::new();
+        for (is_rec, e) in assigns {
+            let pattern = Pattern::<T>::new(&e.right)?;
+            match *e.left {
+                syn::Expr::Path(left) => {
+                    let ident = left.path.require_ident()?.clone();
+                    if is_rec {
+                        rules.push((ident, pattern));
+                    } else {
+                        variables.push((ident, Declare::Pattern(pattern)));
+                    }
+                }
+                syn::Expr::Call(left) => {
+                    if is_rec {
+                        return Err(syn::Error::new(
+                            left.span(),
+                            "Recursive declarations cannot take parameters.",
+                        ));
+                    }
+
+                    let ident = expect_ident(&left.func)?;
+                    let mut params: Vec<syn::Ident> = Vec::new();
+                    for arg in &left.args {
+                        let param = expect_ident(arg)?;
+                        if params.contains(&param) {
+                            return Err(syn::Error::new(
+                                param.span(),
+                                format!("Duplicated parameter `{param}`."),
+                            ));
+                        }
+                        params.push(param);
+                    }
+
+                    let function = Declare::Function {
+                        params,
+                        body: pattern,
+                    };
+                    variables.push((ident, function));
+                }
+                left => return Err(syn::Error::new(left.span(), "Ident was expected.")),
+            }
+        }
+
+        Ok(Self { variables, rules })
+    }
+}
+
+// 宣言の並びを、`rec` が付いているかどうかと代入式の組に分ける。型に依らない構文のみを確かめる。
+pub fn parse_entries(
+    input: syn::parse::ParseStream,
+) -> syn::Result<Punctuated<(bool, syn::ExprAssign), syn::Token![;]>> {
+    Punctuated::parse_terminated_with(input, |input| {
+        if input.peek(syn::Token![use]) {
+            return Err(input.error("`use` cannot be used in pattern libraries."));
+        }
+
+        let fork = input.fork();
+        let is_rec = fork.parse::<syn::Ident>().is_ok_and(|i| i == "rec") && fork.peek(syn::Ident);
+        if is_rec {
+            input.parse::<syn::Ident>()?;
+        }
+
+        Ok((is_rec, input.parse::<syn::ExprAssign>()?))
+    })
+}
+
+fn expect_ident(expr: &syn::Expr) -> syn::Result<syn::Ident> {
+    match expr {
+        syn::Expr::Path(e) => Ok(e.path.require_ident()?.clone()),
+        e => Err(syn::Error::new(e.span(), "Ident was expected.")),
     }
 }
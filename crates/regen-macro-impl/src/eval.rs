@@ -17,26 +17,50 @@ pub fn eval_as_usize(expr: &syn::Expr) -> syn::Result<usize> {
             let lhs = eval_as_usize(&e.left)?;
             let rhs = eval_as_usize(&e.right)?;
             let v = match &e.op {
-                syn::BinOp::Add(_) => lhs + rhs,
-                syn::BinOp::Sub(_) => lhs - rhs,
-                syn::BinOp::Mul(_) => lhs * rhs,
-                syn::BinOp::Div(_) => lhs / rhs,
-                syn::BinOp::Rem(_) => lhs % rhs,
-                syn::BinOp::BitXor(_) => lhs ^ rhs,
-                syn::BinOp::BitAnd(_) => lhs & rhs,
-                syn::BinOp::BitOr(_) => lhs | rhs,
-                syn::BinOp::Shl(_) => lhs << rhs,
-                syn::BinOp::Shr(_) => lhs >> rhs,
+                syn::BinOp::Add(_) => lhs.checked_add(rhs),
+                syn::BinOp::Sub(_) => lhs.checked_sub(rhs),
+                syn::BinOp::Mul(_) => lhs.checked_mul(rhs),
+                syn::BinOp::Div(_) => lhs.checked_div(rhs),
+                syn::BinOp::Rem(_) => lhs.checked_rem(rhs),
+                syn::BinOp::BitXor(_) => Some(lhs ^ rhs),
+                syn::BinOp::BitAnd(_) => Some(lhs & rhs),
+                syn::BinOp::BitOr(_) => Some(lhs | rhs),
+                syn::BinOp::Shl(_) => u32::try_from(rhs).ok().and_then(|r| lhs.checked_shl(r)),
+                syn::BinOp::Shr(_) => u32::try_from(rhs).ok().and_then(|r| lhs.checked_shr(r)),
                 _ => return Err(syn::Error::new(expr.span(), "Unsupported operator.")),
             };
 
-            Ok(v)
+            v.ok_or_else(|| syn::Error::new(expr.span(), "Arithmetic overflow."))
         }
         syn::Expr::Paren(e) => eval_as_usize(&e.expr),
+        syn::Expr::Path(_) => Err(syn::Error::new(
+            expr.span(),
+            "Constants can only be used in bounds of `repeat!` and range patterns.",
+        )),
         _ => Err(syn::Error::new(expr.span(), "Unsupported expression.")),
     }
 }
 
+// 定数を参照する範囲は展開時には評価できないため、生成するコードに埋め込んでコンパイラに評価させる。
+pub fn is_const_range(expr: &syn::Expr) -> bool {
+    match expr {
+        syn::Expr::Range(e) => e.start.iter().chain(&e.end).any(|e| refers_to_const(e)),
+        syn::Expr::Paren(e) => is_const_range(&e.expr),
+        _ => false,
+    }
+}
+
+fn refers_to_const(expr: &syn::Expr) -> bool {
+    match expr {
+        syn::Expr::Path(_) => true,
+        syn::Expr::Binary(e) => refers_to_const(&e.left) || refers_to_const(&e.right),
+        syn::Expr::Unary(e) => refers_to_const(&e.expr),
+        syn::Expr::Paren(e) => refers_to_const(&e.expr),
+        syn::Expr::Cast(e) => refers_to_const(&e.expr),
+        _ => false,
+    }
+}
+
 pub fn eval_as_range(expr: &syn::Expr) -> syn::Result<(Bound<usize>, Bound<usize>)> {
     match expr {
         syn::Expr::Range(e) => {
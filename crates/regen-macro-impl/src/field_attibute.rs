@@ -2,12 +2,24 @@ use syn::spanned::Spanned;
 
 pub struct FieldAttribute {
     builder: Option<syn::Path>,
+    default: Option<syn::Expr>,
+    map: Option<syn::Path>,
 }
 
 impl FieldAttribute {
     pub fn builder(&self) -> Option<&syn::Path> {
         self.builder.as_ref()
     }
+
+    // 受理した経路でcollectも代入もされなかった場合の値。
+    pub fn default(&self) -> Option<&syn::Expr> {
+        self.default.as_ref()
+    }
+
+    // ビルダーの出力をフィールドの型に変換する関数。
+    pub fn map(&self) -> Option<&syn::Path> {
+        self.map.as_ref()
+    }
 }
 
 pub fn strip_field_attribute(field: &mut syn::Field) -> syn::Result<FieldAttribute> {
@@ -16,10 +28,12 @@ pub fn strip_field_attribute(field: &mut syn::Field) -> syn::Result<FieldAttribu
             return false;
         };
 
-        ident == "builder"
+        ident == "builder" || ident == "default" || ident == "map"
     });
 
     let mut builder = None;
+    let mut default = None;
+    let mut map = None;
     for attr in attrs {
         let ident = attr.meta.path().get_ident().unwrap();
 
@@ -36,8 +50,38 @@ pub fn strip_field_attribute(field: &mut syn::Field) -> syn::Result<FieldAttribu
             };
 
             builder = Some(path.path.clone());
+        } else if ident == "default" {
+            if default.is_some() {
+                return Err(syn::Error::new(
+                    attr.span(),
+                    "Duplicated default attributes.",
+                ));
+            }
+
+            default = Some(attr.meta.require_name_value()?.value.clone());
+        } else if ident == "map" {
+            if map.is_some() {
+                return Err(syn::Error::new(attr.span(), "Duplicated map attributes."));
+            }
+
+            let name_value = attr.meta.require_name_value()?;
+            let path = match &name_value.value {
+                syn::Expr::Path(p) => p,
+                _ => {
+                    return Err(syn::Error::new(
+                        name_value.value.span(),
+                        "map path was expected.",
+                    ));
+                }
+            };
+
+            map = Some(path.path.clone());
         }
     }
 
-    Ok(FieldAttribute { builder })
+    Ok(FieldAttribute {
+        builder,
+        default,
+        map,
+    })
 }
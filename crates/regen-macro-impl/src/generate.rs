@@ -1,10 +1,15 @@
-use std::collections::HashMap;
+mod lookup;
+mod sub_pattern;
+mod table;
+
+use std::collections::{BTreeSet, HashMap};
 
 use crate::{
-   field_attibute::FieldAttribute, match_graph::{self, MatchGraph, MatchState}, pattern::{ResolveEnv}, pattern_char::PatternChar, regen_options::RegenOptions, regen_prelude::RegenPrelude, variant_pattern::VariantPattern
+   field_attibute::FieldAttribute, match_graph::{Call, CallTarget, ElementClasses, MatchGraph, MatchProp, MatchState, Rules, Transition}, pattern::{Pattern, PatternIgnoreCase, ResolveEnv}, pattern_char::PatternChar, regen_args::Codegen, regen_options::RegenOptions, regen_prelude::RegenPrelude, variant_pattern::VariantPattern
 };
 use proc_macro2::{Span, TokenStream};
-use quote::{format_ident, quote};
+use quote::{ToTokens, format_ident, quote, quote_spanned};
+use syn::spanned::Spanned;
 
 pub fn generate_state_machine<T: PatternChar>(
     options: &RegenOptions,
@@ -20,66 +25,157 @@ pub fn generate_state_machine<T: PatternChar>(
     let state_machine_state_name = resolver.state_machine_state_type_name(item);
     let match_error_type = resolver.match_error_type();
     let vis = &item.vis;
+    let (impl_generics, ty_generics, where_clause) = item.generics.split_for_impl();
 
     let root_env = match ResolveEnv::new(&ResolveEnv::empty(), &prelude.declares) {
         Ok(v) => v,
         Err(e) => {
             return e.into_compile_error()        },
     };
+    let rule_patterns: Vec<_> = root_env.rules().iter().map(|(name, pattern)| {
+        let pattern = if options.ignore_case() {
+            pattern.ignore_case()
+        } else {
+            pattern.clone()
+        };
+        (name.clone(), pattern)
+    }).collect();
+    let rules = match Rules::new(&rule_patterns, options.max_states()) {
+        Ok(v) => v,
+        Err(e) => return e.into_compile_error(),
+    };
+
+    let mut warnings = root_env.warnings().to_vec();
     let mut variant_field_attrs = Vec::with_capacity(variants.len());
-    let mut builder = match_graph::Builder::new();
-    for (assoc, variant) in variants.into_iter().enumerate() {
+    let mut validates = Vec::with_capacity(variants.len());
+    let mut patterns = Vec::with_capacity(variants.len());
+    for variant in variants {
         let env = match ResolveEnv::new(&root_env, &variant.declares) {
             Ok(v) => v,
             Err(e) => return e.into_compile_error(),
         };
+        warnings.extend_from_slice(env.warnings());
         
-        let pattern = match env.resolve(&variant.pattern) {
+        // 否定を含むパターンも同一視した要素から否定するよう、解決する前に同一視する。
+        let pattern = if options.ignore_case() || variant.ignore_case {
+            Pattern::IgnoreCase(Box::new(PatternIgnoreCase {
+                pattern: variant.pattern,
+            }))
+        } else {
+            variant.pattern
+        };
+        let pattern = match env.resolve(&pattern) {
             Ok(v) => v,
             Err(e) => return e.to_compile_error(),
         };
 
         variant_field_attrs.push(variant.field_attrs);
-        builder.add(assoc, &pattern);
+        validates.push(variant.validate);
+        patterns.push(pattern);
     }
 
-    let graph = builder.build();
-    let errors = match error_check(options, item, prelude, &graph) {
+    // 長い繰り返しはカウンタで数える。展開した場合と同じにマッチしない箇所があれば、全て展開して作り直す。
+    let build = |counting: bool| {
+        let mut builder = rules.builder();
+        builder.set_counting(counting);
+        for (assoc, pattern) in patterns.iter().enumerate() {
+            builder.add(assoc, pattern);
+        }
+        builder.build()
+    };
+    let graph = build(true).and_then(|graph| if graph.counters_are_exact() { Ok(graph) } else { build(false) });
+    let graph = match graph {
+        Ok(v) => v,
+        Err(e) => return e.into_compile_error(),
+    };
+    let errors = match error_check(options, item, prelude, &graph, &rules, &variant_field_attrs) {
         Ok(v) => v,
         Err(e) => return  e.into_compile_error(),
     };
 
+    let subs: HashMap<_, _> = graph
+        .states()
+        .iter()
+        .flat_map(|s| s.calls())
+        .filter_map(|(call, _)| match &call.target {
+            CallTarget::Sub { prop, ty } => Some((prop, ty)),
+            CallTarget::Rule { .. } | CallTarget::Count { .. } => None,
+        })
+        .collect();
+
     let state_variants = graph
         .states()
         .iter()
         .enumerate()
-        .map(|(i, e)| generate_state_variant(options, item, &variant_field_attrs, i, e));
+        .map(|(i, e)| generate_state_variant(options, item, &variant_field_attrs, &subs, i, e));
+
+    // `set!` や `#[default]` を持つフィールドは、値がどこから来たかを実行時に記録する。
+    let mut slots: BTreeSet<MatchProp> = graph.assigns().into_iter().map(|a| a.prop.clone()).collect();
+    for (assoc, attrs) in variant_field_attrs.iter().enumerate() {
+        let defaults = attrs.iter().filter(|(_, a)| a.default().is_some());
+        slots.extend(defaults.map(|(field, _)| MatchProp { assoc, field: field.clone() }));
+    }
 
     let dead_state_variant = resolver.dead_state_variant_name(); 
-    let state_machine_impl = generate_state_machine_impl(options, item, &graph);
-    let default_impl = generate_default_impl(options, item, &graph);
+    let marker_state_variant = (!item.generics.params.is_empty()).then(|| {
+        let marker = resolver.marker_state_variant_name();
+        let phantom_data_type = resolver.phantom_data_type();
+        let infallible_type = resolver.infallible_type();
+        quote! { , #marker(#phantom_data_type<fn() -> #ident #ty_generics>, #infallible_type) }
+    });
+    let impl_where_clause = builder_where_clause(options, item, &graph, &variant_field_attrs);
+    let state_machine_impl = generate_state_machine_impl(options, item, &graph, &rules, &slots, &variant_field_attrs, &validates);
+    let default_impl = generate_default_impl(options, item, &graph, &slots);
+    let sub_pattern_impl = sub_pattern::generate_sub_pattern_impl(options, item, &graph, &rules, impl_where_clause.as_ref());
+
+    // 再帰的な宣言への委譲がある場合のみ、プッシュダウンオートマトンを持たせる。
+    let uses_rules = graph.states().iter().flat_map(|s| s.calls()).any(|(call, _)| matches!(call.target, CallTarget::Rule { .. }));
+    let (recursion, recursion_field) = if uses_rules {
+        let recursion_type_name = resolver.recursion_type_name(item);
+        let recursion = generate_recursion(options, item, &rules);
+        (recursion, quote! { , recursion: #recursion_type_name })
+    } else {
+        (quote! {}, quote! {})
+    };
+
+    // 列挙型に付けた `allow` で警告を抑えられるようにする。
+    let allows: Vec<_> = item.attrs.iter().filter(|a| a.path().is_ident("allow")).collect();
+    let warnings = warnings.iter().map(|w| quote! { #(#allows)* #w });
+
+    // 定数で数える箇所がある場合のみ、数えた回数を持たせる。
+    let counter_field = uses_counts(&graph).then(|| quote! { , counter: usize });
+    let slot_fields = slots.iter().map(|prop| resolver.slot_field_name(prop));
 
     quote! {
         #errors
+        #(#warnings)*
 
-        impl ::regen::__internal_macro::Parse<#base_type> for #ident {
+        impl #impl_generics ::regen::__internal_macro::Parse<#base_type> for #ident #ty_generics #impl_where_clause {
             type Error = #match_error_type<#error_type>;
-            type StateMachine = #state_machine_name;
+            type StateMachine = #state_machine_name #ty_generics;
         }
 
+        #sub_pattern_impl
+
         #[doc(hidden)]
         #[allow(non_camel_case_types)]
-        #vis struct #state_machine_name {
-            state: #state_machine_state_name
+        #vis struct #state_machine_name #impl_generics #where_clause {
+            state: #state_machine_state_name #ty_generics
+            #recursion_field
+            #counter_field
+            #(, #slot_fields: usize)*
         }
 
         #[doc(hidden)]
         #[allow(non_camel_case_types)]
-        #vis enum #state_machine_state_name {
+        #vis enum #state_machine_state_name #impl_generics #where_clause {
             #(#state_variants,)*
             #dead_state_variant
+            #marker_state_variant
         }
 
+        #recursion
+
         #default_impl
 
         #state_machine_impl
@@ -90,7 +186,9 @@ fn error_check<T: PatternChar>(
     options: &RegenOptions,
     item: &syn::ItemEnum,
     _prelude: RegenPrelude<T>,
-    graph: &MatchGraph<T>
+    graph: &MatchGraph<T>,
+    rules: &Rules<T>,
+    field_attrs: &[HashMap<String, FieldAttribute>],
     ) -> syn::Result<TokenStream> {
         let errors = (!options.allow_conflict()).then(|| {
             let conflictions: Vec<_> = graph
@@ -130,17 +228,179 @@ fn error_check<T: PatternChar>(
             }
         }).into_iter();
 
+    // 委譲を含むステートは、委譲先に渡すか他の遷移に進むかを文字から決められない。
+    let graphs = std::iter::once(graph).chain(rules.graphs());
+    let call_errors = graphs.flat_map(|g| g.states()).filter_map(|state| {
+        let (call, _) = state.calls().first()?;
+        let has_branch = state.branches().iter().any(|(_, _, t)| t.is_some()) || !state.guards().is_empty();
+        if state.calls().len() == 1 && !has_branch && state.assoc().is_empty() {
+            return None;
+        }
+
+        let message = match &call.target {
+            CallTarget::Sub { .. } => "`sub!` cannot be used where another pattern can also match.".to_string(),
+            CallTarget::Rule { name, .. } => format!("`{name}` cannot be used where another pattern can also match."),
+            CallTarget::Count { .. } => "Patterns counted by constants cannot be used where another pattern can also match.".to_string(),
+        };
+        let error = syn::Error::new(call.span, message);
+        Some(error.into_compile_error())
+    });
+
+    // 定数で数える繰り返しは数える要素を上限まで貪欲に消費するため、続くパターンが同じ要素で始まると展開した場合と異なる。
+    let count_errors = graph.states().iter().filter_map(|state| {
+        let (call, ret) = state.calls().first()?;
+        if !graph.count_overlaps_return(call, *ret) {
+            return None;
+        }
+
+        let message = "Patterns counted by constants cannot be followed by a pattern that can also match the counted elements.";
+        Some(syn::Error::new(call.span, message).into_compile_error())
+    });
+
+    // 端点に定数を含む範囲は、同じステートの他の遷移と重ならないことを定数の評価で確かめる。
+    // 列挙型の引数を参照する範囲は、列挙型の外で評価できないため確かめない。
+    let params: Vec<_> = item.generics.params.iter().filter_map(|p| match p {
+        syn::GenericParam::Type(p) => Some(&p.ident),
+        syn::GenericParam::Const(p) => Some(&p.ident),
+        syn::GenericParam::Lifetime(_) => None,
+    }).collect();
+    let mut checked = BTreeSet::new();
+    let guard_checks = graph.states().iter().flat_map(|state| {
+        let guards = state.guards();
+        guards.iter().enumerate().flat_map(move |(i, (guard, _))| {
+            let intervals = state.branches().iter().filter(|(_, _, t)| t.is_some()).map(|(start, end, _)| {
+                let lower = start.map(|s| quote!(#s));
+                let upper = end.map(|e| (quote!(#e), false));
+                (lower, upper)
+            });
+            let others = guards[i + 1..].iter().map(|(other, _)| range_bounds(graph.guard(*other)));
+            intervals.chain(others).map(move |other| (*guard, other))
+        })
+    }).filter(|(guard, _)| !mentions_any(graph.guard(*guard).to_token_stream(), &params))
+    .filter(|(guard, (lower, upper))| {
+        let upper = upper.as_ref().map(|(u, inclusive)| (u.to_string(), *inclusive));
+        checked.insert((*guard, lower.as_ref().map(ToString::to_string), upper))
+    })
+    .map(|(guard, (lower, upper))| {
+        let range = graph.guard(guard);
+        let (guard_lower, guard_upper) = range_bounds(range);
+        let before = precedes(&guard_upper, &lower);
+        let after = precedes(&upper, &guard_lower);
+        let message = format!("`{}` overlaps another pattern that can also match.", range.to_token_stream()).replace('{', "{{").replace('}', "}}");
+        quote_spanned! {range.span()=>
+            const _: () = ::core::assert!(#before || #after, #message);
+        }
+    }).collect::<Vec<_>>();
+
+    // 再帰的な宣言は読み進められる限り読み進めるため、続くパターンが同じ要素で始まると宣言を終えられない。
+    let graphs = std::iter::once(graph).chain(rules.graphs());
+    let rule_errors = graphs.flat_map(|g| g.states().iter().flat_map(|s| s.calls()).map(move |c| (g, c))).filter_map(|(g, (call, ret))| {
+        let CallTarget::Rule { index, name } = &call.target else {
+            return None;
+        };
+        if !g.reads_any(*ret, &rules.continuations(*index)) {
+            return None;
+        }
+
+        let message = format!("`{name}` can continue on an element that the pattern following it can also match.");
+        Some(syn::Error::new(call.span, message))
+    });
+    // 同じ委譲が複数のステートに含まれるため、重複を除く。
+    let mut reported = BTreeSet::new();
+    let rule_errors: Vec<_> = rule_errors.filter(|e| reported.insert(e.to_string())).map(|e| e.into_compile_error()).collect();
+
+    let continuation_checks = sub_pattern::generate_continuation_checks(options, item, graph, rules);
+
+    // 同じ経路で同じフィールドに異なる値を代入すると、どちらの値になるかが決まらない。
+    let assign_groups = graph.states().iter().flat_map(|s| {
+        let transitions = s.branches().iter().filter_map(|(_, _, t)| t).chain(s.guards().iter().map(|(_, t)| t));
+        transitions.map(|t| t.assigns()).chain([s.accept_assigns()])
+    });
+    let assign_errors = assign_groups.filter_map(|assigns| {
+        let (_, b) = assigns.iter().enumerate().flat_map(|(i, a)| assigns[i + 1..].iter().map(move |b| (a, b))).find(|(a, b)| a.prop == b.prop && a.index != b.index)?;
+        let message = format!("`set!` assigns different values to `{}` on the same path.", b.prop.field);
+        Some(syn::Error::new_spanned(graph.value(b), message).into_compile_error())
+    });
+
+    // `set!` で代入するフィールドは、受理する全ての経路で値があることを確かめる。
+    let assigned = must_assigned(graph);
+    let field_errors = graph.states().iter().enumerate().filter_map(|(i, state)| {
+        let &assoc = state.assoc().first()?;
+        let variant = &item.variants[assoc];
+        let errors: Vec<_> = variant.fields.iter().enumerate().filter_map(|(j, field)| {
+            let name = field.ident.as_ref().map(ToString::to_string).unwrap_or(j.to_string());
+            let prop = MatchProp { assoc, field: name };
+            let has_default = field_attrs[assoc].get(&prop.field).is_some_and(|a| a.default().is_some());
+            let is_assigned = graph.assigns().iter().any(|a| a.prop == prop);
+            if !is_assigned || has_default || state.props().contains(&prop) || assigned[i].contains(&prop) || state.accept_assigns().iter().any(|a| a.prop == prop) {
+                return None;
+            }
+
+            let message = format!("Field `{}` is not assigned on every path. `#[default = ...]` is required.", prop.field);
+            Some(syn::Error::new_spanned(field, message))
+        }).collect();
+
+        Some(errors)
+    }).flatten();
+    // 複数の受理状態で同じフィールドが報告されるため、重複を除く。
+    let mut reported = BTreeSet::new();
+    let field_errors = field_errors.filter(|e| reported.insert(e.to_string())).map(|e| e.into_compile_error());
+
     Ok(quote! {
         #(#errors)*
+        #(#call_errors)*
+        #(#count_errors)*
+        #(#guard_checks)*
+        #(#rule_errors)*
+        #(#continuation_checks)*
+        #(#assign_errors)*
+        #(#field_errors)*
     })
 }
 
+// 各ステートに到達する全ての経路で `set!` による代入が済んでいるフィールド。
+fn must_assigned<T: PatternChar>(graph: &MatchGraph<T>) -> Vec<BTreeSet<MatchProp>> {
+    let states = graph.states();
+    let mut assigned: Vec<Option<BTreeSet<MatchProp>>> = vec![None; states.len()];
+    assigned[0] = Some(BTreeSet::new());
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (i, state) in states.iter().enumerate() {
+            let Some(current) = assigned[i].clone() else {
+                continue;
+            };
+
+            let transitions = state.branches().iter().filter_map(|(_, _, t)| t).chain(state.guards().iter().map(|(_, t)| t));
+            let transitions = transitions.map(|t| (t.state(), t.assigns()));
+            let calls = state.calls().iter().map(|(_, ret)| (*ret, &[][..]));
+            for (dst, assigns) in transitions.chain(calls) {
+                let mut incoming = current.clone();
+                incoming.extend(assigns.iter().map(|a| a.prop.clone()));
+
+                let next = match &assigned[dst] {
+                    Some(v) => v.intersection(&incoming).cloned().collect(),
+                    None => incoming,
+                };
+                if assigned[dst].as_ref() != Some(&next) {
+                    assigned[dst] = Some(next);
+                    changed = true;
+                }
+            }
+        }
+    }
+
+    assigned.into_iter().map(Option::unwrap_or_default).collect()
+}
+
 
 
 fn generate_state_variant<T: PatternChar>(
     options: &RegenOptions,
     item: &syn::ItemEnum,
     field_attrs: &[HashMap<String, FieldAttribute>],
+    subs: &HashMap<&MatchProp, &syn::Path>,
     state_index: usize,
     state: &MatchState<T>,
 ) -> TokenStream {
@@ -148,7 +408,7 @@ fn generate_state_variant<T: PatternChar>(
     let fields = state.props().iter().map(|prop| {
         let field_name = resolver.state_field_name(prop);
         let attrs = field_attrs[prop.assoc].get(&prop.field).unwrap();
-        let ty = resolver.state_field_type(item, attrs, prop);
+        let ty = resolver.state_field_type(item, attrs, prop, subs.get(prop).copied());
 
         quote! {
             #field_name : #ty
@@ -169,7 +429,8 @@ fn generate_state_variant<T: PatternChar>(
 fn generate_default_impl<T: PatternChar>(
     options: &RegenOptions,
     item: &syn::ItemEnum, 
-    graph: &MatchGraph<T>
+    graph: &MatchGraph<T>,
+    slots: &BTreeSet<MatchProp>,
 ) -> TokenStream {
     let resolver = options.resolver();
     let default_trait = resolver.default_trait();
@@ -185,13 +446,26 @@ fn generate_default_impl<T: PatternChar>(
         }
     });
 
+    let uses_rules = graph.states().iter().flat_map(|s| s.calls()).any(|(call, _)| matches!(call.target, CallTarget::Rule { .. }));
+    let recursion_init = uses_rules.then(|| {
+        let recursion_type_name = resolver.recursion_type_name(item);
+        quote! { , recursion: #recursion_type_name::new() }
+    });
+    let counter_init = uses_counts(graph).then(|| quote! { , counter: 0 });
+    let slot_fields = slots.iter().map(|prop| resolver.slot_field_name(prop));
+
+    let (impl_generics, ty_generics, where_clause) = item.generics.split_for_impl();
+
     quote! {
-        impl #default_trait for #state_machine_name {
+        impl #impl_generics #default_trait for #state_machine_name #ty_generics #where_clause {
             fn default() -> Self {
                 Self {
                     state: #state_machine_state_name::#initial_state_variant { 
                         #(#field_inits),*
                     }
+                    #recursion_init
+                    #counter_init
+                    #(, #slot_fields: 0)*
                 }
             }
         }
@@ -202,6 +476,10 @@ fn generate_state_machine_impl<T: PatternChar>(
     options: &RegenOptions,
     item: &syn::ItemEnum,
     graph: &MatchGraph<T>,
+    rules: &Rules<T>,
+    slots: &BTreeSet<MatchProp>,
+    field_attrs: &[HashMap<String, FieldAttribute>],
+    validates: &[Option<syn::Path>],
 ) -> TokenStream {
     let resolver = options.resolver();
     let base_type = resolver.base_type();
@@ -211,13 +489,19 @@ fn generate_state_machine_impl<T: PatternChar>(
     let item_name = &item.ident;
     let state_machine_name = resolver.state_machine_type_name(item);
 
-    let advance_impl = generate_advance_impl(options, item, graph);
+    let advance_impl = match options.codegen() {
+        Codegen::Match => generate_advance_impl(options, item, graph, rules, slots),
+        Codegen::Table => table::generate_table_advance_impl(options, item, graph, rules, slots),
+    };
     let complete_impl = generate_complete_impl(options, item, graph);
-    let current_impl = generate_current_impl(options, item, graph);
+    let current_impl = generate_current_impl(options, item, graph, slots, field_attrs, validates);
+
+    let (impl_generics, ty_generics, _) = item.generics.split_for_impl();
+    let where_clause = builder_where_clause(options, item, graph, field_attrs);
 
     quote! {
-        impl #state_machine_trait<#base_type> for #state_machine_name {
-            type Output = #item_name;
+        impl #impl_generics #state_machine_trait<#base_type> for #state_machine_name #ty_generics #where_clause {
+            type Output = #item_name #ty_generics;
             type Error = #match_error_type<#error_type>;
 
             #advance_impl
@@ -231,18 +515,23 @@ fn generate_advance_impl<T: PatternChar>(
     options: &RegenOptions,
     item: &syn::ItemEnum, 
     graph: &MatchGraph<T>,
+    rules: &Rules<T>,
+    slots: &BTreeSet<MatchProp>,
 ) -> TokenStream {
     let resolver = options.resolver(); 
+    let marker_arm = marker_arm(options, item, false);
     let base_type = resolver.base_type(); 
-    let from_char_seq_builder_trait = resolver.from_char_seq_builder_trait();
     let advance_result_type = resolver.advance_result_type(); 
-    let default_trait = resolver.default_trait();
     let dead_state = resolver.dead_state_variant_name();
     let state_machine_state_name = resolver.state_machine_state_type_name(item);
     let replace_fn = resolver.replace_fn();
+    let classes = ElementClasses::new(graph);
+
+    // 委譲を含まないステートがある場合のみ、要素の分類を使う。
+    let uses_classes = graph.states().iter().any(|s| s.calls().is_empty());
+    let class_lookup = uses_classes.then(|| lookup::generate_class_lookup(options, &classes));
 
     let states = graph.states();
-    let state_type_name = resolver.state_machine_state_type_name(item);
     let state_branches = states.iter().enumerate().map(|(state_index, state)| {
         let variant = resolver.state_variant_name(state_index);
         let fields = state.props().iter().map(|e|{
@@ -252,72 +541,51 @@ fn generate_advance_impl<T: PatternChar>(
             }
         });
 
-        let branches = state
-            .branches()
-            .iter()
-            .filter_map(|(s, e, t)| t.map(|t| (s, e, t)))
-            .map(|(start, end, dst_state_index)| {
-                let dst_state = &states[dst_state_index];
-                let dst_state_name = resolver.state_variant_name(dst_state_index);
-
-                let introduced_fields_init = dst_state.props().iter().filter(|p| !state.props().contains(p)).map(|prop| {
-                    let field = resolver.state_field_name(prop);
-                    quote! {
-                        let mut #field = #default_trait::default(); 
-                    }
-                });
-
-                // 一度collectの対象になった後、再びcollectの対象になった場合（前のステートでcollect対象ではなく、なおかつpropに存在する場合）にbuilderをリセットする。
-                let re_collect_fields_init = dst_state.collects().iter().filter(|p| state.props().contains(p) && !state.collects().contains(p)).map(|prop| {
-                    let field = resolver.state_field_name(prop);
-                    quote! {
-                        let mut #field = #default_trait::default(); 
-                    }
-                });
-
-                let fields = dst_state.props().iter().map(|prop| resolver.state_field_name(prop));
-
-                let updates = dst_state.collects().iter().map(|prop| {
-                    let field = resolver.state_field_name(prop);
-                    quote! { 
-                        <_ as #from_char_seq_builder_trait<#base_type>>::append(&mut #field, c);
-                    }
-                });
+        // 同じ遷移をする要素の分類をまとめて一つの分岐にする。
+        let mut groups: Vec<(BTreeSet<usize>, &Transition)> = Vec::new();
+        for (start, end, transition) in state.branches().iter().filter_map(|(s, e, t)| t.map(|t| (s, e, t))) {
+            let group = groups.iter_mut().find(|(_, t)| {
+                t.state() == transition.state() && t.begins() == transition.begins() && t.assigns() == transition.assigns()
+            });
+            match group {
+                Some((set, _)) => set.extend(classes.classes_in(start, end)),
+                None => groups.push((classes.classes_in(start, end), transition)),
+            }
+        }
 
-                let result = match dst_state.assoc().first() {
-                    Some(_) => {
-                        quote! { 
-                            #advance_result_type::Match(1)
-                        }
-                    },
-                    None => {
-                        quote! {
-                            #advance_result_type::Partial(1)
-                        }
-                    }
-                };
+        let branches = groups.into_iter().map(|(set, transition)| {
+            let set = set.into_iter().map(lookup::literal);
+            let transition = generate_transition(options, item, graph, rules, slots, state, transition);
 
-                quote! {
-                    #start..#end => {
-                        #(#introduced_fields_init)*
-
-                        #(#re_collect_fields_init)*
+            quote! {
+                #(#set)|* => {
+                    #transition
+                }
+            }
+        });
 
-                        #(#updates)*
+        // 端点に定数を含む範囲は他の遷移と重ならないため、どの分類にも当てはまらなかった要素だけを比べる。
+        let guards = state.guards().iter().map(|(guard, transition)| {
+            let test = guard_test(graph.guard(*guard));
+            let transition = generate_transition(options, item, graph, rules, slots, state, transition);
 
-                        self.state = #state_type_name::#dst_state_name {
-                            #(#fields),*
-                        };
- 
-                        #result
-                    }
+            quote! {
+                _ if #test => {
+                    #transition
                 }
-            });
+            }
+        });
+
+        if let Some((call, ret_index)) = state.calls().first() {
+            return generate_call_branch(options, item, graph, state_index, call, *ret_index, slots);
+        }
 
         quote! {
             #state_machine_state_name::#variant { #(#fields),* } => {
-                match c {
+                match class {
                     #(#branches)*
+                    #(#guards)*
+                    #[allow(unreachable_patterns)]
                     _ => {
                         #advance_result_type::Error
                     }
@@ -328,23 +596,521 @@ fn generate_advance_impl<T: PatternChar>(
 
     quote! {
         fn advance(&mut self, c: #base_type) -> #advance_result_type {
+            #class_lookup
             let state = #replace_fn(&mut self.state, #state_machine_state_name::#dead_state);
             match state {
                 #(#state_branches),*
                 #state_machine_state_name::#dead_state => {
                     #advance_result_type::Error
                 }
+                #marker_arm
+            }
+        }
+    }
+}
+
+// 遷移で行う処理。遷移元のステートのフィールドは、同じ名前の変数に取り出してあるものとする。
+fn generate_transition<T: PatternChar>(
+    options: &RegenOptions,
+    item: &syn::ItemEnum,
+    graph: &MatchGraph<T>,
+    rules: &Rules<T>,
+    slots: &BTreeSet<MatchProp>,
+    state: &MatchState<T>,
+    transition: &Transition,
+) -> TokenStream {
+    let resolver = options.resolver();
+    let base_type = resolver.base_type();
+    let from_char_seq_builder_trait = resolver.from_char_seq_builder_trait();
+    let advance_result_type = resolver.advance_result_type();
+    let default_trait = resolver.default_trait();
+    let state_type_name = resolver.state_machine_state_type_name(item);
+    let states = graph.states();
+
+    let dst_state_index = transition.state();
+    let dst_state = &states[dst_state_index];
+    let dst_state_name = resolver.state_variant_name(dst_state_index);
+
+    let introduced_fields_init = dst_state.props().iter().filter(|p| !state.props().contains(p)).map(|prop| {
+        let field = resolver.state_field_name(prop);
+        quote! {
+            let mut #field = #default_trait::default(); 
+        }
+    });
+
+    // 一度collectの対象になった後、再びcollectの対象になった場合（前のステートでcollect対象ではなく、なおかつpropに存在する場合）にbuilderに伝える。
+    let re_collects = dst_state.collects().iter().filter(|p| state.props().contains(p) && !state.collects().contains(p)).map(|prop| {
+        let field = resolver.state_field_name(prop);
+        quote! {
+            <_ as #from_char_seq_builder_trait<#base_type>>::restart(&mut #field);
+        }
+    });
+
+    // collectの入口を通過した場合、そのフィールドのビルダーに新しい要素の開始を伝える。
+    let begins = transition.begins().iter().map(|prop| {
+        let field = resolver.state_field_name(prop);
+        let slot = slots.contains(prop).then(|| {
+            let slot = resolver.slot_field_name(prop);
+            quote! { self.#slot = 1; }
+        });
+        quote! {
+            <_ as #from_char_seq_builder_trait<#base_type>>::start(&mut #field);
+            #slot
+        }
+    });
+
+    let assigns = transition.assigns().iter().map(|assign| {
+        let slot = resolver.slot_field_name(&assign.prop);
+        let code = assign.index + 2;
+        quote! { self.#slot = #code; }
+    });
+
+    let call_starts = generate_call_starts(options, dst_state, slots);
+
+    let fields = dst_state.props().iter().map(|prop| resolver.state_field_name(prop));
+
+    let updates = dst_state.collects().iter().map(|prop| {
+        let field = resolver.state_field_name(prop);
+        quote! { 
+            <_ as #from_char_seq_builder_trait<#base_type>>::append(&mut #field, c);
+        }
+    });
+
+    let result = match dst_state.assoc().first() {
+        Some(_) => {
+            quote! { 
+                #advance_result_type::Match(1)
+            }
+        },
+        None => {
+            quote! {
+                #advance_result_type::Partial(1)
+            }
+        }
+    };
+
+    // 再帰的な宣言への委譲は先頭の要素で始まるため、その要素をプッシュダウンオートマトンに渡す。
+    let (rule_start, result) = match dst_state.calls().first() {
+        Some((Call { target: CallTarget::Rule { index, .. }, .. }, ret_index)) => {
+            let initial = rules.state_id(*index, 0);
+            let rule_start = quote! {
+                self.recursion.start(#initial);
+                if let #advance_result_type::Error = self.recursion.advance(c) {
+                    return #advance_result_type::Error;
+                }
+            };
+            let result = match states[*ret_index].assoc().first() {
+                Some(_) => quote! {
+                    if self.recursion.is_matched() {
+                        #advance_result_type::Match(1)
+                    } else {
+                        #advance_result_type::Partial(1)
+                    }
+                },
+                None => quote! {
+                    #advance_result_type::Partial(1)
+                },
+            };
+            (rule_start, result)
+        }
+        // 定数で数える箇所は0回でもよい場合があるため、入った時点でマッチしうる。
+        Some((call @ Call { target: CallTarget::Count { .. }, .. }, ret_index)) if !states[*ret_index].assoc().is_empty() => {
+            let (_, is_matched) = call_exprs(options, call);
+            let result = quote! {
+                if #is_matched {
+                    #advance_result_type::Match(1)
+                } else {
+                    #advance_result_type::Partial(1)
+                }
+            };
+            (quote! {}, result)
+        }
+        _ => (quote! {}, result),
+    };
+
+    quote! {
+        #(#introduced_fields_init)*
+
+        #(#re_collects)*
+
+        #(#begins)*
+
+        #(#assigns)*
+
+        #call_starts
+
+        #(#updates)*
+
+        #rule_start
+
+        self.state = #state_type_name::#dst_state_name {
+            #(#fields),*
+        };
+
+        #result
+    }
+}
+
+// 委譲先に文字を渡す。委譲先がエラーを返した時点で委譲先がマッチしていれば、戻り先のステートで同じ文字を処理し直す。
+fn generate_call_branch<T: PatternChar>(
+    options: &RegenOptions,
+    item: &syn::ItemEnum,
+    graph: &MatchGraph<T>,
+    state_index: usize,
+    call: &Call,
+    ret_index: usize,
+    slots: &BTreeSet<MatchProp>,
+) -> TokenStream {
+    let resolver = options.resolver();
+    let base_type = resolver.base_type();
+    let from_char_seq_builder_trait = resolver.from_char_seq_builder_trait();
+    let advance_result_type = resolver.advance_result_type();
+    let default_trait = resolver.default_trait();
+    let state_type_name = resolver.state_machine_state_type_name(item);
+
+    let states = graph.states();
+    let state = &states[state_index];
+    let ret_state = &states[ret_index];
+    let variant = resolver.state_variant_name(state_index);
+    let ret_variant = resolver.state_variant_name(ret_index);
+    let (advance, is_matched) = call_exprs(options, call);
+
+    // 再帰的な宣言や定数で数える箇所が消費した要素は、委譲元でcollectの対象になっているフィールドにも加える。
+    let updates = match &call.target {
+        CallTarget::Sub { .. } => Vec::new(),
+        CallTarget::Count { collects, .. } => collects.iter().map(|prop| {
+            let field = resolver.state_field_name(prop);
+            quote! {
+                <_ as #from_char_seq_builder_trait<#base_type>>::append(&mut #field, c);
+            }
+        }).collect(),
+        CallTarget::Rule { .. } => state.collects().iter().map(|prop| {
+            let field = resolver.state_field_name(prop);
+            quote! {
+                <_ as #from_char_seq_builder_trait<#base_type>>::append(&mut #field, c);
+            }
+        }).collect(),
+    };
+
+    let fields: Vec<_> = state.props().iter().map(|prop| resolver.state_field_name(prop)).collect();
+    let ret_fields = ret_state.props().iter().map(|prop| resolver.state_field_name(prop));
+
+    let introduced_fields_init = ret_state.props().iter().filter(|p| !state.props().contains(p)).map(|prop| {
+        let field = resolver.state_field_name(prop);
+        quote! {
+            let mut #field = #default_trait::default();
+        }
+    });
+
+    let call_starts = generate_call_starts(options, ret_state, slots);
+
+    let result = match ret_state.assoc().first() {
+        Some(_) => quote! {
+            if #is_matched {
+                #advance_result_type::Match(1)
+            } else {
+                #advance_result_type::Partial(1)
+            }
+        },
+        None => quote! {
+            #advance_result_type::Partial(1)
+        },
+    };
+
+    quote! {
+        #state_type_name::#variant { #(mut #fields),* } => {
+            match #advance {
+                #advance_result_type::Error => {
+                    if !#is_matched {
+                        return #advance_result_type::Error;
+                    }
+
+                    #(#introduced_fields_init)*
+
+                    #call_starts
+
+                    self.state = #state_type_name::#ret_variant {
+                        #(#ret_fields),*
+                    };
+
+                    self.advance(c)
+                }
+                _ => {
+                    #(#updates)*
+
+                    let result = #result;
+                    self.state = #state_type_name::#variant {
+                        #(#fields),*
+                    };
+
+                    result
+                }
             }
         }
     }
 }
 
+// 委譲を含むステートに入るたびに、委譲先の状態機械を初期化する。
+fn generate_call_starts<T: PatternChar>(options: &RegenOptions, state: &MatchState<T>, slots: &BTreeSet<MatchProp>) -> TokenStream {
+    let resolver = options.resolver();
+    let base_type = resolver.base_type();
+    let from_char_seq_builder_trait = resolver.from_char_seq_builder_trait();
+    let start = |prop: &MatchProp, method: &str| {
+        let field = resolver.state_field_name(prop);
+        let method = format_ident!("{}", method);
+        let slot = slots.contains(prop).then(|| {
+            let slot = resolver.slot_field_name(prop);
+            quote! { self.#slot = 1; }
+        });
+        quote! {
+            <_ as #from_char_seq_builder_trait<#base_type>>::#method(&mut #field);
+            #slot
+        }
+    };
+    let starts = state.calls().iter().filter_map(|(call, _)| match &call.target {
+        // 委譲先の状態機械は入るたびに作り直す。
+        CallTarget::Sub { prop, .. } => Some(start(prop, "restart")),
+        CallTarget::Count { .. } => {
+            let begins = state.call_begins().iter().map(|prop| start(prop, "start"));
+            Some(quote! {
+                #(#begins)*
+                self.counter = 0;
+            })
+        }
+        CallTarget::Rule { .. } => None,
+    });
+
+    quote! {
+        #(#starts)*
+    }
+}
+
+// 委譲先に要素を渡す式と、委譲先がマッチしているかを調べる式。
+fn call_exprs(options: &RegenOptions, call: &Call) -> (TokenStream, TokenStream) {
+    let resolver = options.resolver();
+    let sub_builder_type = resolver.sub_builder_type();
+    let advance_result_type = resolver.advance_result_type();
+    match &call.target {
+        CallTarget::Sub { prop, .. } => {
+            let field = resolver.state_field_name(prop);
+            (
+                quote! { #sub_builder_type::advance(&mut #field, c) },
+                quote! { #sub_builder_type::is_matched(&#field) },
+            )
+        }
+        CallTarget::Rule { .. } => (
+            quote! { self.recursion.advance(c) },
+            quote! { self.recursion.is_matched() },
+        ),
+        // 回数はコンパイラに評価させるため、オーバーフローや空の範囲は定数の評価エラーになる。
+        CallTarget::Count { test, min, max, .. } => (
+            quote! {
+                {
+                    const MAX: usize = #max;
+                    const _: () = ::core::assert!(#min <= MAX, "Range must not be empty.");
+                    if self.counter < MAX && #test {
+                        self.counter += 1;
+                        #advance_result_type::Partial(1)
+                    } else {
+                        #advance_result_type::Error
+                    }
+                }
+            },
+            quote! {
+                {
+                    const MIN: usize = #min;
+                    self.counter >= MIN
+                }
+            },
+        ),
+    }
+}
+
+// 再帰的な宣言のDFAを一つの通し番号で並べたプッシュダウンオートマトン。
+// 委譲に入るときは戻り先をスタックに積み、受理状態で続けられなくなったら戻り先で同じ要素を処理し直す。
+fn generate_recursion<T: PatternChar>(
+    options: &RegenOptions,
+    item: &syn::ItemEnum,
+    rules: &Rules<T>,
+) -> TokenStream {
+    let resolver = options.resolver();
+    let base_type = resolver.base_type();
+    let advance_result_type = resolver.advance_result_type();
+    let vec_type = resolver.vec_type();
+    let recursion_type_name = resolver.recursion_type_name(item);
+    let max_depth = options.max_depth();
+    let vis = &item.vis;
+
+    let state_branches = rules.graphs().iter().enumerate().flat_map(|(rule_index, graph)| {
+        let states = graph.states();
+        let advance_result_type = &advance_result_type;
+        // 委譲を含むステートには留まらず、そこへの遷移で委譲先の初期状態に進む。
+        states.iter().enumerate().filter(|(_, s)| s.calls().is_empty()).map(move |(state_index, state)| {
+            let id = rules.state_id(rule_index, state_index);
+            let branches = state
+                .branches()
+                .iter()
+                .filter_map(|(s, e, t)| t.map(|t| (s, e, t)))
+                .map(|(start, end, transition)| {
+                    let range = range_pattern(start, end);
+                    let dst_state = &states[transition.state()];
+                    match dst_state.calls().first() {
+                        Some((Call { target: CallTarget::Rule { index, .. }, .. }, ret_index)) => {
+                            let initial = rules.state_id(*index, 0);
+                            let ret = rules.state_id(rule_index, *ret_index);
+                            let ret_accepted = !states[*ret_index].assoc().is_empty();
+                            let pending = (!ret_accepted).then(|| quote! {
+                                self.pending += 1;
+                            });
+                            quote! {
+                                #range => {
+                                    if self.stack.len() >= #max_depth {
+                                        return #advance_result_type::Error;
+                                    }
+                                    self.stack.push((#ret, #ret_accepted));
+                                    #pending
+                                    self.state = #initial;
+                                }
+                            }
+                        }
+                        _ => {
+                            let dst = rules.state_id(rule_index, transition.state());
+                            let matched = match dst_state.assoc().first() {
+                                Some(_) => quote! { self.pending == 0 },
+                                None => quote! { false },
+                            };
+                            quote! {
+                                #range => {
+                                    self.state = #dst;
+                                    self.matched = #matched;
+                                    return if self.matched {
+                                        #advance_result_type::Match(1)
+                                    } else {
+                                        #advance_result_type::Partial(1)
+                                    };
+                                }
+                            }
+                        }
+                    }
+                });
+
+            let fallback = match state.assoc().first() {
+                Some(_) => quote! {
+                    _ => {
+                        let Some((ret, accepted)) = self.stack.pop() else {
+                            return #advance_result_type::Error;
+                        };
+                        if !accepted {
+                            self.pending -= 1;
+                        }
+                        self.state = ret;
+                    }
+                },
+                None => quote! {
+                    _ => {
+                        return #advance_result_type::Error;
+                    }
+                },
+            };
+
+            quote! {
+                #id => match c {
+                    #(#branches)*
+                    #[allow(unreachable_patterns)]
+                    #fallback
+                }
+            }
+        })
+    });
+
+    quote! {
+        #[doc(hidden)]
+        #[allow(non_camel_case_types)]
+        #vis struct #recursion_type_name {
+            // 戻り先のステートと、それが受理状態かどうか。
+            stack: #vec_type<(usize, bool)>,
+            state: usize,
+            // スタックに積まれた戻り先のうち、受理状態でないものの数。
+            pending: usize,
+            matched: bool,
+        }
+
+        impl #recursion_type_name {
+            fn new() -> Self {
+                Self {
+                    stack: #vec_type::new(),
+                    state: 0,
+                    pending: 0,
+                    matched: false,
+                }
+            }
+
+            fn start(&mut self, state: usize) {
+                self.stack.clear();
+                self.state = state;
+                self.pending = 0;
+                self.matched = false;
+            }
+
+            fn is_matched(&self) -> bool {
+                self.matched
+            }
+
+            fn advance(&mut self, c: #base_type) -> #advance_result_type {
+                loop {
+                    match self.state {
+                        #(#state_branches)*
+                        _ => {
+                            return #advance_result_type::Error;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn uses_counts<T: PatternChar>(graph: &MatchGraph<T>) -> bool {
+    graph.states().iter().flat_map(|s| s.calls()).any(|(call, _)| matches!(call.target, CallTarget::Count { .. }))
+}
+
+// 要素 `c` が端点に定数を含む範囲に入るかどうかを調べる式。
+fn guard_test(range: &syn::ExprRange) -> TokenStream {
+    quote_spanned! {range.span()=>
+        (#range).contains(&c)
+    }
+}
+
+// 範囲の下端と、上端およびそれを含むかどうか。端がなければ None。
+fn range_bounds(range: &syn::ExprRange) -> (Option<TokenStream>, Option<(TokenStream, bool)>) {
+    let lower = range.start.as_ref().map(|e| quote!(#e));
+    let upper = range.end.as_ref().map(|e| (quote!(#e), matches!(range.limits, syn::RangeLimits::Closed(_))));
+    (lower, upper)
+}
+
+// 上端が `upper` の範囲の要素が、全て下端が `lower` の範囲の要素より小さいことを表す定数式。
+fn precedes(upper: &Option<(TokenStream, bool)>, lower: &Option<TokenStream>) -> TokenStream {
+    match (upper, lower) {
+        (Some((upper, true)), Some(lower)) => quote!((#upper) < (#lower)),
+        (Some((upper, false)), Some(lower)) => quote!((#upper) <= (#lower)),
+        _ => quote!(false),
+    }
+}
+
+fn range_pattern<T: PatternChar>(start: Option<&T>, end: Option<&T>) -> TokenStream {
+    match (start, end) {
+        (None, None) => quote!(_),
+        (start, end) => quote!(#start..#end),
+    }
+}
+
 fn generate_complete_impl<T: PatternChar>(
     options: &RegenOptions,
     item: &syn::ItemEnum,
     graph: &MatchGraph<T>,
 ) -> TokenStream {
     let resolver = options.resolver();
+    let marker_arm = marker_arm(options, item, false);
     let complete_result_type = resolver.complete_result_type(); 
     let dead_state = resolver.dead_state_variant_name();
     let state_machine_state_name = resolver.state_machine_state_type_name(item);
@@ -361,7 +1127,23 @@ fn generate_complete_impl<T: PatternChar>(
             }
         });
 
-        let result = match state.assoc().first() {
+        let result = match state.calls().first() {
+            Some((call, ret_index)) => {
+                let (_, is_matched) = call_exprs(options, call);
+                match states[*ret_index].assoc().first() {
+                    Some(_) => quote! {
+                        if #is_matched {
+                            #complete_result_type::Match(0)
+                        } else {
+                            #complete_result_type::Error
+                        }
+                    },
+                    None => quote! {
+                        #complete_result_type::Error
+                    },
+                }
+            }
+            None => match state.assoc().first() {
             Some(_) => {
                 quote! { 
                     #complete_result_type::Match(0)
@@ -372,6 +1154,7 @@ fn generate_complete_impl<T: PatternChar>(
                     #complete_result_type::Error
                 }
             }
+            },
         };
 
         quote! {
@@ -390,6 +1173,7 @@ fn generate_complete_impl<T: PatternChar>(
                 #state_machine_state_name::#dead_state => {
                     #complete_result_type::Error
                 }
+                #marker_arm
             }
         }
     }
@@ -399,8 +1183,14 @@ fn generate_current_impl<T: PatternChar>(
     options: &RegenOptions,
     item: &syn::ItemEnum,
     graph: &MatchGraph<T>,
+    slots: &BTreeSet<MatchProp>,
+    field_attrs: &[HashMap<String, FieldAttribute>],
+    validates: &[Option<syn::Path>],
 ) -> TokenStream {
     let resolver = options.resolver();  
+    let marker_arm = marker_arm(options, item, true);
+    let validation_trait = resolver.validation_trait();
+    let error_type = options.error_type();
     let match_error_type = resolver.match_error_type();
     let from_char_seq_builder_trait = resolver.from_char_seq_builder_trait(); 
     let dead_state = resolver.dead_state_variant_name();
@@ -414,30 +1204,99 @@ fn generate_current_impl<T: PatternChar>(
     let state_branches = states.iter().enumerate().map(|(state_index, state)| {
         let variant = resolver.state_variant_name(state_index);
         let fields  = state.props().iter().map(|e| resolver.state_field_name(e));
+
+        // 委譲中のステートは、委譲先がマッチしていれば戻り先のステートとして扱う。
+        let (accept_state, guard) = match state.calls().first() {
+            Some((call, ret_index)) => {
+                let (_, is_matched) = call_exprs(options, call);
+                let guard = quote! {
+                    if !#is_matched {
+                        return #result_type::Err(#match_error_type::NotMatched);
+                    }
+                };
+                (&states[*ret_index], Some(guard))
+            }
+            None => (state, None),
+        };
         
-        let result = match state.assoc().first() {
+        let result = match accept_state.assoc().first() {
             Some(&assoc) => {
                 let variant = &item.variants[assoc].ident;
-                let declares = state.props().iter().filter(|p| p.assoc == assoc).map(|prop| {
-                    let field = format_ident!("{}", &prop.field);
-                    let state_field = resolver.state_field_name(prop);
-                    
-                    quote! {
-                        let #field = <_ as #from_char_seq_builder_trait<#base_type>>::build(#state_field).map_err(|e| {
-                            #match_error_type::Collect(<_ as #into_trait<_>>::into(e))
-                        })?;
+                let assigns = graph.assigns();
+
+                // タプルのフィールドは `0`, `1` と名付けているため、`Variant { 0: _, 1: _ }` の形で構築する。
+                let fields = item.variants[assoc].fields.iter().enumerate().filter_map(|(i, field)| {
+                    let name = field.ident.as_ref().map(ToString::to_string).unwrap_or(i.to_string());
+                    let prop = MatchProp { assoc, field: name };
+                    let member = field_member(&prop.field);
+
+                    // 受理するまでに通過した `set!` が最も後に行われた代入になる。
+                    if let Some(assign) = accept_state.accept_assigns().iter().find(|a| a.prop == prop) {
+                        let value = graph.value(assign);
+                        return Some(quote! { #member: #value });
                     }
-                });
 
-                let fields = state.props().iter().filter(|p| p.assoc == assoc).map(|p| format_ident!("{}", &p.field));
+                    let attrs = field_attrs[assoc].get(&prop.field);
+                    let build = state.props().contains(&prop).then(|| {
+                        let state_field = resolver.state_field_name(&prop);
+                        let built = quote! {
+                            <_ as #from_char_seq_builder_trait<#base_type>>::build(#state_field).map_err(|e| {
+                                #match_error_type::Collect(<_ as #into_trait<_>>::into(e))
+                            })?
+                        };
+                        match attrs.and_then(|a| a.map()) {
+                            Some(map) => quote! { #map(#built) },
+                            None => built,
+                        }
+                    });
+                    let default = attrs.and_then(|a| a.default()).map(|e| quote! { #e });
 
-                quote! {
-                    #(#declares)*
-                    #result_type::Ok(
-                        #item_name::#variant {
-                            #(#fields),*
+                    if !slots.contains(&prop) {
+                        let value = build.or(default)?;
+                        return Some(quote! { #member: #value });
+                    }
+
+                    let slot = resolver.slot_field_name(&prop);
+                    let values = assigns.iter().filter(|a| a.prop == prop).map(|a| {
+                        let code = a.index + 2;
+                        let value = graph.value(a);
+                        quote! { #code => #value, }
+                    });
+                    let collected = build.as_ref().map(|build| quote! { 1 => #build, });
+                    // 値がない経路は `error_check` で拒否しているため、ここには到達しない。
+                    let fallback = default.or(build).unwrap_or_else(|| quote! { ::core::unreachable!() });
+
+                    Some(quote! {
+                        #member: match self.#slot {
+                            #(#values)*
+                            #collected
+                            _ => #fallback,
                         }
-                    )
+                    })
+                });
+
+                let constructor = if options.is_struct() {
+                    quote! { #item_name }
+                } else {
+                    quote! { #item_name::#variant }
+                };
+                let value = quote! {
+                    #constructor {
+                        #(#fields),*
+                    }
+                };
+
+                match &validates[assoc] {
+                    Some(validate) => quote! {
+                        #guard
+                        let value = #value;
+                        <_ as #validation_trait<#error_type>>::into_result(#validate(&value))?;
+                        #result_type::Ok(value)
+                    },
+                    None => quote! {
+                        #guard
+                        #result_type::Ok(#value)
+                    },
                 }
             },
             None => {
@@ -462,7 +1321,84 @@ fn generate_current_impl<T: PatternChar>(
                 #state_machine_state_name::#dead_state => {
                     #result_type::Err(#match_error_type::NotMatched)
                 }
+                #marker_arm
             }
         }
     }
-}
\ No newline at end of file
+}
+
+// 構築されない状態への分岐。列挙型に型引数がある場合のみ必要になる。
+fn marker_arm(options: &RegenOptions, item: &syn::ItemEnum, by_ref: bool) -> Option<TokenStream> {
+    let resolver = options.resolver();
+    let state_machine_state_name = resolver.state_machine_state_type_name(item);
+    let marker = resolver.marker_state_variant_name();
+    let never = if by_ref { quote!(*never) } else { quote!(never) };
+
+    (!item.generics.params.is_empty()).then(|| quote! {
+        #state_machine_state_name::#marker(_, never) => match #never {}
+    })
+}
+
+// 型引数を含むフィールドについて、ビルダーが組み立てる型がフィールドの型と一致することを実装の条件に加える。
+fn builder_where_clause<T: PatternChar>(
+    options: &RegenOptions,
+    item: &syn::ItemEnum,
+    graph: &MatchGraph<T>,
+    field_attrs: &[HashMap<String, FieldAttribute>],
+) -> Option<syn::WhereClause> {
+    let params: Vec<_> = item.generics.type_params().map(|p| &p.ident).collect();
+    if params.is_empty() {
+        return item.generics.where_clause.clone();
+    }
+
+    let resolver = options.resolver();
+    let base_type = resolver.base_type();
+    let from_char_seq_trait = resolver.from_char_seq_trait();
+    let from_char_seq_builder_trait = resolver.from_char_seq_builder_trait();
+
+    let mut generics = item.generics.clone();
+    let where_clause = generics.make_where_clause();
+    let is_sub = |prop: &MatchProp| {
+        let mut calls = graph.states().iter().flat_map(|s| s.calls());
+        calls.any(|(call, _)| matches!(&call.target, CallTarget::Sub { prop: p, .. } if p == prop))
+    };
+    let props: BTreeSet<_> = graph.states().iter().flat_map(|s| s.props()).collect();
+    for prop in props {
+        let attrs = &field_attrs[prop.assoc][&prop.field];
+        if attrs.builder().is_some() || attrs.map().is_some() || is_sub(prop) {
+            continue;
+        }
+
+        let field = item.variants[prop.assoc].fields.iter().enumerate().find(|(i, field)| {
+            field.ident.as_ref().map(ToString::to_string).unwrap_or(i.to_string()) == prop.field
+        });
+        let Some((_, field)) = field else {
+            continue;
+        };
+        let ty = &field.ty;
+        if !mentions_any(quote!(#ty), &params) {
+            continue;
+        }
+
+        where_clause.predicates.push(syn::parse_quote! {
+            <#ty as #from_char_seq_trait<#base_type>>::Builder: #from_char_seq_builder_trait<#base_type, Type = #ty>
+        });
+    }
+
+    generics.where_clause
+}
+
+fn mentions_any(tokens: TokenStream, idents: &[&syn::Ident]) -> bool {
+    tokens.into_iter().any(|t| match t {
+        proc_macro2::TokenTree::Ident(ident) => idents.contains(&&ident),
+        proc_macro2::TokenTree::Group(group) => mentions_any(group.stream(), idents),
+        _ => false,
+    })
+}
+
+fn field_member(field: &str) -> syn::Member {
+    match field.parse::<u32>() {
+        Ok(index) => syn::Member::Unnamed(syn::Index { index, span: Span::call_site() }),
+        Err(_) => syn::Member::Named(format_ident!("{}", field)),
+    }
+}
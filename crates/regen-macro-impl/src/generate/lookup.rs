@@ -0,0 +1,134 @@
+use std::collections::BTreeMap;
+
+use crate::{match_graph::ElementClasses, pattern_char::PatternChar, regen_options::RegenOptions};
+use proc_macro2::{Literal, TokenStream};
+use quote::quote;
+
+const BLOCK_BITS: u32 = 8;
+const BLOCK_SIZE: usize = 1 << BLOCK_BITS;
+
+// 範囲による分岐一つが、表の要素いくつ分の大きさになるかの目安。
+const ARM_COST: usize = 4;
+
+// 要素 `c` の分類を求め、`class` に束縛する文。
+// 番号の範囲が狭い型は表を引き、`u8` は一段、`char` や `u16` は256要素ごとのブロックを共有する二段の表にする。
+// 表が範囲による分岐より大きくなる場合は `c` の範囲で分岐する。それ以外の型は境界を二分探索する。
+pub(super) fn generate_class_lookup<T: PatternChar>(
+    options: &RegenOptions,
+    classes: &ElementClasses<T>,
+) -> TokenStream {
+    let base_type = options.resolver().base_type();
+    let class_type = index_type(classes.count());
+
+    let Some(max) = T::DENSE_MAX else {
+        let bounds = classes.intervals().filter_map(|(start, _, _)| start);
+        let cells = classes.intervals().map(|(_, _, class)| literal(class));
+        let bound_count = classes.intervals().count() - 1;
+        let class_count = bound_count + 1;
+        return quote! {
+            static BOUNDS: [#base_type; #bound_count] = [#(#bounds),*];
+            static CLASSES: [#class_type; #class_count] = [#(#cells),*];
+            let class = CLASSES[BOUNDS.partition_point(|b| *b <= c)] as usize;
+        };
+    };
+
+    let len = max as usize + 1;
+    let mut table = vec![0; len];
+    for (start, end, class) in classes.intervals() {
+        let start = start.map_or(0, |s| s.to_index() as usize);
+        let end = end.map_or(len, |e| e.to_index() as usize);
+        table[start..end].fill(class);
+    }
+
+    let arms = range_arms(classes);
+    let arms_cost = arms.len() * ARM_COST;
+
+    if len <= BLOCK_SIZE {
+        if arms_cost < len {
+            return range_lookup(arms);
+        }
+
+        let cells = table.iter().map(|&class| literal(class));
+        return quote! {
+            static CLASSES: [#class_type; #len] = [#(#cells),*];
+            let class = CLASSES[c as usize] as usize;
+        };
+    }
+
+    let mut ids = BTreeMap::new();
+    let mut blocks = Vec::new();
+    let indices: Vec<_> = table
+        .chunks(BLOCK_SIZE)
+        .map(|block| {
+            *ids.entry(block).or_insert_with(|| {
+                blocks.push(block);
+                blocks.len() - 1
+            })
+        })
+        .collect();
+
+    if arms_cost < indices.len() + blocks.len() * BLOCK_SIZE {
+        return range_lookup(arms);
+    }
+
+    let index_type = index_type(blocks.len());
+    let index_count = indices.len();
+    let block_count = blocks.len();
+    let indices = indices.into_iter().map(literal);
+    let blocks = blocks.into_iter().map(|block| {
+        // 最後のブロックは番号の最大値で終わるため、残りを埋めて揃える。
+        let cells = (0..BLOCK_SIZE).map(|i| literal(block.get(i).copied().unwrap_or(0)));
+        quote! { [#(#cells),*] }
+    });
+    let mask = BLOCK_SIZE - 1;
+
+    quote! {
+        static BLOCKS: [#index_type; #index_count] = [#(#indices),*];
+        static CLASSES: [[#class_type; #BLOCK_SIZE]; #block_count] = [#(#blocks),*];
+        let element = c as usize;
+        let class = CLASSES[BLOCKS[element >> #BLOCK_BITS] as usize][element & #mask] as usize;
+    }
+}
+
+// 隣り合う同じ分類の区間をまとめた、分類ごとの要素の範囲。
+fn range_arms<T: PatternChar>(classes: &ElementClasses<T>) -> Vec<(Option<&T>, Option<&T>, usize)> {
+    let mut arms: Vec<(Option<&T>, Option<&T>, usize)> = Vec::new();
+    for (start, end, class) in classes.intervals() {
+        match arms.last_mut() {
+            Some((_, last_end, last)) if *last == class => *last_end = end,
+            _ => arms.push((start, end, class)),
+        }
+    }
+    arms
+}
+
+fn range_lookup<T: PatternChar>(arms: Vec<(Option<&T>, Option<&T>, usize)>) -> TokenStream {
+    let arms = arms.into_iter().map(|(start, end, class)| {
+        let class = literal(class);
+        match (start, end) {
+            (None, None) => quote! { _ => #class, },
+            (Some(start), None) => quote! { #start.. => #class, },
+            (None, Some(end)) => quote! { ..#end => #class, },
+            (Some(start), Some(end)) => quote! { #start..#end => #class, },
+        }
+    });
+
+    quote! {
+        let class: usize = match c {
+            #(#arms)*
+        };
+    }
+}
+
+// `count` 個の番号を表せる最も小さい整数型。
+pub(super) fn index_type(count: usize) -> TokenStream {
+    match count {
+        0..=0x100 => quote!(u8),
+        0x101..=0x10000 => quote!(u16),
+        _ => quote!(u32),
+    }
+}
+
+pub(super) fn literal(n: usize) -> Literal {
+    Literal::usize_unsuffixed(n)
+}
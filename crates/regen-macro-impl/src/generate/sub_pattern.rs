@@ -0,0 +1,273 @@
+use std::collections::BTreeSet;
+
+use super::mentions_any;
+use crate::{
+    match_graph::{CallTarget, MatchGraph, Rules},
+    pattern_char::PatternChar,
+    regen_options::RegenOptions,
+};
+use proc_macro2::TokenStream;
+use quote::{ToTokens, quote, quote_spanned};
+use syn::spanned::Spanned;
+
+// `sub!` で参照されたときに、委譲する側が曖昧さを検査するための `SubPattern` の実装。
+pub(super) fn generate_sub_pattern_impl<T: PatternChar>(
+    options: &RegenOptions,
+    item: &syn::ItemEnum,
+    graph: &MatchGraph<T>,
+    rules: &Rules<T>,
+    where_clause: Option<&syn::WhereClause>,
+) -> TokenStream {
+    let resolver = options.resolver();
+    let base_type = resolver.base_type();
+    let sub_pattern_trait = resolver.sub_pattern_trait();
+    let element_set_type = resolver.element_set_type();
+    let ident = &item.ident;
+    let (impl_generics, ty_generics, _) = item.generics.split_for_impl();
+
+    let first = first_set(options, graph, rules, 0, &mut BTreeSet::new());
+    let nullable = nullable(options, graph, 0, &mut BTreeSet::new());
+
+    // 受理しているステートから読める要素。委譲を含むステートは、戻り先が受理するときに委譲先と戻り先の両方から読める。
+    let mut intervals = Vec::new();
+    let mut continuations = Vec::new();
+    for state in graph.states() {
+        let Some((call, ret)) = state.calls().first() else {
+            if !state.assoc().is_empty() {
+                let branches = state.branches().iter().filter(|(_, _, t)| t.is_some());
+                intervals.extend(branches.map(|(start, end, _)| (start.copied(), end.copied())));
+                let guards = state.guards().iter();
+                continuations
+                    .extend(guards.map(|(guard, _)| guard_set(options, graph.guard(*guard))));
+            }
+            continue;
+        };
+        if graph.states()[*ret].assoc().is_empty() {
+            continue;
+        }
+
+        continuations.push(match &call.target {
+            CallTarget::Sub { ty, .. } => quote! {
+                <#ty as #sub_pattern_trait<#base_type>>::CONTINUATIONS
+            },
+            CallTarget::Count { counter, .. } => intervals_set(options, graph.counter(*counter)),
+            CallTarget::Rule { index, .. } => intervals_set(options, &rules.continuations(*index)),
+        });
+        continuations.push(first_set(options, graph, rules, *ret, &mut BTreeSet::new()));
+    }
+    let intervals = intervals_set(options, &intervals);
+
+    quote! {
+        impl #impl_generics #sub_pattern_trait<#base_type> for #ident #ty_generics #where_clause {
+            const FIRST: #element_set_type = #first;
+            const NULLABLE: bool = #nullable;
+            const CONTINUATIONS: #element_set_type = #element_set_type::Union(&[#intervals #(, #continuations)*]);
+        }
+    }
+}
+
+// `sub!` で委譲した列挙型がマッチした後にさらに読み進めうる要素を、戻り先のパターンも読みうる場合は、
+// どちらに渡すかが決まらないため定数の評価でエラーにする。
+// 再帰的な宣言は展開時に検査するが、戻り先が `sub!` などで始まる場合はここで検査する。
+// 列挙型の引数を参照するパターンは、列挙型の外で評価できないため確かめない。
+pub(super) fn generate_continuation_checks<T: PatternChar>(
+    options: &RegenOptions,
+    item: &syn::ItemEnum,
+    graph: &MatchGraph<T>,
+    rules: &Rules<T>,
+) -> Vec<TokenStream> {
+    let resolver = options.resolver();
+    let base_type = resolver.base_type();
+    let sub_pattern_trait = resolver.sub_pattern_trait();
+    let element_set_type = resolver.element_set_type();
+    let params: Vec<_> = item
+        .generics
+        .params
+        .iter()
+        .filter_map(|p| match p {
+            syn::GenericParam::Type(p) => Some(&p.ident),
+            syn::GenericParam::Const(p) => Some(&p.ident),
+            syn::GenericParam::Lifetime(_) => None,
+        })
+        .collect();
+
+    graph
+        .states()
+        .iter()
+        .flat_map(|state| state.calls())
+        .filter_map(|(call, ret)| {
+            let (continuations, message) = match &call.target {
+                CallTarget::Sub { ty, .. } => (
+                    quote! { <#ty as #sub_pattern_trait<#base_type>>::CONTINUATIONS },
+                    format!(
+                        "`{}` can continue on an element that the pattern following `sub!` can also match.",
+                        ty.to_token_stream()
+                    ),
+                ),
+                CallTarget::Rule { index, name } => {
+                    let ret_state = &graph.states()[*ret];
+                    if ret_state.calls().is_empty() && ret_state.guards().is_empty() {
+                        return None;
+                    }
+                    (
+                        intervals_set(options, &rules.continuations(*index)),
+                        format!("`{name}` can continue on an element that the pattern following it can also match."),
+                    )
+                }
+                CallTarget::Count { .. } => return None,
+            };
+
+            let first = first_set(options, graph, rules, *ret, &mut BTreeSet::new());
+            if mentions_any(quote!(#continuations #first), &params) {
+                return None;
+            }
+
+            let message = message.replace('{', "{{").replace('}', "}}");
+            // 集合は他の列挙型の定数を参照するため、定数に束縛して `'static` にする。
+            Some(quote_spanned! {call.span=>
+                const _: () = {
+                    const CONTINUATIONS: #element_set_type = #continuations;
+                    const FIRST: #element_set_type = #first;
+                    ::core::assert!(!CONTINUATIONS.overlaps(FIRST), #message);
+                };
+            })
+        })
+        .collect()
+}
+
+// ステート `index` から読みうる要素。委譲を含むステートでは委譲先の先頭の要素を加え、
+// 委譲先が空列にマッチしうるなら戻り先から読みうる要素も加える。
+fn first_set<T: PatternChar>(
+    options: &RegenOptions,
+    graph: &MatchGraph<T>,
+    rules: &Rules<T>,
+    index: usize,
+    visited: &mut BTreeSet<usize>,
+) -> TokenStream {
+    let resolver = options.resolver();
+    let base_type = resolver.base_type();
+    let sub_pattern_trait = resolver.sub_pattern_trait();
+    let element_set_type = resolver.element_set_type();
+    let state = &graph.states()[index];
+    visited.insert(index);
+
+    let mut sets = vec![state_set(options, graph, state)];
+    for (call, ret) in state.calls() {
+        let (first, nullable) = match &call.target {
+            CallTarget::Sub { ty, .. } => (
+                quote! { <#ty as #sub_pattern_trait<#base_type>>::FIRST },
+                quote! { <#ty as #sub_pattern_trait<#base_type>>::NULLABLE },
+            ),
+            CallTarget::Count { counter, min, .. } => (
+                intervals_set(options, graph.counter(*counter)),
+                quote! { (#min) == 0 },
+            ),
+            CallTarget::Rule { index, .. } => (
+                intervals_set(options, rules.first(*index)),
+                quote! { false },
+            ),
+        };
+        sets.push(first);
+
+        if !visited.contains(ret) {
+            let rest = first_set(options, graph, rules, *ret, visited);
+            sets.push(quote! {
+                if #nullable { #rest } else { #element_set_type::EMPTY }
+            });
+        }
+    }
+
+    quote! { #element_set_type::Union(&[#(#sets),*]) }
+}
+
+// ステート `index` で空列を受理しうるかどうかを表す定数式。
+fn nullable<T: PatternChar>(
+    options: &RegenOptions,
+    graph: &MatchGraph<T>,
+    index: usize,
+    visited: &mut BTreeSet<usize>,
+) -> TokenStream {
+    let resolver = options.resolver();
+    let base_type = resolver.base_type();
+    let sub_pattern_trait = resolver.sub_pattern_trait();
+    let state = &graph.states()[index];
+    if !state.assoc().is_empty() {
+        return quote! { true };
+    }
+    visited.insert(index);
+
+    let calls = state
+        .calls()
+        .iter()
+        .filter(|(_, ret)| !visited.contains(ret));
+    let calls: Vec<_> = calls.collect();
+    let terms = calls.into_iter().map(|(call, ret)| {
+        let callee = match &call.target {
+            CallTarget::Sub { ty, .. } => {
+                quote! { <#ty as #sub_pattern_trait<#base_type>>::NULLABLE }
+            }
+            CallTarget::Count { min, .. } => quote! { (#min) == 0 },
+            CallTarget::Rule { .. } => quote! { false },
+        };
+        let ret = nullable(options, graph, *ret, visited);
+        quote! { (#callee && #ret) }
+    });
+
+    quote! { false #(|| #terms)* }
+}
+
+// ステートの要素による遷移と、端点に定数を含む範囲による遷移で読む要素。
+fn state_set<T: PatternChar>(
+    options: &RegenOptions,
+    graph: &MatchGraph<T>,
+    state: &crate::match_graph::MatchState<T>,
+) -> TokenStream {
+    let element_set_type = options.resolver().element_set_type();
+    let intervals: Vec<_> = state
+        .branches()
+        .iter()
+        .filter(|(_, _, t)| t.is_some())
+        .map(|(start, end, _)| (start.copied(), end.copied()))
+        .collect();
+    let intervals = intervals_set(options, &intervals);
+    let guards = state
+        .guards()
+        .iter()
+        .map(|(guard, _)| guard_set(options, graph.guard(*guard)));
+
+    quote! { #element_set_type::Union(&[#intervals #(, #guards)*]) }
+}
+
+// 右半開区間の列を、両端を含む範囲の集まりにする。
+fn intervals_set<T: PatternChar>(
+    options: &RegenOptions,
+    intervals: &[(Option<T>, Option<T>)],
+) -> TokenStream {
+    let element_set_type = options.resolver().element_set_type();
+    let ranges = intervals.iter().map(|(start, end)| {
+        let start = start.map_or(0, |s| s.to_index());
+        let end = end.map_or(u64::MAX, |e| e.to_index() - 1);
+        quote! { #element_set_type::Range(#start, #end) }
+    });
+    quote! { #element_set_type::Union(&[#(#ranges),*]) }
+}
+
+// 端点に定数を含む範囲。半開区間の上端が0なら、下端を上端より大きくして空にする。
+fn guard_set(options: &RegenOptions, range: &syn::ExprRange) -> TokenStream {
+    let element_set_type = options.resolver().element_set_type();
+    let start = match &range.start {
+        Some(e) => quote! { (#e) as u64 },
+        None => quote! { 0 },
+    };
+    let (start, end) = match (&range.end, &range.limits) {
+        (Some(e), syn::RangeLimits::Closed(_)) => (start, quote! { (#e) as u64 }),
+        (Some(e), syn::RangeLimits::HalfOpen(_)) => (
+            quote! { if (#e) as u64 == 0 { 1 } else { #start } },
+            quote! { ((#e) as u64).wrapping_sub(1) },
+        ),
+        (None, _) => (start, quote! { u64::MAX }),
+    };
+    quote_spanned! {range.span()=>
+        #element_set_type::Range(#start, #end)
+    }
+}
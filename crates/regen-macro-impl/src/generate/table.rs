@@ -0,0 +1,145 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use super::{
+    generate_call_branch, generate_transition, guard_test,
+    lookup::{generate_class_lookup, index_type, literal},
+    marker_arm,
+};
+use crate::{
+    match_graph::{Assign, ElementClasses, MatchGraph, MatchProp, Rules},
+    pattern_char::PatternChar,
+    regen_options::RegenOptions,
+};
+use proc_macro2::TokenStream;
+use quote::quote;
+
+// 遷移表を引いて遷移ごとの処理を呼び分ける `advance`。
+// ステートと要素の分類の組から処理の番号を引く。0は遷移がないことを表す。
+pub(super) fn generate_table_advance_impl<T: PatternChar>(
+    options: &RegenOptions,
+    item: &syn::ItemEnum,
+    graph: &MatchGraph<T>,
+    rules: &Rules<T>,
+    slots: &BTreeSet<MatchProp>,
+) -> TokenStream {
+    let resolver = options.resolver();
+    let marker_arm = marker_arm(options, item, true);
+    let base_type = resolver.base_type();
+    let advance_result_type = resolver.advance_result_type();
+    let dead_state = resolver.dead_state_variant_name();
+    let state_type_name = resolver.state_machine_state_type_name(item);
+    let replace_fn = resolver.replace_fn();
+    let states = graph.states();
+
+    let classes = ElementClasses::new(graph);
+    let class_lookup = generate_class_lookup(options, &classes);
+    let class_count = classes.count();
+
+    let mut actions = Vec::new();
+    let mut guards = Vec::new();
+    let mut table = Vec::with_capacity(states.len());
+    for (state_index, state) in states.iter().enumerate() {
+        let variant = resolver.state_variant_name(state_index);
+        let fields: Vec<_> = state
+            .props()
+            .iter()
+            .map(|prop| resolver.state_field_name(prop))
+            .collect();
+
+        // 委譲を含むステートでは、要素によらず委譲先に渡す。
+        if let Some((call, ret_index)) = state.calls().first() {
+            actions.push(generate_call_branch(
+                options,
+                item,
+                graph,
+                state_index,
+                call,
+                *ret_index,
+                slots,
+            ));
+            table.push(vec![actions.len(); class_count]);
+            continue;
+        }
+
+        let mut row = vec![0; class_count];
+        let mut ids: BTreeMap<(usize, Vec<MatchProp>, Vec<Assign>), usize> = BTreeMap::new();
+        for (start, end, transition) in state.branches().iter() {
+            let Some(transition) = transition else {
+                continue;
+            };
+
+            let key = (
+                transition.state(),
+                transition.begins().to_vec(),
+                transition.assigns().to_vec(),
+            );
+            let id = *ids.entry(key).or_insert_with(|| {
+                let body =
+                    generate_transition(options, item, graph, rules, slots, state, transition);
+                actions.push(quote! {
+                    #state_type_name::#variant { #(mut #fields),* } => {
+                        #body
+                    }
+                });
+                actions.len()
+            });
+
+            for class in classes.classes_in(start, end) {
+                row[class] = id;
+            }
+        }
+        table.push(row);
+
+        // 端点に定数を含む範囲は表に載らないため、遷移がなかった要素だけを比べる。
+        for (guard, transition) in state.guards() {
+            let test = guard_test(graph.guard(*guard));
+            let body = generate_transition(options, item, graph, rules, slots, state, transition);
+            guards.push(quote! {
+                #state_type_name::#variant { #(mut #fields),* } if #test => {
+                    #body
+                }
+            });
+        }
+    }
+
+    let cell_type = index_type(actions.len() + 1);
+    let state_count = states.len();
+    let rows = table.iter().map(|row| {
+        let cells = row.iter().map(|&id| literal(id));
+        quote! { [#(#cells),*] }
+    });
+    let indices = (0..states.len()).map(|i| {
+        let variant = resolver.state_variant_name(i);
+        quote! { #state_type_name::#variant { .. } => #i, }
+    });
+    let action_arms = actions.iter().enumerate().map(|(i, action)| {
+        let id = i + 1;
+        quote! {
+            #id => match #replace_fn(&mut self.state, #state_type_name::#dead_state) {
+                #action
+                _ => ::core::unreachable!(),
+            },
+        }
+    });
+
+    quote! {
+        fn advance(&mut self, c: #base_type) -> #advance_result_type {
+            static TRANSITIONS: [[#cell_type; #class_count]; #state_count] = [#(#rows),*];
+
+            let index = match &self.state {
+                #(#indices)*
+                #state_type_name::#dead_state => return #advance_result_type::Error,
+                #marker_arm
+            };
+            #class_lookup
+
+            match TRANSITIONS[index][class] as usize {
+                #(#action_arms)*
+                _ => match #replace_fn(&mut self.state, #state_type_name::#dead_state) {
+                    #(#guards)*
+                    _ => #advance_result_type::Error,
+                },
+            }
+        }
+    }
+}
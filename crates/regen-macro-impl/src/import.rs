@@ -0,0 +1,313 @@
+use crate::declares::parse_entries;
+use proc_macro2::{TokenStream, TokenTree};
+use quote::{ToTokens, format_ident, quote};
+use syn::{parse::Parser, spanned::Spanned};
+
+// `patterns!` で定義したライブラリのマクロは、宣言を `regen_import!` に渡して呼び出し元の列挙型に埋め込む。
+const LIBRARY_MACRO: &str = "__regen_patterns";
+const IMPORT_ATTR: &str = "__regen_import";
+
+// PatternLibrary ::= vis + "mod" + ident + "{" + declares + "}"
+pub struct PatternLibrary {
+    vis: syn::Visibility,
+    ident: syn::Ident,
+    declares: TokenStream,
+}
+
+impl syn::parse::Parse for PatternLibrary {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let vis = input.parse()?;
+        input.parse::<syn::Token![mod]>()?;
+        let ident = input.parse()?;
+        let content;
+        syn::braced!(content in input);
+        let declares: TokenStream = content.parse()?;
+
+        // 要素の型は使う側で決まるため、ここでは構文のみを確かめる。
+        parse_entries.parse2(declares.clone())?;
+
+        Ok(Self {
+            vis,
+            ident,
+            declares,
+        })
+    }
+}
+
+pub fn generate_library(library: PatternLibrary) -> TokenStream {
+    let PatternLibrary {
+        vis,
+        ident,
+        declares,
+    } = library;
+    let library_macro = format_ident!("{}", LIBRARY_MACRO);
+    let body = quote! {
+        ($($tt:tt)*) => {
+            ::regen::__internal_macro::regen_import! {
+                { #declares }
+                $($tt)*
+            }
+        };
+    };
+
+    // クレートの外に公開しないライブラリは、モジュールの中で定義したマクロを使うため名前が重ならない。
+    if !matches!(vis, syn::Visibility::Public(_)) {
+        return quote! {
+            #vis mod #ident {
+                #[doc(hidden)]
+                macro_rules! #library_macro {
+                    #body
+                }
+
+                #[doc(hidden)]
+                pub(crate) use #library_macro;
+            }
+        };
+    }
+
+    // `#[macro_export]` したマクロはクレートの最上位に置かれるため、名前と宣言から別のモジュールにある同名のライブラリと区別する。
+    // 名前も宣言も同じ公開ライブラリは重複した定義になり、コンパイラが両方の `patterns!` を示す。
+    let hash = fnv1a(format!("{ident} {declares}").as_bytes());
+    let macro_name = format_ident!(
+        "{}_{}_{:016x}",
+        LIBRARY_MACRO,
+        ident,
+        hash,
+        span = ident.span()
+    );
+
+    quote! {
+        #[doc(hidden)]
+        #[macro_export]
+        macro_rules! #macro_name {
+            #body
+        }
+
+        #vis mod #ident {
+            #[doc(hidden)]
+            pub use #macro_name as #library_macro;
+        }
+    }
+}
+
+// コンパイラやビルドによらず同じ名前を付けるため、アルゴリズムが決まっているハッシュを使う。
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &b| {
+        (hash ^ b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+// 最初に見つかった `use` をプレースホルダの属性に置き換え、ライブラリのモジュールのパスを返す。
+pub fn take_import(item: &mut syn::ItemEnum) -> syn::Result<Option<syn::Path>> {
+    if let Some(path) = take_import_from(&mut item.attrs)? {
+        return Ok(Some(path));
+    }
+
+    for variant in &mut item.variants {
+        if let Some(path) = take_import_from(&mut variant.attrs)? {
+            return Ok(Some(path));
+        }
+    }
+
+    Ok(None)
+}
+
+fn take_import_from(attrs: &mut Vec<syn::Attribute>) -> syn::Result<Option<syn::Path>> {
+    for i in 0..attrs.len() {
+        if !attrs[i].path().is_ident("declare") {
+            continue;
+        }
+
+        let entries = split_entries(attrs[i].meta.require_list()?.tokens.clone());
+        let Some(pos) = entries.iter().position(is_use) else {
+            continue;
+        };
+
+        let tree: syn::UseTree = syn::parse2(entries[pos].clone().into_iter().skip(1).collect())?;
+        let (path, selection) = import_path(&tree)?;
+
+        let before = &entries[..pos];
+        let after = &entries[pos + 1..];
+        let mut replaced: Vec<syn::Attribute> = Vec::new();
+        if !before.is_empty() {
+            replaced.push(syn::parse_quote!(#[declare(#(#before);*)]));
+        }
+        let import_attr = format_ident!("{}", IMPORT_ATTR);
+        replaced.push(syn::parse_quote!(#[#import_attr(#selection)]));
+        if !after.is_empty() {
+            replaced.push(syn::parse_quote!(#[declare(#(#after);*)]));
+        }
+
+        attrs.splice(i..=i, replaced);
+        return Ok(Some(path));
+    }
+
+    Ok(None)
+}
+
+pub fn library_macro_call(
+    path: &syn::Path,
+    attr: TokenStream,
+    item: &syn::ItemEnum,
+) -> TokenStream {
+    let library_macro = format_ident!("{}", LIBRARY_MACRO);
+    quote! {
+        #path::#library_macro! {
+            (#attr)
+            #item
+        }
+    }
+}
+
+// ImportInput ::= "{" + declares + "}" + "(" + regen_args + ")" + item
+pub struct ImportInput {
+    declares: TokenStream,
+    attr: TokenStream,
+    item: syn::ItemEnum,
+}
+
+impl syn::parse::Parse for ImportInput {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let declares;
+        syn::braced!(declares in input);
+        let attr;
+        syn::parenthesized!(attr in input);
+
+        Ok(Self {
+            declares: declares.parse()?,
+            attr: attr.parse()?,
+            item: input.parse()?,
+        })
+    }
+}
+
+// プレースホルダの属性を、選ばれた宣言を持つ `declare` 属性に置き換える。
+pub fn expand_import(input: ImportInput) -> syn::Result<(TokenStream, syn::ItemEnum)> {
+    let ImportInput {
+        declares,
+        attr,
+        mut item,
+    } = input;
+
+    let attrs = std::iter::once(&mut item.attrs)
+        .chain(item.variants.iter_mut().map(|v| &mut v.attrs))
+        .find(|attrs| attrs.iter().any(|a| a.path().is_ident(IMPORT_ATTR)));
+    let Some(attrs) = attrs else {
+        return Err(syn::Error::new(item.span(), "No import was found."));
+    };
+
+    let i = attrs
+        .iter()
+        .position(|a| a.path().is_ident(IMPORT_ATTR))
+        .unwrap();
+    let selection = attrs[i].meta.require_list()?.tokens.clone();
+
+    let entries = split_entries(declares);
+    let selected = if is_glob(&selection) {
+        entries
+    } else {
+        let names = syn::punctuated::Punctuated::<syn::Ident, syn::Token![,]>::parse_terminated
+            .parse2(selection)?;
+        names
+            .iter()
+            .map(|name| {
+                entries
+                    .iter()
+                    .find(|e| entry_name(e).is_some_and(|n| n == *name))
+                    .cloned()
+                    .ok_or_else(|| {
+                        syn::Error::new(
+                            name.span(),
+                            format!("`{name}` is not declared in the pattern library."),
+                        )
+                    })
+            })
+            .collect::<syn::Result<_>>()?
+    };
+
+    attrs[i] = syn::parse_quote!(#[declare(#(#selected);*)]);
+    Ok((attr, item))
+}
+
+fn split_entries(tokens: TokenStream) -> Vec<TokenStream> {
+    let mut entries = vec![TokenStream::new()];
+    for tt in tokens {
+        match &tt {
+            TokenTree::Punct(p) if p.as_char() == ';' => entries.push(TokenStream::new()),
+            _ => entries.last_mut().unwrap().extend([tt]),
+        }
+    }
+
+    entries.retain(|e| !e.is_empty());
+    entries
+}
+
+fn is_use(entry: &TokenStream) -> bool {
+    matches!(entry.clone().into_iter().next(), Some(TokenTree::Ident(i)) if i == "use")
+}
+
+fn is_glob(selection: &TokenStream) -> bool {
+    let tokens: Vec<_> = selection.clone().into_iter().collect();
+    matches!(tokens.as_slice(), [TokenTree::Punct(p)] if p.as_char() == '*')
+}
+
+// `rec` を除いた、宣言の先頭の識別子。
+fn entry_name(entry: &TokenStream) -> Option<syn::Ident> {
+    let mut idents = entry.clone().into_iter().map_while(|tt| match tt {
+        TokenTree::Ident(i) => Some(i),
+        _ => None,
+    });
+
+    let first = idents.next()?;
+    if first == "rec" {
+        return idents.next().or(Some(first));
+    }
+    Some(first)
+}
+
+// `use` のパスを、ライブラリのモジュールと取り込む宣言の選択に分ける。
+fn import_path(tree: &syn::UseTree) -> syn::Result<(syn::Path, TokenStream)> {
+    let mut segments: syn::punctuated::Punctuated<syn::PathSegment, syn::Token![::]> =
+        Default::default();
+    let mut tree = tree;
+    let selection = loop {
+        match tree {
+            syn::UseTree::Path(p) => {
+                segments.push(p.ident.clone().into());
+                tree = &p.tree;
+            }
+            syn::UseTree::Glob(g) => break g.star_token.to_token_stream(),
+            syn::UseTree::Name(n) => break n.ident.to_token_stream(),
+            syn::UseTree::Group(g) => {
+                let names = g
+                    .items
+                    .iter()
+                    .map(|item| match item {
+                        syn::UseTree::Name(n) => Ok(n.ident.clone()),
+                        item => Err(syn::Error::new(item.span(), "Ident was expected.")),
+                    })
+                    .collect::<syn::Result<Vec<_>>>()?;
+                break quote!(#(#names),*);
+            }
+            syn::UseTree::Rename(r) => {
+                return Err(syn::Error::new(
+                    r.span(),
+                    "Renaming imports is not supported.",
+                ));
+            }
+        }
+    };
+
+    if segments.is_empty() {
+        return Err(syn::Error::new(
+            tree.span(),
+            "Path to a pattern library was expected.",
+        ));
+    }
+
+    let path = syn::Path {
+        leading_colon: None,
+        segments,
+    };
+    Ok((path, selection))
+}
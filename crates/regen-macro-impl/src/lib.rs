@@ -3,36 +3,48 @@ mod declares;
 mod eval;
 mod field_attibute;
 mod generate;
+mod import;
 mod match_graph;
 mod pattern;
 mod pattern_char;
+mod regex;
 mod regen_args;
 mod regen_options;
 mod regen_prelude;
+mod struct_item;
+mod unicode;
 mod util;
 mod variant_pattern;
+mod warning;
 
 use base_type::BaseType;
 use generate::generate_state_machine;
 use pattern_char::PatternChar;
 use proc_macro2::TokenStream;
-use quote::quote;
+use quote::{ToTokens, quote};
 use regen_args::RegenArgs;
 use regen_options::strip_options;
 use regen_prelude::strip_prelude;
 use variant_pattern::strip_variant_attrs;
 
 pub fn regen(attr: TokenStream, body: TokenStream) -> TokenStream {
-    let options: RegenArgs = match syn::parse2(attr) {
+    let options: RegenArgs = match syn::parse2(attr.clone()) {
         Ok(v) => v,
         Err(e) => return e.to_compile_error(),
     };
 
-    let item: syn::ItemEnum = match syn::parse2(body) {
+    let mut item = match struct_item::parse_item(body) {
         Ok(v) => v,
         Err(e) => return e.to_compile_error(),
     };
 
+    // パターンライブラリの取り込みは、ライブラリのマクロを経由して一つずつ展開する。
+    match import::take_import(&mut item) {
+        Ok(Some(path)) => return import::library_macro_call(&path, attr, &item),
+        Ok(None) => {}
+        Err(e) => return e.to_compile_error(),
+    }
+
     match options.base_type() {
         BaseType::Char => gen_impl::<char>(options, item),
         BaseType::U8 => gen_impl::<u8>(options, item),
@@ -42,6 +54,21 @@ pub fn regen(attr: TokenStream, body: TokenStream) -> TokenStream {
     }
 }
 
+pub fn patterns(input: TokenStream) -> TokenStream {
+    match syn::parse2(input) {
+        Ok(library) => import::generate_library(library),
+        Err(e) => e.to_compile_error(),
+    }
+}
+
+pub fn regen_import(input: TokenStream) -> TokenStream {
+    let expanded = syn::parse2(input).and_then(import::expand_import);
+    match expanded {
+        Ok((attr, item)) => regen(attr, item.into_token_stream()),
+        Err(e) => e.to_compile_error(),
+    }
+}
+
 fn gen_impl<T: PatternChar>(args: RegenArgs, mut item: syn::ItemEnum) -> TokenStream {
     let options = match strip_options(&mut item, args) {
         Ok(v) => v,
@@ -59,6 +86,11 @@ fn gen_impl<T: PatternChar>(args: RegenArgs, mut item: syn::ItemEnum) -> TokenSt
     };
 
     let state_machine = generate_state_machine::<T>(&options, &item, prelude, variants);
+    let item = if options.is_struct() {
+        struct_item::to_struct(item).into_token_stream()
+    } else {
+        item.into_token_stream()
+    };
 
     quote! {
         #item
@@ -70,6 +102,29 @@ fn gen_impl<T: PatternChar>(args: RegenArgs, mut item: syn::ItemEnum) -> TokenSt
 mod test {
     pub use super::*;
 
+    fn compile_errors(attr: TokenStream, body: TokenStream) -> Vec<String> {
+        let tokens = regen(attr, body);
+        let file: syn::File = syn::parse2(tokens).unwrap();
+
+        file.items
+            .iter()
+            .filter_map(|e| match e {
+                syn::Item::Macro(item_macro) => {
+                    if item_macro.mac.path == syn::parse_quote!(::core::compile_error) {
+                        item_macro
+                            .mac
+                            .parse_body::<syn::LitStr>()
+                            .ok()
+                            .map(|e| e.value())
+                    } else {
+                        None
+                    }
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
     #[test]
     fn test_conflict() {
         let attr: TokenStream = syn::parse_quote! {
@@ -87,29 +142,795 @@ mod test {
             }
         };
 
-        let tokens = regen(attr, body);
-        let file: syn::File = syn::parse2(tokens).unwrap();
+        assert_eq!(
+            compile_errors(attr, body),
+            [String::from(
+                "The following patterns are conflicting: `A` and `B`"
+            )]
+        );
+    }
+
+    #[test]
+    fn test_negate_sequence() {
+        let attr: TokenStream = syn::parse_quote! {
+            char
+        };
+
+        let body: TokenStream = syn::parse_quote! {
+            pub enum Test {
+                #[pattern = !"ab"]
+                A,
+            }
+        };
+
+        assert_eq!(
+            compile_errors(attr, body),
+            [String::from(
+                "Only patterns matching a single element can be negated."
+            )]
+        );
+    }
+
+    #[test]
+    fn test_collect_in_difference() {
+        let attr: TokenStream = syn::parse_quote! {
+            char
+        };
+
+        let body: TokenStream = syn::parse_quote! {
+            pub enum Test {
+                #[pattern = collect!(x <- "ab") - "ab"]
+                A { x: String },
+            }
+        };
+
+        assert_eq!(
+            compile_errors(attr, body),
+            [String::from(
+                "`collect!` and `set!` cannot be used in operands of `-`."
+            )]
+        );
+    }
+
+    #[test]
+    fn test_unsupported_regex() {
+        let attr: TokenStream = syn::parse_quote! {
+            char
+        };
+
+        let body: TokenStream = syn::parse_quote! {
+            pub enum Test {
+                #[regex = r"(a)\1"]
+                A,
+            }
+        };
+
+        assert_eq!(
+            compile_errors(attr, body),
+            [String::from("Backreferences are not supported.")]
+        );
+
+        let attr: TokenStream = syn::parse_quote! {
+            char
+        };
+
+        let body: TokenStream = syn::parse_quote! {
+            pub enum Test {
+                #[regex = r"a(?=b)"]
+                B,
+            }
+        };
+
+        assert_eq!(
+            compile_errors(attr, body),
+            [String::from("Lookaround is not supported.")]
+        );
+
+        let attr: TokenStream = syn::parse_quote! {
+            char
+        };
+
+        let body: TokenStream = syn::parse_quote! {
+            pub enum Test {
+                #[regex = "a{99999999999999999999999}"]
+                C,
+            }
+        };
+
+        assert_eq!(
+            compile_errors(attr, body),
+            [String::from("Repetition count is too large.")]
+        );
+    }
+
+    #[test]
+    fn test_ambiguous_sub() {
+        let attr: TokenStream = syn::parse_quote! {
+            char
+        };
+
+        let body: TokenStream = syn::parse_quote! {
+            pub enum Test {
+                #[pattern = opt!(sub!(x <- Other)) + "a"]
+                A { x: Other },
+            }
+        };
 
-        let x = file.items.iter().find_map(|e| match e {
-            syn::Item::Macro(item_macro) => {
-                if item_macro.mac.path == syn::parse_quote!(::core::compile_error) {
-                    item_macro
-                        .mac
-                        .parse_body::<syn::LitStr>()
-                        .ok()
-                        .map(|e| e.value())
-                } else {
-                    None
+        assert_eq!(
+            compile_errors(attr, body),
+            [String::from(
+                "`sub!` cannot be used where another pattern can also match."
+            )]
+        );
+    }
+
+    #[test]
+    fn test_sub_continuation() {
+        let attr: TokenStream = syn::parse_quote! {
+            char
+        };
+
+        // 委譲先の列挙型は別に展開されるため、読みうる要素の重なりは定数の評価で検査する。
+        let body: TokenStream = syn::parse_quote! {
+            pub enum Test {
+                #[pattern = sub!(x <- Inner) + "bd"]
+                A { x: Inner },
+            }
+        };
+
+        assert_eq!(compile_errors(attr.clone(), body.clone()), [] as [String; 0]);
+        let tokens = regen(attr.clone(), body).to_string();
+        assert!(tokens.contains(
+            "`Inner` can continue on an element that the pattern following `sub!` can also match."
+        ));
+
+        // 再帰的な宣言に `sub!` が続く場合も、委譲先が読む要素は定数の評価で比べる。
+        let body: TokenStream = syn::parse_quote! {
+            #[declare(rec r = 'a' + opt!('b'))]
+            pub enum Test {
+                #[pattern = r + sub!(x <- Inner)]
+                A { x: Inner },
+            }
+        };
+
+        assert_eq!(compile_errors(attr.clone(), body.clone()), [] as [String; 0]);
+        let tokens = regen(attr, body).to_string();
+        assert!(tokens.contains(
+            "`r` can continue on an element that the pattern following it can also match."
+        ));
+    }
+
+    #[test]
+    fn test_invalid_recursive_declares() {
+        let cases: [(TokenStream, &str); 6] = [
+            (
+                syn::parse_quote! {
+                    #[declare(rec expr = expr + '+' + "1" | "1")]
+                    pub enum Test {
+                        #[pattern = expr]
+                        A,
+                    }
+                },
+                "Left recursion is not supported.",
+            ),
+            (
+                syn::parse_quote! {
+                    #[declare(rec group = many0!('(' + group + ')'))]
+                    pub enum Test {
+                        #[pattern = group]
+                        A,
+                    }
+                },
+                "Recursive declarations must not match an empty sequence.",
+            ),
+            (
+                syn::parse_quote! {
+                    #[declare(rec item = 'x' | '(' + (item | "xy") + ')')]
+                    pub enum Test {
+                        #[pattern = item]
+                        A,
+                    }
+                },
+                "`item` cannot be used where another pattern can also match.",
+            ),
+            (
+                syn::parse_quote! {
+                    #[declare(rec r = 'a' + opt!('b'))]
+                    pub enum Test {
+                        #[pattern = r + 'b']
+                        A,
+                    }
+                },
+                "`r` can continue on an element that the pattern following it can also match.",
+            ),
+            (
+                syn::parse_quote! {
+                    #[declare(
+                        rec inner = 'b' + opt!('c');
+                        rec outer = 'a' + inner + 'c'
+                    )]
+                    pub enum Test {
+                        #[pattern = outer]
+                        A,
+                    }
+                },
+                "`inner` can continue on an element that the pattern following it can also match.",
+            ),
+            (
+                syn::parse_quote! {
+                    pub enum Test {
+                        #[declare(rec group = '(' + opt!(group) + ')')]
+                        #[pattern = group]
+                        A,
+                    }
+                },
+                "Recursive declarations must be placed on the enum.",
+            ),
+        ];
+
+        for (body, error) in cases {
+            let attr: TokenStream = syn::parse_quote! {
+                char
+            };
+
+            assert_eq!(compile_errors(attr, body), [String::from(error)]);
+        }
+    }
+
+    #[test]
+    fn test_parameterised_declare_errors() {
+        let cases: [(TokenStream, &[&str]); 3] = [
+            (
+                syn::parse_quote! {
+                    #[declare(list(p, sep) = p + [sep + p; ..])]
+                    pub enum Test {
+                        #[pattern = list('a')]
+                        A,
+                    }
+                },
+                &[
+                    "`list` takes 2 arguments, but 1 was given.",
+                    "`list` is declared here.",
+                ],
+            ),
+            (
+                syn::parse_quote! {
+                    #[declare(neg(p) = !p)]
+                    pub enum Test {
+                        #[pattern = neg("ab")]
+                        A,
+                    }
+                },
+                &[
+                    "Only patterns matching a single element can be negated.",
+                    "In this use of `neg`.",
+                ],
+            ),
+            (
+                syn::parse_quote! {
+                    #[declare(pair(p) = p + p; twice(q) = pair(p))]
+                    pub enum Test {
+                        #[pattern = twice('a')]
+                        A,
+                    }
+                },
+                &[
+                    "Undeclared variable. Did you mean `q`?",
+                    "In this use of `twice`.",
+                ],
+            ),
+        ];
+
+        for (body, errors) in cases {
+            let attr: TokenStream = syn::parse_quote! {
+                char
+            };
+
+            assert_eq!(compile_errors(attr, body), errors);
+        }
+    }
+
+    #[test]
+    fn test_declare_order_errors() {
+        let cases: [(TokenStream, &[&str]); 4] = [
+            (
+                syn::parse_quote! {
+                    #[declare(a = 'x' + b; b = c | 'y'; c = [a; 2..])]
+                    pub enum Test {
+                        #[pattern = a]
+                        A,
+                    }
+                },
+                &["Cyclic declarations: `a` -> `b` -> `c` -> `a`."],
+            ),
+            (
+                syn::parse_quote! {
+                    #[declare(wrap(p) = '(' + p + ')'; item = wrap(item))]
+                    pub enum Test {
+                        #[pattern = item]
+                        A,
+                    }
+                },
+                &["Cyclic declarations: `item` -> `item`."],
+            ),
+            (
+                syn::parse_quote! {
+                    #[declare(number = [digit; 1..])]
+                    pub enum Test {
+                        #[declare(number = 'x')]
+                        #[pattern = nubmer]
+                        A,
+                    }
+                },
+                &["Undeclared variable. Did you mean `number`?"],
+            ),
+            (
+                syn::parse_quote! {
+                    #[declare(x = 'a')]
+                    #[declare(x = 'b')]
+                    pub enum Test {
+                        #[pattern = x]
+                        A,
+                    }
+                },
+                &["Duplicated declaration `x`.", "`x` is first declared here."],
+            ),
+        ];
+
+        for (body, errors) in cases {
+            let attr: TokenStream = syn::parse_quote! {
+                char
+            };
+
+            assert_eq!(compile_errors(attr, body), errors);
+        }
+
+        let attr: TokenStream = syn::parse_quote! {
+            char
+        };
+        let body: TokenStream = syn::parse_quote! {
+            #[declare(number = [digit; 1..])]
+            pub enum Test {
+                #[declare(number = 'x')]
+                #[pattern = number]
+                A,
+            }
+        };
+        let tokens = regen(attr, body).to_string();
+        assert!(tokens.contains("deprecated"));
+        assert!(tokens.contains("`number` shadows an outer declaration."));
+
+        // 外側に同名の宣言があれば、自身への参照は外側の宣言を指す。
+        let attr: TokenStream = syn::parse_quote! {
+            char
+        };
+        let body: TokenStream = syn::parse_quote! {
+            #[declare(number = ['0'..='9'; 1..])]
+            pub enum Test {
+                #[declare(number = number + '.')]
+                #[pattern = number]
+                A,
+            }
+        };
+        assert_eq!(compile_errors(attr.clone(), body.clone()), [] as [String; 0]);
+        let tokens = regen(attr, body).to_string();
+        assert!(tokens.contains("`number` shadows an outer declaration."));
+    }
+
+    #[test]
+    fn test_invalid_bounds() {
+        let cases: [(TokenStream, &str); 7] = [
+            (
+                syn::parse_quote! {
+                    pub enum Test {
+                        #[pattern = ['a'; 1 - 2..]]
+                        A,
+                    }
+                },
+                "Arithmetic overflow.",
+            ),
+            (
+                syn::parse_quote! {
+                    pub enum Test {
+                        #[pattern = sep_by!(digit, ',', 1..N)]
+                        A,
+                    }
+                },
+                "Constants can only be used in bounds of `repeat!` and range patterns.",
+            ),
+            // 定数の回数は展開時に分からず並べて展開できないため、複数の要素を読むパターンは数えられない。
+            (
+                syn::parse_quote! {
+                    pub enum Test {
+                        #[pattern = ["ab"; 1..N]]
+                        A,
+                    }
+                },
+                "Only patterns matching a single element can be repeated a number of times given by constants.",
+            ),
+            (
+                syn::parse_quote! {
+                    pub enum Test {
+                        #[pattern = [(opt!('-') + digit); 2..=N]]
+                        A,
+                    }
+                },
+                "Only patterns matching a single element can be repeated a number of times given by constants.",
+            ),
+            (
+                syn::parse_quote! {
+                    pub enum Test {
+                        #[pattern = 'a' + [digit; ..N]]
+                        A,
+                        #[pattern = "a1"]
+                        B,
+                    }
+                },
+                "Patterns counted by constants cannot be used where another pattern can also match.",
+            ),
+            (
+                syn::parse_quote! {
+                    pub enum Test {
+                        #[pattern = [('0'..='9'); 1..=N] + '5']
+                        A,
+                    }
+                },
+                "Patterns counted by constants cannot be followed by a pattern that can also match the counted elements.",
+            ),
+            (
+                syn::parse_quote! {
+                    #[declare(rec item = 'x' + [digit; ..N])]
+                    pub enum Test {
+                        #[pattern = item]
+                        A,
+                    }
+                },
+                "Constants cannot be used in recursive declarations.",
+            ),
+        ];
+
+        for (body, error) in cases {
+            let attr: TokenStream = syn::parse_quote! {
+                char
+            };
+
+            assert_eq!(compile_errors(attr, body), [String::from(error)]);
+        }
+    }
+
+    #[test]
+    fn test_invalid_assigns() {
+        let cases: [(TokenStream, &str); 4] = [
+            (
+                syn::parse_quote! {
+                    pub enum Test {
+                        #[pattern = "a" | ("b" + set!(x = 1))]
+                        A { x: usize },
+                    }
+                },
+                "Field `x` is not assigned on every path. `#[default = ...]` is required.",
+            ),
+            (
+                syn::parse_quote! {
+                    pub enum Test {
+                        #[pattern = ("a" + set!(x = 1)) | ("a" + set!(x = 2))]
+                        A { x: usize },
+                    }
+                },
+                "`set!` assigns different values to `x` on the same path.",
+            ),
+            (
+                syn::parse_quote! {
+                    pub enum Test {
+                        #[pattern = set!(x = 1, 2)]
+                        A { x: usize },
+                    }
+                },
+                "Unexpected arguments of `set!`.",
+            ),
+            (
+                syn::parse_quote! {
+                    pub enum Test {
+                        #[pattern = "a" + set!(x = 1)]
+                        A {
+                            #[default = 0]
+                            #[default = 1]
+                            x: usize
+                        },
+                    }
+                },
+                "Duplicated default attributes.",
+            ),
+        ];
+
+        for (body, error) in cases {
+            let attr: TokenStream = syn::parse_quote! {
+                char
+            };
+
+            assert_eq!(compile_errors(attr, body), [String::from(error)]);
+        }
+    }
+
+    #[test]
+    fn test_invalid_hooks() {
+        let cases: [(TokenStream, &str); 4] = [
+            (
+                syn::parse_quote! {
+                    pub enum Test {
+                        #[validate = |_| true]
+                        #[pattern = "a"]
+                        A,
+                    }
+                },
+                "validate path was expected.",
+            ),
+            (
+                syn::parse_quote! {
+                    pub enum Test {
+                        #[validate = check]
+                        #[validate = check]
+                        #[pattern = "a"]
+                        A,
+                    }
+                },
+                "Duplicated validate attributes.",
+            ),
+            (
+                syn::parse_quote! {
+                    pub enum Test {
+                        #[pattern = collect!(0 <- "a")]
+                        A(#[map = "a"] usize),
+                    }
+                },
+                "map path was expected.",
+            ),
+            (
+                syn::parse_quote! {
+                    pub enum Test {
+                        #[pattern = collect!(0 <- "a")]
+                        A(#[map = f] #[map = g] usize),
+                    }
+                },
+                "Duplicated map attributes.",
+            ),
+        ];
+
+        for (body, error) in cases {
+            let attr: TokenStream = syn::parse_quote! {
+                char
+            };
+
+            assert_eq!(compile_errors(attr, body), [String::from(error)]);
+        }
+    }
+
+    #[test]
+    fn test_invalid_items() {
+        let cases: [(TokenStream, &str); 2] = [
+            (
+                syn::parse_quote! {
+                    pub struct Test {
+                        x: usize,
+                    }
+                },
+                "Structs require a pattern attribute.",
+            ),
+            (
+                syn::parse_quote! {
+                    pub union Test {
+                        x: usize,
+                    }
+                },
+                "`regen` can only be applied to enums and structs.",
+            ),
+        ];
+
+        for (body, error) in cases {
+            let attr: TokenStream = syn::parse_quote! {
+                char
+            };
+
+            assert_eq!(compile_errors(attr, body), [String::from(error)]);
+        }
+    }
+
+    #[test]
+    fn test_invalid_args() {
+        let cases: [(TokenStream, &str); 6] = [
+            (
+                syn::parse_quote! { char, codegen = "jump" },
+                "Unknown codegen. Expected `match` or `table`.",
+            ),
+            (
+                syn::parse_quote! { char, codegen = "table", codegen = "match" },
+                "Duplicated option `codegen`.",
+            ),
+            (
+                syn::parse_quote! { char, max_states = 100, max_states = 200 },
+                "Duplicated option `max_states`.",
+            ),
+            (
+                syn::parse_quote! { char, max_depth = 4, max_depth = 8 },
+                "Duplicated option `max_depth`.",
+            ),
+            (
+                syn::parse_quote! { char, speed = "fast" },
+                "Unknown option `speed`.",
+            ),
+            (
+                syn::parse_quote! { char, ParseIntError, ParseFloatError },
+                "Duplicated error types.",
+            ),
+        ];
+
+        for (attr, error) in cases {
+            let body: TokenStream = syn::parse_quote! {
+                pub enum Test {
+                    #[pattern = "a"]
+                    A,
                 }
+            };
+
+            assert_eq!(compile_errors(attr, body), [String::from(error)]);
+        }
+    }
+
+    #[test]
+    fn test_too_many_states() {
+        let cases: [(TokenStream, &str); 3] = [
+            (
+                syn::parse_quote! {
+                    pub enum Test {
+                        #[pattern = "v" + ["ab"; 0..=50]]
+                        A,
+                    }
+                },
+                "The automaton has more than 64 states, mostly produced by this pattern. Reduce the repetition or raise `max_states`.",
+            ),
+            (
+                syn::parse_quote! {
+                    pub enum Test {
+                        #[pattern = "abcdefghijklmnopqrstuvwxyz" + "abcdefghijklmnopqrstuvwxyz" + "abcdefghijklmnopqrstuvwxyz"]
+                        A,
+                    }
+                },
+                "The automaton has more than 64 states. Simplify the patterns or raise `max_states`.",
+            ),
+            (
+                syn::parse_quote! {
+                    #[declare(rec item = 'x' + [any; 0..=100])]
+                    pub enum Test {
+                        #[pattern = item]
+                        A,
+                    }
+                },
+                "The automaton has more than 64 states, mostly produced by this pattern. Reduce the repetition or raise `max_states`.",
+            ),
+        ];
+
+        for (body, error) in cases {
+            let attr: TokenStream = syn::parse_quote! {
+                char, max_states = 64
+            };
+
+            assert_eq!(compile_errors(attr, body), [String::from(error)]);
+        }
+
+        // 上限を上げれば同じパターンを受け付ける。
+        let attr: TokenStream = syn::parse_quote! { char, max_states = 200 };
+        let body: TokenStream = syn::parse_quote! {
+            pub enum Test {
+                #[pattern = "v" + ["ab"; 0..=50]]
+                A,
             }
-            _ => None,
-        });
+        };
+        assert!(compile_errors(attr, body).is_empty());
+    }
+
+    #[test]
+    fn test_invalid_imports() {
+        let cases: [(TokenStream, &str); 3] = [
+            (
+                syn::parse_quote! {
+                    #[declare(use common::number as n)]
+                    pub enum Test {
+                        #[pattern = n]
+                        A,
+                    }
+                },
+                "Renaming imports is not supported.",
+            ),
+            (
+                syn::parse_quote! {
+                    #[declare(use number)]
+                    pub enum Test {
+                        #[pattern = number]
+                        A,
+                    }
+                },
+                "Path to a pattern library was expected.",
+            ),
+            (
+                syn::parse_quote! {
+                    #[declare(use common::{number, alpha::*})]
+                    pub enum Test {
+                        #[pattern = number]
+                        A,
+                    }
+                },
+                "Ident was expected.",
+            ),
+        ];
+
+        for (body, error) in cases {
+            let attr: TokenStream = syn::parse_quote! {
+                char
+            };
+
+            assert_eq!(compile_errors(attr, body), [String::from(error)]);
+        }
+
+        let library: TokenStream = syn::parse_quote! {
+            mod common {
+                number = [digit; 1..];
+                use other::*
+            }
+        };
+        assert!(
+            patterns(library)
+                .to_string()
+                .contains("`use` cannot be used in pattern libraries.")
+        );
+    }
+
+    #[test]
+    fn test_library_macro_name() {
+        let digits: TokenStream = syn::parse_quote! {
+            pub mod token {
+                token = [digit; 1..]
+            }
+        };
+        let alphas: TokenStream = syn::parse_quote! {
+            pub mod token {
+                token = [alpha; 1..]
+            }
+        };
+
+        // 公開するマクロの名前は、名前と宣言だけから決まる。
+        let expanded = patterns(digits.clone()).to_string();
+        assert!(expanded.contains("macro_export"));
+        assert_eq!(patterns(digits).to_string(), expanded);
+        assert_ne!(patterns(alphas).to_string(), expanded);
+
+        // 公開しないライブラリは、クレートの最上位にマクロを置かない。
+        let private: TokenStream = syn::parse_quote! {
+            pub(crate) mod token {
+                token = [digit; 1..]
+            }
+        };
+        assert!(!patterns(private).to_string().contains("macro_export"));
+    }
+
+    #[cfg(feature = "unicode")]
+    #[test]
+    fn test_unicode_class_for_bytes() {
+        let attr: TokenStream = syn::parse_quote! {
+            u8
+        };
+
+        let body: TokenStream = syn::parse_quote! {
+            pub enum Test {
+                #[pattern = [Nd; 1..]]
+                A,
+            }
+        };
 
         assert_eq!(
-            x,
-            Some(String::from(
-                "The following patterns are conflicting: `A` and `B`"
-            ))
+            compile_errors(attr, body),
+            [String::from(
+                "Unicode property classes can only be used with `char`."
+            )]
         );
     }
 }
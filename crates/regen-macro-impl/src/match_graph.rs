@@ -1,12 +1,56 @@
 mod builder;
+mod classes;
 mod deterministic;
 mod nondeterministic;
+mod product;
+mod rules;
 
 pub use builder::{BuildContext, Builder, MatchPattern, StateId};
-pub use deterministic::{MatchGraph, MatchState};
+pub use classes::ElementClasses;
+pub use deterministic::{MatchGraph, MatchState, Transition};
+pub use product::ProductOp;
+pub use rules::Rules;
 
-#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct MatchProp {
     pub assoc: usize,
     pub field: String,
 }
+
+// `set!` による代入。`index` はグラフの中での `set!` の通し番号。
+#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Assign {
+    pub prop: MatchProp,
+    pub index: usize,
+}
+
+// 別の状態機械に処理を委ねる箇所。
+#[derive(Debug, Clone)]
+pub struct Call {
+    pub target: CallTarget,
+    pub span: proc_macro2::Span,
+}
+
+#[derive(Debug, Clone)]
+pub enum CallTarget {
+    // `sub!` で参照された列挙型。入口はε遷移で到達する。
+    Sub {
+        prop: MatchProp,
+        ty: syn::Path,
+    },
+    // `rec` で宣言されたパターン。先頭の要素を消費して入口に到達する。
+    Rule {
+        index: usize,
+        name: String,
+    },
+    // 定数で与えられた回数だけ、`test` を満たす要素を数える。数えた要素は `collects` に加える。
+    // `counter` は数える要素の集合の番号。`unrollable` は展開しても作れる繰り返しかどうか。
+    Count {
+        test: proc_macro2::TokenStream,
+        min: proc_macro2::TokenStream,
+        max: proc_macro2::TokenStream,
+        collects: Vec<MatchProp>,
+        counter: usize,
+        unrollable: bool,
+    },
+}
@@ -1,37 +1,110 @@
 use crate::{
-    match_graph::{MatchProp, deterministic, nondeterministic},
+    match_graph::{
+        Assign, Call, CallTarget, MatchProp, deterministic, nondeterministic,
+        product::{Product, ProductOp},
+        rules::Intervals,
+    },
     pattern_char::PatternChar,
     util::LinkedList,
     util::interval_map::Interval,
 };
+use proc_macro2::{Literal, Span, TokenStream};
+use quote::{ToTokens, quote};
 
 #[derive(Debug, Clone, Copy)]
 pub struct StateId(usize);
 
 pub struct Builder<T: PatternChar> {
     states: Vec<nondeterministic::MatchState<T>>,
+    // `set!` で代入する値。`Assign::index` で参照する。
+    values: Vec<syn::Expr>,
+    // 再帰的な宣言それぞれの先頭に現れうる要素。未計算の間は None。
+    firsts: Option<Vec<Intervals<T>>>,
+    // ステートを展開するパターンの位置。ステートが多すぎるときに原因として示す。
+    origins: Vec<Span>,
+    // 挿入中のステートが属する、最も内側の展開するパターン。
+    origin: Option<usize>,
+    // DFAのステート数の上限。
+    max_states: usize,
+    // 挿入中に見つかったエラー。`build` で報告する。
+    error: Option<syn::Error>,
+    // 長い繰り返しを展開せずにカウンタで数えるかどうか。
+    counting: bool,
+    // 数える繰り返しが数える要素。`CallTarget::Count::counter` で参照する。
+    counters: Vec<Intervals<T>>,
+    // 端点に定数を含む範囲。`insert_guard` で振った番号で参照する。
+    guards: Vec<syn::ExprRange>,
 }
 
 impl<T: PatternChar> Builder<T> {
     pub fn new() -> Self {
         Self {
             states: vec![Default::default()],
+            values: Vec::new(),
+            firsts: None,
+            origins: Vec::new(),
+            origin: None,
+            max_states: usize::MAX,
+            error: None,
+            counting: false,
+            counters: Vec::new(),
+            guards: Vec::new(),
         }
     }
 
+    pub fn with_firsts(firsts: Vec<Intervals<T>>) -> Self {
+        Self {
+            firsts: Some(firsts),
+            ..Self::new()
+        }
+    }
+
+    pub fn set_max_states(&mut self, max_states: usize) {
+        self.max_states = max_states;
+    }
+
+    pub fn set_counting(&mut self, counting: bool) {
+        self.counting = counting;
+    }
+
+    pub fn counting(&self) -> bool {
+        self.counting
+    }
+
     pub fn initial_state(&self) -> StateId {
         StateId(0)
     }
 
+    // 要素による遷移先となるステート。遷移時にcollect対象のフィールドへ要素を追加する。
     fn alloc_state(&mut self, context: &mut BuildContext) -> StateId {
+        let collects = context.collects.to_vec();
+        self.push_state(context, collects, Vec::new())
+    }
+
+    // ε遷移でのみ到達するステート。要素を消費しないためcollectは持たない。
+    fn alloc_junction_state(&mut self, context: &mut BuildContext) -> StateId {
+        self.push_state(context, Vec::new(), Vec::new())
+    }
+
+    fn push_state(
+        &mut self,
+        context: &mut BuildContext,
+        collects: Vec<MatchProp>,
+        begins: Vec<MatchProp>,
+    ) -> StateId {
         let next = self.states.len();
 
         let state = nondeterministic::MatchState {
             branches: Default::default(),
             epsilon_transitions: Default::default(),
             assoc: Default::default(),
-            collects: context.collects.to_vec(),
+            collects,
             props: context.props.clone(),
+            begins,
+            assigns: Vec::new(),
+            guards: Vec::new(),
+            call: None,
+            origin: self.origin,
         };
 
         self.states.push(state);
@@ -54,6 +127,19 @@ impl<T: PatternChar> Builder<T> {
         self.states[state.0].assoc.push(assoc);
     }
 
+    // `insert` で挿入したステートを、`span` のパターンが展開したものとして記録する。
+    pub fn insert_with_origin(
+        &mut self,
+        span: Span,
+        insert: impl FnOnce(&mut Self) -> StateId,
+    ) -> StateId {
+        let outer = self.origin.replace(self.origins.len());
+        self.origins.push(span);
+        let state = insert(self);
+        self.origin = outer;
+        state
+    }
+
     pub fn insert_atom(
         &mut self,
         context: &mut BuildContext,
@@ -65,19 +151,105 @@ impl<T: PatternChar> Builder<T> {
         state
     }
 
+    pub fn insert_set(
+        &mut self,
+        context: &mut BuildContext,
+        from: StateId,
+        intervals: impl IntoIterator<Item = impl Interval<T>>,
+    ) -> StateId {
+        let state = self.alloc_state(context);
+        for range in intervals {
+            self.states[from.0].branches.insert(range, &[state.0]);
+        }
+        state
+    }
+
     pub fn insert_repeat(
         &mut self,
         context: &mut BuildContext,
         from: StateId,
         pattern: &impl MatchPattern<T>,
     ) -> StateId {
-        let state = self.alloc_state(context);
+        let state = self.alloc_junction_state(context);
         self.insert_epsilon_transition(from, state);
         let end = pattern.insert(self, context, state);
         self.insert_epsilon_transition(end, state);
         state
     }
 
+    // 一回以上の繰り返し。パターンは一度だけ挿入し、その終端から先頭へ戻る。
+    pub fn insert_repeat1(
+        &mut self,
+        context: &mut BuildContext,
+        from: StateId,
+        pattern: &impl MatchPattern<T>,
+    ) -> StateId {
+        let state = self.alloc_junction_state(context);
+        self.insert_epsilon_transition(from, state);
+        let end = pattern.insert(self, context, state);
+        let out = self.alloc_junction_state(context);
+        self.insert_epsilon_transition(end, out);
+        self.insert_epsilon_transition(out, state);
+        out
+    }
+
+    pub fn insert_opt(
+        &mut self,
+        context: &mut BuildContext,
+        from: StateId,
+        pattern: &impl MatchPattern<T>,
+    ) -> StateId {
+        let end = pattern.insert(self, context, from);
+        self.insert_junction(context, [from, end])
+    }
+
+    // `pattern` を `separator` で区切って `min` 回以上 `max` 回以下並べる。
+    pub fn insert_sep_by(
+        &mut self,
+        context: &mut BuildContext,
+        from: StateId,
+        pattern: &impl MatchPattern<T>,
+        separator: &impl MatchPattern<T>,
+        min: usize,
+        max: Option<usize>,
+    ) -> StateId {
+        if max == Some(0) {
+            return self.insert_junction(context, [from]);
+        }
+
+        let mut state = pattern.insert(self, context, from);
+        for _ in 1..min {
+            state = separator.insert(self, context, state);
+            state = pattern.insert(self, context, state);
+        }
+
+        let end = match max {
+            Some(max) => {
+                let mut states = vec![state];
+                for _ in min.max(1)..max {
+                    state = separator.insert(self, context, state);
+                    state = pattern.insert(self, context, state);
+                    states.push(state);
+                }
+                self.insert_junction(context, states)
+            }
+            None => {
+                let head = self.alloc_junction_state(context);
+                self.insert_epsilon_transition(state, head);
+                let state = separator.insert(self, context, head);
+                let state = pattern.insert(self, context, state);
+                self.insert_epsilon_transition(state, head);
+                head
+            }
+        };
+
+        if min == 0 {
+            self.insert_junction(context, [from, end])
+        } else {
+            end
+        }
+    }
+
     pub fn insert_or(
         &mut self,
         context: &mut BuildContext,
@@ -87,7 +259,7 @@ impl<T: PatternChar> Builder<T> {
     ) -> StateId {
         let state0 = pattern0.insert(self, context, from);
         let state1 = pattern1.insert(self, context, from);
-        let state = self.alloc_state(context);
+        let state = self.alloc_junction_state(context);
 
         self.insert_epsilon_transition(state0, state);
         self.insert_epsilon_transition(state1, state);
@@ -107,7 +279,7 @@ impl<T: PatternChar> Builder<T> {
             field: field.to_string(),
         };
         let collects = context.collects.append(prop.clone());
-        context.props.push(prop);
+        context.props.push(prop.clone());
 
         let mut ctx = BuildContext {
             assoc: context.assoc,
@@ -115,7 +287,255 @@ impl<T: PatternChar> Builder<T> {
             props: context.props,
         };
 
-        pattern.insert(self, &mut ctx, from)
+        // 入口を経由する遷移ごとに、フィールドのビルダーへ新しい要素の開始を伝える。
+        let entry = self.push_state(&mut ctx, Vec::new(), vec![prop]);
+        self.insert_epsilon_transition(from, entry);
+        pattern.insert(self, &mut ctx, entry)
+    }
+
+    // 要素を消費せず、この経路を通ったときにフィールドへ値を代入する。
+    pub fn insert_assign(
+        &mut self,
+        context: &mut BuildContext,
+        from: StateId,
+        field: &str,
+        value: &syn::Expr,
+    ) -> StateId {
+        let assign = Assign {
+            prop: MatchProp {
+                assoc: context.assoc,
+                field: field.to_string(),
+            },
+            index: self.values.len(),
+        };
+        self.values.push(value.clone());
+
+        let state = self.alloc_junction_state(context);
+        self.states[state.0].assigns.push(assign);
+        self.insert_epsilon_transition(from, state);
+        state
+    }
+
+    // 入口から委譲先に進み、委譲先が完了すると戻り先のステートに移る。
+    pub fn insert_sub(
+        &mut self,
+        context: &mut BuildContext,
+        from: StateId,
+        field: &str,
+        ty: &syn::Path,
+        span: Span,
+    ) -> StateId {
+        let prop = MatchProp {
+            assoc: context.assoc,
+            field: field.to_string(),
+        };
+        context.props.push(prop.clone());
+
+        let entry = self.push_state(context, Vec::new(), vec![prop.clone()]);
+        let ret = self.alloc_junction_state(context);
+        let call = Call {
+            target: CallTarget::Sub {
+                prop,
+                ty: ty.clone(),
+            },
+            span,
+        };
+        self.states[entry.0].call = Some((call, ret.0));
+        self.insert_epsilon_transition(from, entry);
+        ret
+    }
+
+    // 定数で与えられた回数だけ要素を数える。回数は生成するコードで数えるため、委譲と同じく入口はε遷移で到達する。
+    pub fn insert_count(
+        &mut self,
+        context: &mut BuildContext,
+        from: StateId,
+        intervals: Intervals<T>,
+        min: TokenStream,
+        max: TokenStream,
+        span: Span,
+    ) -> StateId {
+        let target = self.count_target(context, intervals, min, max, false);
+        self.push_count(context, from, target, span)
+    }
+
+    // 一要素のパターンの長い繰り返しを、展開せずに生成するコードのカウンタで数える。
+    pub fn insert_counter(
+        &mut self,
+        context: &mut BuildContext,
+        from: StateId,
+        intervals: Intervals<T>,
+        min: usize,
+        max: Option<usize>,
+        span: Span,
+    ) -> StateId {
+        let min = Literal::usize_unsuffixed(min).into_token_stream();
+        let max = match max {
+            Some(max) => Literal::usize_unsuffixed(max).into_token_stream(),
+            None => quote!(usize::MAX),
+        };
+        let target = self.count_target(context, intervals, min, max, true);
+        self.push_count(context, from, target, span)
+    }
+
+    // 端点に定数を含む範囲の一要素。範囲は生成するコードで比べるため、同じ範囲ごとに番号を振って遷移を分ける。
+    pub fn insert_guard(
+        &mut self,
+        context: &mut BuildContext,
+        from: StateId,
+        range: &syn::ExprRange,
+    ) -> StateId {
+        let key = range.to_token_stream().to_string();
+        let guard = match self
+            .guards
+            .iter()
+            .position(|g| g.to_token_stream().to_string() == key)
+        {
+            Some(i) => i,
+            None => {
+                self.guards.push(range.clone());
+                self.guards.len() - 1
+            }
+        };
+
+        let state = self.alloc_state(context);
+        self.states[from.0].guards.push((guard, state.0));
+        state
+    }
+
+    // 数える要素の集合を登録し、それを数える委譲先を作る。
+    fn count_target(
+        &mut self,
+        context: &BuildContext,
+        intervals: Intervals<T>,
+        min: TokenStream,
+        max: TokenStream,
+        unrollable: bool,
+    ) -> CallTarget {
+        let ranges = intervals.iter().map(|(start, end)| match (start, end) {
+            (None, None) => quote!(_),
+            (start, end) => quote!(#start..#end),
+        });
+        let test = quote! { matches!(c, #(#ranges)|*) };
+
+        self.counters.push(intervals);
+        CallTarget::Count {
+            test,
+            min,
+            max,
+            collects: context.collects.to_vec(),
+            counter: self.counters.len() - 1,
+            unrollable,
+        }
+    }
+
+    fn push_count(
+        &mut self,
+        context: &mut BuildContext,
+        from: StateId,
+        target: CallTarget,
+        span: Span,
+    ) -> StateId {
+        let entry = self.alloc_junction_state(context);
+        let ret = self.alloc_junction_state(context);
+        let call = Call { target, span };
+        self.states[entry.0].call = Some((call, ret.0));
+        self.insert_epsilon_transition(from, entry);
+        ret
+    }
+
+    // 再帰的な宣言への委譲。先頭の要素が分かっていれば、その要素で入口に進むことで
+    // 他の遷移と同じように曖昧さを検出できる。分かっていなければε遷移で入口に進む。
+    pub fn insert_rule(
+        &mut self,
+        context: &mut BuildContext,
+        from: StateId,
+        index: usize,
+        name: &str,
+        span: Span,
+    ) -> StateId {
+        let entry = match &self.firsts {
+            Some(firsts) => {
+                let intervals = firsts[index].clone();
+                self.insert_set(context, from, intervals)
+            }
+            None => {
+                let entry = self.alloc_junction_state(context);
+                self.insert_epsilon_transition(from, entry);
+                entry
+            }
+        };
+
+        let ret = self.alloc_junction_state(context);
+        let call = Call {
+            target: CallTarget::Rule {
+                index,
+                name: name.to_string(),
+            },
+            span,
+        };
+        self.states[entry.0].call = Some((call, ret.0));
+        ret
+    }
+
+    // 両辺をそれぞれDFAに変換し、その直積をこのグラフに埋め込む。
+    pub fn insert_product(
+        &mut self,
+        context: &mut BuildContext,
+        from: StateId,
+        lhs: &impl MatchPattern<T>,
+        rhs: &impl MatchPattern<T>,
+        op: ProductOp,
+    ) -> StateId {
+        let (lhs, rhs) = match (self.subgraph(lhs), self.subgraph(rhs)) {
+            (Ok(lhs), Ok(rhs)) => (lhs, rhs),
+            (Err(e), _) | (_, Err(e)) => {
+                self.error.get_or_insert(e);
+                return from;
+            }
+        };
+        let product = Product::new(&lhs, &rhs, op);
+
+        let states: Vec<_> = product
+            .states
+            .iter()
+            .map(|_| self.alloc_state(context))
+            .collect();
+        let entry = self.alloc_junction_state(context);
+        let end = self.alloc_junction_state(context);
+        self.insert_epsilon_transition(from, entry);
+
+        for (i, state) in product.states.iter().enumerate() {
+            for &(lower, upper, t) in &state.branches {
+                let range = (lower, upper);
+                self.states[states[i].0]
+                    .branches
+                    .insert(range, &[states[t].0]);
+                // 初期状態へは要素による遷移でも戻りうるため、入口は別のステートとして複製する。
+                if i == 0 {
+                    self.states[entry.0].branches.insert(range, &[states[t].0]);
+                }
+            }
+
+            if state.accept {
+                self.insert_epsilon_transition(states[i], end);
+                if i == 0 {
+                    self.insert_epsilon_transition(entry, end);
+                }
+            }
+        }
+
+        end
+    }
+
+    fn subgraph(
+        &self,
+        pattern: &impl MatchPattern<T>,
+    ) -> syn::Result<deterministic::MatchGraph<T>> {
+        let mut builder = Self::new();
+        builder.set_max_states(self.max_states);
+        builder.add(0, pattern);
+        builder.build()
     }
 
     pub fn insert_junction(
@@ -123,19 +543,44 @@ impl<T: PatternChar> Builder<T> {
         context: &mut BuildContext,
         states: impl IntoIterator<Item = StateId>,
     ) -> StateId {
-        let state = self.alloc_state(context);
+        let state = self.alloc_junction_state(context);
         for s in states {
             self.insert_epsilon_transition(s, state);
         }
         state
     }
 
-    pub fn build(self) -> deterministic::MatchGraph<T> {
+    pub fn build(self) -> syn::Result<deterministic::MatchGraph<T>> {
+        if let Some(e) = self.error {
+            return Err(e);
+        }
+
         let ndgraph = nondeterministic::MatchGraph {
             states: self.states,
+            values: self.values,
+            counters: self.counters,
+            guards: self.guards,
         };
 
-        deterministic::MatchGraph::from_nondeterministic(&ndgraph)
+        let max = self.max_states;
+        deterministic::MatchGraph::from_nondeterministic(&ndgraph, max).map_err(|e| {
+            let Some(origin) = e.origin else {
+                return syn::Error::new(
+                    Span::call_site(),
+                    format!("The automaton has more than {max} states. Simplify the patterns or raise `max_states`."),
+                );
+            };
+
+            let span = self.origins[origin];
+            let pattern = match span.source_text() {
+                Some(text) => format!("`{text}`"),
+                None => String::from("this pattern"),
+            };
+            syn::Error::new(
+                span,
+                format!("The automaton has more than {max} states, mostly produced by {pattern}. Reduce the repetition or raise `max_states`."),
+            )
+        })
     }
 }
 
@@ -153,3 +598,77 @@ pub struct BuildContext<'a> {
     collects: LinkedList<MatchProp>,
     props: &'a mut Vec<MatchProp>,
 }
+
+#[cfg(test)]
+mod test {
+    use super::Builder;
+    use crate::{
+        match_graph::{deterministic, nondeterministic},
+        pattern::{Pattern, ResolveEnv},
+    };
+
+    fn graph(pattern: syn::Expr, counting: bool) -> deterministic::MatchGraph<char> {
+        let env = ResolveEnv::<char>::empty();
+        let pattern = env.resolve(&Pattern::new(&pattern).unwrap()).unwrap();
+        let mut builder = Builder::new();
+        builder.set_counting(counting);
+        builder.add(0, &pattern);
+        builder.build().unwrap()
+    }
+
+    // ステート数が上限を超えたときに原因とされる、展開したパターンの番号。
+    fn blamed_origin(pattern: syn::Expr, max_states: usize) -> Option<usize> {
+        let env = ResolveEnv::<char>::empty();
+        let pattern = env.resolve(&Pattern::new(&pattern).unwrap()).unwrap();
+        let mut builder = Builder::new();
+        builder.add(0, &pattern);
+
+        let ndgraph = nondeterministic::MatchGraph {
+            states: builder.states,
+            values: builder.values,
+            counters: builder.counters,
+            guards: builder.guards,
+        };
+        deterministic::MatchGraph::from_nondeterministic(&ndgraph, max_states)
+            .unwrap_err()
+            .origin
+    }
+
+    #[test]
+    fn test_too_many_states() {
+        // 後ろの繰り返しがステートの大半を作る。
+        assert_eq!(
+            blamed_origin(
+                syn::parse_quote!(['a'; 0..=3] + "-" + [('0'..='9'); 0..=100]),
+                50
+            ),
+            Some(1)
+        );
+        // 入れ子の繰り返しでは、内側を何度も複製する外側の繰り返しが原因になる。
+        assert_eq!(
+            blamed_origin(syn::parse_quote!(['x' + ['a'; 0..=10]; 0..=10]), 50),
+            Some(0)
+        );
+        assert_eq!(blamed_origin(syn::parse_quote!("abcdefghij"), 5), None);
+    }
+
+    #[test]
+    fn test_counters() {
+        let pattern: syn::Expr = syn::parse_quote!("id" + [xdigit; 64..=64] + ';');
+        assert!(graph(pattern.clone(), false).states().len() > 64);
+        let counted = graph(pattern, true);
+        assert!(counted.states().len() < 8);
+        assert!(counted.counters_are_exact());
+
+        // 短い繰り返しは展開する。
+        let short = graph(syn::parse_quote!([digit; 1..=4]), true);
+        assert!(short.states().iter().all(|s| s.calls().is_empty()));
+
+        // 繰り返しの後に同じ要素が続くと、貪欲に数えた場合とマッチする列が変わる。
+        assert!(!graph(syn::parse_quote!([digit; 0..=16] + "00"), true).counters_are_exact());
+        assert!(
+            !graph(syn::parse_quote!([digit; 0..=16] + [alnum; 0..=16]), true).counters_are_exact()
+        );
+        assert!(!graph(syn::parse_quote!([digit; 9..] | "1a"), true).counters_are_exact());
+    }
+}
@@ -0,0 +1,133 @@
+use super::{Assign, MatchGraph, MatchProp};
+use crate::pattern_char::PatternChar;
+use std::collections::{BTreeMap, BTreeSet};
+
+// 全てのステートで同じ遷移をする要素の集まり。
+// 区間の境界で分けた要素の区間を、どのステートでも遷移が変わらないものどうしまとめる。
+#[derive(Debug)]
+pub struct ElementClasses<T: PatternChar> {
+    bounds: Vec<T>,
+    classes: Vec<usize>,
+    count: usize,
+}
+
+impl<T: PatternChar> ElementClasses<T> {
+    pub fn new(graph: &MatchGraph<T>) -> Self {
+        let states = graph.states();
+        let bounds: Vec<T> = states
+            .iter()
+            .flat_map(|s| s.branches().iter())
+            .flat_map(|(start, end, _)| [start, end])
+            .flatten()
+            .copied()
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+
+        // 区間ごとに、各ステートでの遷移を番号で並べたもの。0は遷移がないことを表す。
+        let mut columns = vec![vec![0; states.len()]; bounds.len() + 1];
+        for (state_index, state) in states.iter().enumerate() {
+            let mut ids: BTreeMap<(usize, &[MatchProp], &[Assign]), usize> = BTreeMap::new();
+            for (start, end, transition) in state.branches().iter() {
+                let Some(t) = transition else {
+                    continue;
+                };
+
+                let len = ids.len();
+                let id = *ids
+                    .entry((t.state(), t.begins(), t.assigns()))
+                    .or_insert(len + 1);
+                for column in &mut columns[interval_index(&bounds, start, 0)
+                    ..interval_index(&bounds, end, bounds.len() + 1)]
+                {
+                    column[state_index] = id;
+                }
+            }
+        }
+
+        let mut map = BTreeMap::new();
+        let classes: Vec<_> = columns
+            .iter()
+            .map(|column| {
+                let len = map.len();
+                *map.entry(column).or_insert(len)
+            })
+            .collect();
+
+        Self {
+            bounds,
+            classes,
+            count: map.len(),
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    // 区間に含まれる要素の分類。
+    pub fn classes_in(&self, start: Option<&T>, end: Option<&T>) -> BTreeSet<usize> {
+        let from = interval_index(&self.bounds, start, 0);
+        let to = interval_index(&self.bounds, end, self.classes.len());
+        self.classes[from..to].iter().copied().collect()
+    }
+
+    // 境界で分けた区間と、その区間の要素の分類。
+    pub fn intervals(&self) -> impl Iterator<Item = (Option<&T>, Option<&T>, usize)> {
+        let starts = std::iter::once(None).chain(self.bounds.iter().map(Some));
+        let ends = self.bounds.iter().map(Some).chain([None]);
+        starts
+            .zip(ends)
+            .zip(&self.classes)
+            .map(|((start, end), &class)| (start, end, class))
+    }
+}
+
+// 境界 `bound` から始まる区間の番号。
+fn interval_index<T: Ord>(bounds: &[T], bound: Option<&T>, none: usize) -> usize {
+    match bound {
+        Some(b) => bounds.binary_search(b).unwrap() + 1,
+        None => none,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ElementClasses;
+    use crate::{
+        match_graph::Builder,
+        pattern::{Pattern, ResolveEnv},
+    };
+
+    fn classes(patterns: &[syn::Expr]) -> ElementClasses<char> {
+        let env = ResolveEnv::<char>::empty();
+        let mut builder = Builder::new();
+        for (assoc, expr) in patterns.iter().enumerate() {
+            let pattern = env.resolve(&Pattern::new(expr).unwrap()).unwrap();
+            builder.add(assoc, &pattern);
+        }
+        ElementClasses::new(&builder.build().unwrap())
+    }
+
+    #[test]
+    fn test_element_classes() {
+        // 大文字と小文字は離れた区間だが、同じ遷移をするため一つにまとまる。
+        let c = classes(&[
+            syn::parse_quote!([('0'..='9'); 1..]),
+            syn::parse_quote!([('a'..='z') | ('A'..='Z'); 1..]),
+        ]);
+        assert_eq!(c.count(), 3);
+        assert_eq!(
+            c.classes_in(Some(&'a'), Some(&'{')),
+            c.classes_in(Some(&'A'), Some(&'['))
+        );
+        assert_ne!(
+            c.classes_in(Some(&'0'), Some(&':')),
+            c.classes_in(Some(&'a'), Some(&'{'))
+        );
+
+        // 'b' はあるステートでだけ異なる遷移をするため、'a' や 'c' とは分かれる。
+        let c = classes(&[syn::parse_quote!(['a'..='c'; 1..] + 'b')]);
+        assert_eq!(c.count(), 3);
+    }
+}
@@ -1,15 +1,29 @@
+mod minimize;
+
 use super::nondeterministic;
-use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    rc::Rc,
+};
 
 use crate::{
-    match_graph::MatchProp,
+    match_graph::{Assign, Call, CallTarget, MatchProp, rules::Intervals},
     pattern_char::PatternChar,
-    util::{IntervalMap, interval_map::store::Unique, sorted_vec::SortedVec},
+    util::{
+        BitSet, Interner, IntervalMap,
+        interval_map::store::{Bits, Unique},
+        sorted_vec::SortedVec,
+    },
 };
 
 #[derive(Debug)]
 pub struct MatchGraph<T: PatternChar> {
     states: Vec<MatchState<T>>,
+    values: Vec<syn::Expr>,
+    // 数える繰り返しが数える要素。`CallTarget::Count::counter` で参照する。
+    counters: Vec<Intervals<T>>,
+    // 端点に定数を含む範囲。`MatchState::guards` の番号で参照する。
+    guards: Vec<syn::ExprRange>,
 }
 
 impl<T: PatternChar> MatchGraph<T> {
@@ -17,112 +31,214 @@ impl<T: PatternChar> MatchGraph<T> {
         &self.states
     }
 
-    pub(super) fn from_nondeterministic(graph: &nondeterministic::MatchGraph<T>) -> Self {
-        let initial_state = epsilon_closure(graph, [0]);
-        let mut context = ConvertionContext {
-            map: BTreeMap::from_iter([(initial_state.clone(), 0)]),
-            unchecked: vec![initial_state],
-            states: vec![MatchState::new()],
+    // 数える繰り返しが数える要素。
+    pub fn counter(&self, index: usize) -> &[(Option<T>, Option<T>)] {
+        &self.counters[index]
+    }
+
+    // 端点に定数を含む範囲。
+    pub fn guard(&self, index: usize) -> &syn::ExprRange {
+        &self.guards[index]
+    }
+
+    // `set!` で代入する値。
+    pub fn value(&self, assign: &Assign) -> &syn::Expr {
+        &self.values[assign.index]
+    }
+
+    // 遷移や受理で行われうる全ての代入。
+    pub fn assigns(&self) -> BTreeSet<&Assign> {
+        self.states
+            .iter()
+            .flat_map(|s| {
+                let transitions = s.branches.iter().filter_map(|(_, _, t)| t);
+                transitions
+                    .chain(s.guards.iter().map(|(_, t)| t))
+                    .flat_map(|t| &t.assigns)
+                    .chain(&s.accept_assigns)
+            })
+            .collect()
+    }
+
+    // 展開せずに数える繰り返しが、展開した場合と同じようにマッチするかどうか。
+    // カウンタは数える要素を上限まで貪欲に消費するため、入口のステートが他の遷移を持たず、
+    // 戻り先のステートが数える要素で遷移しない場合に限る。
+    pub fn counters_are_exact(&self) -> bool {
+        self.states.iter().all(|state| {
+            let Some((call, ret)) = state.calls.first() else {
+                return true;
+            };
+            let CallTarget::Count {
+                unrollable: true, ..
+            } = &call.target
+            else {
+                return true;
+            };
+
+            let has_branch = state.branches.iter().any(|(_, _, t)| t.is_some());
+            let has_branch = has_branch || !state.guards.is_empty();
+            if state.calls.len() > 1 || has_branch || !state.assoc.is_empty() {
+                return false;
+            }
+
+            !self.count_overlaps_return(call, *ret)
+        })
+    }
+
+    // 数える繰り返しの戻り先のステートが、数える要素でも遷移しうるかどうか。
+    // 戻り先が委譲や端点に定数を含む範囲で始まる場合は、要素を比べられないため遷移しうるとみなす。
+    pub fn count_overlaps_return(&self, call: &Call, ret: usize) -> bool {
+        let CallTarget::Count { counter, .. } = &call.target else {
+            return false;
         };
 
-        while let Some(closure) = context.unchecked.pop() {
-            let id = context.get_id(&closure).unwrap();
-            let branches = create_branches(&mut context, graph, &closure);
-            let state = &mut context.states[id];
-            state.branches = branches;
-            state.assoc = closure
-                .states
+        let ret = &self.states[ret];
+        if !ret.calls.is_empty() || !ret.guards.is_empty() {
+            return true;
+        }
+
+        let counted = &self.counters[*counter];
+        ret.branches
+            .iter()
+            .filter(|(_, _, t)| t.is_some())
+            .any(|(start, end, _)| {
+                counted
+                    .iter()
+                    .any(|(s, e)| intervals_overlap((start, end), (s.as_ref(), e.as_ref())))
+            })
+    }
+
+    // ステート `index` から要素による遷移や数える繰り返しで読みうる要素が、`intervals` と重なるかどうか。
+    // `sub!` や端点に定数を含む範囲で読む要素は展開時に分からないため、含めない。
+    pub fn reads_any(&self, index: usize, intervals: &[(Option<T>, Option<T>)]) -> bool {
+        let state = &self.states[index];
+        let branches = state
+            .branches
+            .iter()
+            .filter(|(_, _, t)| t.is_some())
+            .map(|(start, end, _)| (start, end));
+        let counted = state.calls.iter().filter_map(|(call, _)| match &call.target {
+            CallTarget::Count { counter, .. } => Some(&self.counters[*counter]),
+            CallTarget::Sub { .. } | CallTarget::Rule { .. } => None,
+        });
+        let counted = counted.flatten().map(|(s, e)| (s.as_ref(), e.as_ref()));
+
+        branches.chain(counted).any(|a| {
+            intervals
                 .iter()
-                .flat_map(|i| &graph.states[*i].assoc)
-                .copied()
-                .collect();
+                .any(|(s, e)| intervals_overlap(a, (s.as_ref(), e.as_ref())))
+        })
+    }
 
-            state.collects = closure
+    // ステート数が `max_states` を超えた時点で変換をやめる。
+    pub(super) fn from_nondeterministic(
+        graph: &nondeterministic::MatchGraph<T>,
+        max_states: usize,
+    ) -> Result<Self, TooManyStates> {
+        let mut context = ConvertionContext::new(graph);
+        context.state_for(BitSet::from_iter([0]));
+
+        while let Some(id) = context.unchecked.pop() {
+            if context.states.len() > max_states {
+                return Err(context.too_many_states());
+            }
+
+            let closure = context.closures.get(id).clone();
+            let branches = context.create_branches(&closure);
+            let guards = context.create_guards(&closure);
+            let nfa_states = || closure.states.iter().map(|(i, _)| &graph.states[*i]);
+
+            let call_begins = closure
                 .states
                 .iter()
-                .flat_map(|i| &graph.states[*i].collects)
-                .cloned()
+                .filter(|(i, _)| graph.states[*i].call.is_some())
+                .flat_map(|(_, marks)| context.sorted_marks(marks))
+                .filter_map(|m| match m {
+                    Mark::Begin(p) => Some(p),
+                    Mark::Assign(_) => None,
+                })
                 .collect();
 
-            state.props = closure
+            let accept_assigns: BTreeSet<_> = closure
                 .states
                 .iter()
-                .flat_map(|i| &graph.states[*i].props)
-                .cloned()
+                .filter(|(i, _)| !graph.states[*i].assoc.is_empty())
+                .flat_map(|(_, marks)| context.sorted_marks(marks))
+                .filter_map(|m| match m {
+                    Mark::Assign(a) => Some(a),
+                    Mark::Begin(_) => None,
+                })
                 .collect();
-        }
-
-        Self {
-            states: context.states,
-        }
-    }
-}
 
-fn create_branches<T: PatternChar>(
-    context: &mut ConvertionContext<T>,
-    graph: &nondeterministic::MatchGraph<T>,
-    state: &EpsilonClosure,
-) -> MatchBranches<T> {
-    let mut branches: nondeterministic::MatchBranches<T> = Default::default();
-
-    for &s in state.states.iter() {
-        branches.combine(&graph.states[s].branches)
-    }
+            let calls = nfa_states()
+                .filter_map(|s| s.call.clone())
+                .map(|(call, ret)| (call, context.state_for(BitSet::from_iter([ret]))))
+                .collect();
 
-    let mut map = IntervalMap::new();
-    for (min, max, s) in branches.iter() {
-        if s.is_empty() {
-            continue;
+            let state = &mut context.states[id];
+            state.branches = branches;
+            state.guards = guards;
+            state.assoc = nfa_states().flat_map(|s| &s.assoc).copied().collect();
+            state.collects = nfa_states().flat_map(|s| &s.collects).cloned().collect();
+            state.props = nfa_states().flat_map(|s| &s.props).cloned().collect();
+            state.call_begins = call_begins;
+            state.accept_assigns = accept_assigns.into_iter().collect();
+            state.calls = calls;
         }
 
-        let closure = epsilon_closure(graph, s.iter().copied());
-
-        let id = context.get_id(&closure);
-
-        let id = match id {
-            Some(v) => v,
-            None => context.register_and_push(closure),
-        };
+        if context.states.len() > max_states {
+            return Err(context.too_many_states());
+        }
 
-        map.insert_item((min.copied(), max.copied()), &id);
+        Ok(minimize::minimize(Self {
+            states: context.states,
+            values: graph.values.clone(),
+            counters: graph.counters.clone(),
+            guards: graph.guards.clone(),
+        }))
     }
-
-    MatchBranches { map }
 }
 
-fn epsilon_closure<T: PatternChar>(
-    graph: &nondeterministic::MatchGraph<T>,
-    state: impl IntoIterator<Item = usize>,
-) -> EpsilonClosure {
-    let mut reachable = BTreeSet::new();
-    let mut unchecked: Vec<_> = state.into_iter().collect();
-
-    while let Some(s) = unchecked.pop() {
-        if !reachable.insert(s) {
-            continue;
-        }
-
-        unchecked.extend_from_slice(&graph.states[s].epsilon_transitions);
-    }
+// 右半開区間どうしが重なるかどうか。`None` は端がないことを表す。
+fn intervals_overlap<T: Ord>(
+    (start0, end0): (Option<&T>, Option<&T>),
+    (start1, end1): (Option<&T>, Option<&T>),
+) -> bool {
+    let before =
+        |end: Option<&T>, start: Option<&T>| matches!((end, start), (Some(e), Some(s)) if e <= s);
+    !before(end0, start1) && !before(end1, start0)
+}
 
-    EpsilonClosure { states: reachable }
+// ε遷移で通過したcollectの入口と `set!`。
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum Mark {
+    Begin(MatchProp),
+    Assign(Assign),
 }
 
 #[derive(Debug)]
 pub struct MatchState<T: PatternChar> {
     branches: MatchBranches<T>,
+    guards: Vec<(usize, Transition)>,
     assoc: SortedVec<usize>,
     collects: HashSet<MatchProp>,
     props: HashSet<MatchProp>,
+    calls: Vec<(Call, usize)>,
+    call_begins: Vec<MatchProp>,
+    accept_assigns: Vec<Assign>,
 }
 
 impl<T: PatternChar> MatchState<T> {
     fn new() -> Self {
         Self {
             branches: MatchBranches::new(),
+            guards: Vec::new(),
             assoc: SortedVec::new(),
             collects: HashSet::new(),
             props: HashSet::new(),
+            calls: Vec::new(),
+            call_begins: Vec::new(),
+            accept_assigns: Vec::new(),
         }
     }
 
@@ -130,6 +246,12 @@ impl<T: PatternChar> MatchState<T> {
         &self.branches
     }
 
+    // 端点に定数を含む範囲の番号と、その範囲の要素による遷移。
+    // 範囲は `branches` の区間とも互いにも重ならないものとして扱い、重なりは生成するコードで検査する。
+    pub fn guards(&self) -> &[(usize, Transition)] {
+        &self.guards
+    }
+
     pub fn assoc(&self) -> &[usize] {
         &self.assoc
     }
@@ -141,11 +263,27 @@ impl<T: PatternChar> MatchState<T> {
     pub fn props(&self) -> &HashSet<MatchProp> {
         &self.props
     }
+
+    // `sub!` や再帰的な宣言への委譲と、委譲先が完了した後に進むステート。
+    // 委譲を含むステートは他の遷移を持てないため、正しい状態機械では高々一つ。
+    pub fn calls(&self) -> &[(Call, usize)] {
+        &self.calls
+    }
+
+    // 委譲に入る前に通過したcollectの入口。委譲先が消費する要素が新しい要素の始まりになる。
+    pub fn call_begins(&self) -> &[MatchProp] {
+        &self.call_begins
+    }
+
+    // 受理するまでにε遷移で通過した `set!`。
+    pub fn accept_assigns(&self) -> &[Assign] {
+        &self.accept_assigns
+    }
 }
 
 #[derive(Debug)]
 pub struct MatchBranches<T: PatternChar> {
-    map: IntervalMap<T, usize, Unique>,
+    map: IntervalMap<T, Transition, Unique>,
 }
 
 impl<T: PatternChar> MatchBranches<T> {
@@ -155,32 +293,266 @@ impl<T: PatternChar> MatchBranches<T> {
         }
     }
 
-    pub fn iter(&self) -> impl Iterator<Item = (Option<&T>, Option<&T>, &Option<usize>)> {
-        self.map.iter()
+    pub fn iter(&self) -> impl Iterator<Item = (Option<&T>, Option<&T>, Option<&Transition>)> {
+        self.map.iter().map(|(min, max, t)| (min, max, t.as_ref()))
     }
 }
 
-#[derive(Debug, Clone, Eq, PartialEq, PartialOrd, Ord)]
+#[derive(Debug, Clone)]
+pub struct Transition {
+    state: usize,
+    begins: Vec<MatchProp>,
+    assigns: Vec<Assign>,
+}
+
+impl Transition {
+    pub fn state(&self) -> usize {
+        self.state
+    }
+
+    // この遷移でcollectの対象に新たに入るフィールド。
+    pub fn begins(&self) -> &[MatchProp] {
+        &self.begins
+    }
+
+    // この遷移で行う代入。
+    pub fn assigns(&self) -> &[Assign] {
+        &self.assigns
+    }
+}
+
+// ステート数が上限を超えたこと。`origin` は最も多くのステートに含まれる、展開したパターンの番号。
+#[derive(Debug)]
+pub(super) struct TooManyStates {
+    pub(super) origin: Option<usize>,
+}
+
+// ε遷移で到達するNFAのステートと、そこまでに通過した印の番号。どちらも昇順に並べる。
+#[derive(Debug, PartialEq, Eq, Hash)]
 struct EpsilonClosure {
-    states: BTreeSet<usize>,
+    states: Vec<(usize, Vec<usize>)>,
 }
 
-struct ConvertionContext<T: PatternChar> {
+struct ConvertionContext<'a, T: PatternChar> {
+    graph: &'a nondeterministic::MatchGraph<T>,
     states: Vec<MatchState<T>>,
-    map: BTreeMap<EpsilonClosure, usize>,
-    unchecked: Vec<EpsilonClosure>,
+    // ε遷移で通過した印に振った番号。
+    marks: Interner<Mark>,
+    // NFAのステートそれぞれを起点とするε閉包。未計算の間は None。
+    reachable: Vec<Option<Rc<EpsilonClosure>>>,
+    // DFAのステートとなるε閉包。番号はDFAのステートの番号と一致する。
+    closures: Interner<Rc<EpsilonClosure>>,
+    // 要素による遷移先のNFAのステートの組から、DFAのステートを引く。
+    targets: HashMap<BitSet, usize>,
+    unchecked: Vec<usize>,
 }
 
-impl<T: PatternChar> ConvertionContext<T> {
-    fn get_id(&mut self, closure: &EpsilonClosure) -> Option<usize> {
-        self.map.get(closure).copied()
+impl<'a, T: PatternChar> ConvertionContext<'a, T> {
+    fn new(graph: &'a nondeterministic::MatchGraph<T>) -> Self {
+        Self {
+            graph,
+            states: Vec::new(),
+            marks: Interner::new(),
+            reachable: vec![None; graph.states.len()],
+            closures: Interner::new(),
+            targets: HashMap::new(),
+            unchecked: Vec::new(),
+        }
     }
 
-    fn register_and_push(&mut self, closure: EpsilonClosure) -> usize {
-        let id = self.states.len();
-        self.map.insert(closure.clone(), id);
-        self.unchecked.push(closure);
-        self.states.push(MatchState::new());
+    // NFAのステートの組 `targets` から始まるε閉包に対応するDFAのステート。
+    // 初めて現れたε閉包には新しいステートを割り当て、後で遷移を調べる。
+    fn state_for(&mut self, targets: BitSet) -> usize {
+        if let Some(&id) = self.targets.get(&targets) {
+            return id;
+        }
+
+        let states: Vec<_> = targets.iter().collect();
+        let mut iter = states.into_iter();
+        let first = self.closure_of(iter.next().unwrap());
+        let closure = match iter.next() {
+            None => first,
+            Some(second) => {
+                let mut merged: BTreeMap<usize, BTreeSet<usize>> = BTreeMap::new();
+                for s in [first, self.closure_of(second)] {
+                    merge_closure(&mut merged, &s);
+                }
+                for t in iter {
+                    let closure = self.closure_of(t);
+                    merge_closure(&mut merged, &closure);
+                }
+
+                let states = merged
+                    .into_iter()
+                    .map(|(s, marks)| (s, marks.into_iter().collect()))
+                    .collect();
+                Rc::new(EpsilonClosure { states })
+            }
+        };
+
+        let (id, is_new) = self.closures.intern(closure);
+        if is_new {
+            self.states.push(MatchState::new());
+            self.unchecked.push(id);
+        }
+        self.targets.insert(targets, id);
         id
     }
+
+    // ステート `state` からε遷移で到達するステートと、そこまでに通過した印を求める。
+    fn closure_of(&mut self, state: usize) -> Rc<EpsilonClosure> {
+        if let Some(closure) = &self.reachable[state] {
+            return closure.clone();
+        }
+
+        let mut reachable: BTreeMap<usize, BTreeSet<usize>> = BTreeMap::new();
+        let mut unchecked = vec![(state, self.marks_of(state))];
+        while let Some((s, marks)) = unchecked.pop() {
+            let is_new = !reachable.contains_key(&s);
+            let entry = reachable.entry(s).or_default();
+            let len = entry.len();
+            entry.extend(marks);
+            if !is_new && entry.len() == len {
+                continue;
+            }
+
+            let entry = entry.clone();
+            for &t in &self.graph.states[s].epsilon_transitions {
+                let mut marks = entry.clone();
+                marks.extend(self.marks_of(t));
+                unchecked.push((t, marks.into_iter().collect()));
+            }
+        }
+
+        let states = reachable
+            .into_iter()
+            .map(|(s, marks)| (s, marks.into_iter().collect()))
+            .collect();
+        let closure = Rc::new(EpsilonClosure { states });
+        self.reachable[state] = Some(closure.clone());
+        closure
+    }
+
+    // ステートを経由するときに通過する印の番号。
+    fn marks_of(&mut self, state: usize) -> Vec<usize> {
+        let state = &self.graph.states[state];
+        let begins = state.begins.iter().cloned().map(Mark::Begin);
+        let assigns = state.assigns.iter().cloned().map(Mark::Assign);
+        begins
+            .chain(assigns)
+            .map(|m| self.marks.intern(m).0)
+            .collect()
+    }
+
+    // 番号で表した印を、印そのものの順に並べる。
+    fn sorted_marks(&self, ids: &[usize]) -> Vec<Mark> {
+        let mut marks: Vec<_> = ids.iter().map(|&id| self.marks.get(id).clone()).collect();
+        marks.sort();
+        marks
+    }
+
+    fn create_branches(&mut self, closure: &EpsilonClosure) -> MatchBranches<T> {
+        // 遷移先と、その遷移で通過した入口のcollectや代入を区間ごとにまとめる。
+        // 印の番号はNFAのステートの数だけずらして、遷移先と同じ集合に入れる。
+        let offset = self.graph.states.len();
+        let mut branches = IntervalMap::<T, usize, Bits>::new();
+        for (s, marks) in &closure.states {
+            for (min, max, targets) in self.graph.states[*s].branches.iter() {
+                if targets.is_empty() {
+                    continue;
+                }
+
+                let items: Vec<_> = targets
+                    .iter()
+                    .copied()
+                    .chain(marks.iter().map(|m| offset + m))
+                    .collect();
+                branches.insert((min.copied(), max.copied()), &items);
+            }
+        }
+
+        let mut map = IntervalMap::new();
+        for (min, max, items) in branches.iter() {
+            let targets: BitSet = items.iter().take_while(|&i| i < offset).collect();
+            if targets.is_empty() {
+                continue;
+            }
+
+            let marks: Vec<_> = items
+                .iter()
+                .skip_while(|&i| i < offset)
+                .map(|i| i - offset)
+                .collect();
+            let transition = self.transition(targets, &marks);
+            map.insert_item((min.copied(), max.copied()), &transition);
+        }
+
+        MatchBranches { map }
+    }
+
+    // 端点に定数を含む範囲による遷移を、範囲ごとにまとめる。
+    fn create_guards(&mut self, closure: &EpsilonClosure) -> Vec<(usize, Transition)> {
+        let mut guards: BTreeMap<usize, (BitSet, BTreeSet<usize>)> = BTreeMap::new();
+        for (s, marks) in &closure.states {
+            for &(guard, target) in &self.graph.states[*s].guards {
+                let (targets, all_marks) = guards.entry(guard).or_default();
+                targets.insert(target);
+                all_marks.extend(marks);
+            }
+        }
+
+        guards
+            .into_iter()
+            .map(|(guard, (targets, marks))| {
+                let marks: Vec<_> = marks.into_iter().collect();
+                (guard, self.transition(targets, &marks))
+            })
+            .collect()
+    }
+
+    // 遷移先のNFAのステートの組と、遷移までに通過した印から遷移を作る。
+    fn transition(&mut self, targets: BitSet, marks: &[usize]) -> Transition {
+        let mut begins = Vec::new();
+        let mut assigns = Vec::new();
+        for mark in self.sorted_marks(marks) {
+            match mark {
+                Mark::Begin(p) => begins.push(p),
+                Mark::Assign(a) => assigns.push(a),
+            }
+        }
+
+        Transition {
+            state: self.state_for(targets),
+            begins,
+            assigns,
+        }
+    }
+
+    // 作ったステートごとに含まれる展開したパターンを数え、最も多いものを原因とする。
+    // 同数なら先に挿入した外側のパターンを選ぶ。
+    fn too_many_states(&self) -> TooManyStates {
+        let mut counts = BTreeMap::new();
+        for closure in self.closures.values() {
+            let origins: BTreeSet<_> = closure
+                .states
+                .iter()
+                .filter_map(|(i, _)| self.graph.states[*i].origin)
+                .collect();
+            for origin in origins {
+                *counts.entry(origin).or_insert(0usize) += 1;
+            }
+        }
+
+        let origin = counts
+            .into_iter()
+            .max_by(|(o0, c0), (o1, c1)| c0.cmp(c1).then(o1.cmp(o0)))
+            .map(|(origin, _)| origin);
+        TooManyStates { origin }
+    }
+}
+
+fn merge_closure(merged: &mut BTreeMap<usize, BTreeSet<usize>>, closure: &EpsilonClosure) {
+    for (s, marks) in &closure.states {
+        merged.entry(*s).or_default().extend(marks);
+    }
 }
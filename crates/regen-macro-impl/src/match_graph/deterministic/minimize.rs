@@ -0,0 +1,223 @@
+use super::{MatchBranches, MatchGraph, MatchState, Transition};
+use crate::{
+    match_graph::{Assign, MatchProp},
+    pattern_char::PatternChar,
+    util::{Interner, IntervalMap},
+};
+use std::collections::BTreeMap;
+
+// 遷移先と、遷移で行うcollectや代入に振った番号。
+type Branches<T> = Vec<(Option<T>, Option<T>, Option<(usize, usize)>)>;
+
+// 遷移以外で区別されるステートの性質。
+// 委譲を含むステートは委譲先を比べられないため、常に区別する。
+// 端点に定数を含む範囲で遷移するステートも、その遷移を比べないため常に区別する。
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+struct StateKey {
+    assoc: Vec<usize>,
+    collects: Vec<MatchProp>,
+    props: Vec<MatchProp>,
+    call_begins: Vec<MatchProp>,
+    accept_assigns: Vec<Assign>,
+    call: Option<usize>,
+}
+
+impl StateKey {
+    fn new<T: PatternChar>(index: usize, state: &MatchState<T>) -> Self {
+        let mut collects: Vec<_> = state.collects.iter().cloned().collect();
+        collects.sort();
+        let mut props: Vec<_> = state.props.iter().cloned().collect();
+        props.sort();
+
+        Self {
+            assoc: state.assoc.to_vec(),
+            collects,
+            props,
+            call_begins: state.call_begins.clone(),
+            accept_assigns: state.accept_assigns.clone(),
+            call: (!state.calls.is_empty() || !state.guards.is_empty()).then_some(index),
+        }
+    }
+}
+
+// Mooreのアルゴリズムで等価なステートをまとめる。
+// 受理するパターンやcollectの対象で初めに分け、遷移先の組とその遷移で行うcollectや代入で細かくしていく。
+pub(super) fn minimize<T: PatternChar>(graph: MatchGraph<T>) -> MatchGraph<T> {
+    let states = &graph.states;
+    let keys: Vec<_> = states
+        .iter()
+        .enumerate()
+        .map(|(i, s)| StateKey::new(i, s))
+        .collect();
+    let mut classes = numbering(&keys);
+    let mut count = classes.iter().max().map_or(0, |c| c + 1);
+
+    // 遷移で行うcollectや代入は毎回比べずに済むよう、先に番号に置き換えておく。
+    let mut labels = Interner::new();
+    let edges: Vec<Branches<T>> = states
+        .iter()
+        .map(|s| {
+            s.branches
+                .iter()
+                .map(|(min, max, t)| {
+                    let t = t.map(|t| {
+                        let (label, _) = labels.intern((t.begins.clone(), t.assigns.clone()));
+                        (t.state, label)
+                    });
+                    (min.copied(), max.copied(), t)
+                })
+                .collect()
+        })
+        .collect();
+
+    loop {
+        let signatures: Vec<_> = edges
+            .iter()
+            .enumerate()
+            .map(|(i, e)| (classes[i], branches(e, &classes)))
+            .collect();
+        let refined = numbering(&signatures);
+        let refined_count = refined.iter().max().map_or(0, |c| c + 1);
+        classes = refined;
+
+        if refined_count == count {
+            break;
+        }
+        count = refined_count;
+    }
+
+    // 番号は最初に現れた順に振っているため、初期状態は常に0になる。
+    let mut representatives = vec![None; count];
+    for (i, &c) in classes.iter().enumerate() {
+        representatives[c].get_or_insert(i);
+    }
+
+    let states = representatives
+        .into_iter()
+        .map(|i| {
+            let i = i.unwrap();
+            let state = &states[i];
+            let mut map = IntervalMap::new();
+            for (min, max, t) in branches(&edges[i], &classes) {
+                let Some((state, label)) = t else {
+                    continue;
+                };
+                let (begins, assigns): &(Vec<MatchProp>, Vec<Assign>) = labels.get(label);
+                let transition = Transition {
+                    state,
+                    begins: begins.clone(),
+                    assigns: assigns.clone(),
+                };
+                map.insert_item((min, max), &transition);
+            }
+
+            let guards = state
+                .guards
+                .iter()
+                .map(|(guard, t)| {
+                    let transition = Transition {
+                        state: classes[t.state],
+                        ..t.clone()
+                    };
+                    (*guard, transition)
+                })
+                .collect();
+
+            MatchState {
+                branches: MatchBranches { map },
+                guards,
+                assoc: state.assoc.iter().copied().collect(),
+                collects: state.collects.clone(),
+                props: state.props.clone(),
+                calls: state
+                    .calls
+                    .iter()
+                    .map(|(call, ret)| (call.clone(), classes[*ret]))
+                    .collect(),
+                call_begins: state.call_begins.clone(),
+                accept_assigns: state.accept_assigns.clone(),
+            }
+        })
+        .collect();
+
+    MatchGraph {
+        states,
+        values: graph.values,
+        counters: graph.counters,
+        guards: graph.guards,
+    }
+}
+
+// 遷移先を分類の番号に置き換え、隣り合う同じ遷移の区間をつなげる。
+fn branches<T: PatternChar>(edges: &Branches<T>, classes: &[usize]) -> Branches<T> {
+    let mut buf: Branches<T> = Vec::new();
+    for &(min, max, t) in edges {
+        let t = t.map(|(state, label)| (classes[state], label));
+        match buf.last_mut() {
+            Some((_, last_max, last)) if *last == t => *last_max = max,
+            _ => buf.push((min, max, t)),
+        }
+    }
+    buf
+}
+
+// 等しい値に同じ番号を、最初に現れた順に振る。
+fn numbering<K: Ord>(keys: &[K]) -> Vec<usize> {
+    let mut map = BTreeMap::new();
+    keys.iter()
+        .map(|k| {
+            let len = map.len();
+            *map.entry(k).or_insert(len)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        match_graph::Builder,
+        pattern::{Pattern, ResolveEnv},
+    };
+
+    fn state_count(patterns: &[syn::Expr]) -> usize {
+        let env = ResolveEnv::<char>::empty();
+        let mut builder = Builder::new();
+        for (assoc, expr) in patterns.iter().enumerate() {
+            let pattern = env.resolve(&Pattern::new(expr).unwrap()).unwrap();
+            builder.add(assoc, &pattern);
+        }
+        builder.build().unwrap().states().len()
+    }
+
+    #[test]
+    fn test_minimize() {
+        // 'a' の後と 'b' の後は同じ振る舞いになる。
+        assert_eq!(state_count(&[syn::parse_quote!(('a' | 'b') + 'c')]), 3);
+        assert_eq!(
+            state_count(&[syn::parse_quote!(("ac" | "bc") + ['d'; ..])]),
+            3
+        );
+        assert_eq!(state_count(&[syn::parse_quote!("abc" | "xbc")]), 4);
+        // 受理するパターンが異なるステートはまとめない。
+        assert_eq!(
+            state_count(&[syn::parse_quote!("ab"), syn::parse_quote!("cb")]),
+            5
+        );
+        // collectの対象が異なるステートはまとめない。
+        assert_eq!(
+            state_count(&[syn::parse_quote!(collect!(x <- 'a') + 'c' | 'b' + 'c')]),
+            4
+        );
+    }
+
+    #[test]
+    fn test_minimize_complex() {
+        let pattern: syn::Expr = syn::parse_quote! {
+            collect!(digits <- ['0'..='9'; 1..])
+                | ("0" + collect!(radix <- "b") + collect!(digits <- [('0' | '1'); 1..]))
+                | ("0" + collect!(radix <- "o") + collect!(digits <- ['0'..='7'; 1..]))
+                | ("0" + collect!(radix <- "x") + collect!(digits <- [('0'..='9') | ('A'..='F') | ('a'..='f'); 1..]))
+        };
+        assert_eq!(state_count(&[pattern]), 9);
+    }
+}
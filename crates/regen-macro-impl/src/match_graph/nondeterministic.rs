@@ -1,5 +1,5 @@
 use crate::{
-    match_graph::MatchProp,
+    match_graph::{Assign, Call, MatchProp, rules::Intervals},
     pattern_char::PatternChar,
     util::{IntervalMap, interval_map::Interval},
 };
@@ -10,6 +10,9 @@ use std::collections::BTreeSet;
 #[derive(Debug)]
 pub struct MatchGraph<T: PatternChar> {
     pub(super) states: Vec<MatchState<T>>,
+    pub(super) values: Vec<syn::Expr>,
+    pub(super) counters: Vec<Intervals<T>>,
+    pub(super) guards: Vec<syn::ExprRange>,
 }
 
 #[derive(Debug)]
@@ -19,6 +22,16 @@ pub(super) struct MatchState<T: PatternChar> {
     pub(super) assoc: Vec<usize>,
     pub(super) collects: Vec<MatchProp>,
     pub(super) props: Vec<MatchProp>,
+    // このステートを経由する遷移で、collectの対象に新たに入るフィールド。
+    pub(super) begins: Vec<MatchProp>,
+    // このステートを経由する遷移や受理で行う代入。
+    pub(super) assigns: Vec<Assign>,
+    // 端点に定数を含む範囲の番号と、その範囲の要素による遷移先。
+    pub(super) guards: Vec<(usize, usize)>,
+    // 委譲先と、委譲先が完了した後に進むステート。
+    pub(super) call: Option<(Call, usize)>,
+    // このステートを展開したパターンの番号。`Builder::origins` で位置を引く。
+    pub(super) origin: Option<usize>,
 }
 
 impl<T: PatternChar> Default for MatchState<T> {
@@ -29,6 +42,11 @@ impl<T: PatternChar> Default for MatchState<T> {
             assoc: Default::default(),
             collects: Default::default(),
             props: Default::default(),
+            begins: Default::default(),
+            assigns: Default::default(),
+            guards: Default::default(),
+            call: Default::default(),
+            origin: Default::default(),
         }
     }
 }
@@ -49,10 +67,6 @@ impl<T: PatternChar> MatchBranches<T> {
     pub fn iter(&self) -> impl Iterator<Item = (Option<&T>, Option<&T>, &BTreeSet<usize>)> {
         self.map.iter()
     }
-
-    pub fn combine(&mut self, other: &Self) {
-        self.map.combine(&other.map);
-    }
 }
 
 impl<T: PatternChar> Default for MatchBranches<T> {
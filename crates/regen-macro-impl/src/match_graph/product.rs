@@ -0,0 +1,101 @@
+use super::deterministic::MatchGraph;
+use crate::{
+    pattern_char::PatternChar,
+    util::{IntervalMap, interval_map::store::Set},
+};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProductOp {
+    Intersection,
+    Difference,
+}
+
+// 二つのDFAの直積。右辺が受理しなくなった（死んだ）状態は None で表す。
+pub(super) struct Product<T: PatternChar> {
+    pub(super) states: Vec<ProductState<T>>,
+}
+
+pub(super) struct ProductState<T: PatternChar> {
+    pub(super) branches: Vec<(Option<T>, Option<T>, usize)>,
+    pub(super) accept: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Side {
+    Lhs(usize),
+    Rhs(usize),
+}
+
+impl<T: PatternChar> Product<T> {
+    pub(super) fn new(lhs: &MatchGraph<T>, rhs: &MatchGraph<T>, op: ProductOp) -> Self {
+        let initial = (0, Some(0));
+        let mut map = HashMap::from([(initial, 0)]);
+        let mut unchecked = vec![initial];
+        let mut states = vec![ProductState {
+            branches: Vec::new(),
+            accept: false,
+        }];
+
+        while let Some(pair @ (l, r)) = unchecked.pop() {
+            let id = map[&pair];
+            let lhs_state = &lhs.states()[l];
+            let rhs_state = r.map(|r| &rhs.states()[r]);
+
+            let mut sides = IntervalMap::<T, Side, Set>::new();
+            for (start, end, t) in lhs_state.branches().iter() {
+                if let Some(t) = t {
+                    sides.insert_item((start.copied(), end.copied()), &Side::Lhs(t.state()));
+                }
+            }
+            for (start, end, t) in rhs_state.iter().flat_map(|s| s.branches().iter()) {
+                if let Some(t) = t {
+                    sides.insert_item((start.copied(), end.copied()), &Side::Rhs(t.state()));
+                }
+            }
+
+            let mut branches = Vec::new();
+            for (start, end, s) in sides.iter() {
+                let mut lhs_target = None;
+                let mut rhs_target = None;
+                for side in s {
+                    match side {
+                        Side::Lhs(t) => lhs_target = Some(*t),
+                        Side::Rhs(t) => rhs_target = Some(*t),
+                    }
+                }
+
+                let Some(lhs_target) = lhs_target else {
+                    continue;
+                };
+
+                if op == ProductOp::Intersection && rhs_target.is_none() {
+                    continue;
+                }
+
+                let target = (lhs_target, rhs_target);
+                let target_id = *map.entry(target).or_insert_with(|| {
+                    unchecked.push(target);
+                    states.push(ProductState {
+                        branches: Vec::new(),
+                        accept: false,
+                    });
+                    states.len() - 1
+                });
+
+                branches.push((start.copied(), end.copied(), target_id));
+            }
+
+            let lhs_accept = !lhs_state.assoc().is_empty();
+            let rhs_accept = rhs_state.is_some_and(|s| !s.assoc().is_empty());
+            let state = &mut states[id];
+            state.branches = branches;
+            state.accept = match op {
+                ProductOp::Intersection => lhs_accept && rhs_accept,
+                ProductOp::Difference => lhs_accept && !rhs_accept,
+            };
+        }
+
+        Self { states }
+    }
+}
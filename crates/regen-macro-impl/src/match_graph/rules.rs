@@ -0,0 +1,160 @@
+use super::{Builder, CallTarget, MatchPattern, deterministic::MatchGraph};
+use crate::pattern_char::PatternChar;
+
+pub(super) type Intervals<T> = Vec<(Option<T>, Option<T>)>;
+
+// `rec` で宣言されたパターンをそれぞれDFAにしたもの。
+// 委譲先は先頭の要素から決めるため、各パターンは空列にマッチしてはならず、左再帰も持てない。
+pub struct Rules<T: PatternChar> {
+    graphs: Vec<MatchGraph<T>>,
+    firsts: Vec<Intervals<T>>,
+    max_states: usize,
+}
+
+impl<T: PatternChar> Rules<T> {
+    pub fn new(
+        rules: &[(syn::Ident, impl MatchPattern<T>)],
+        max_states: usize,
+    ) -> syn::Result<Self> {
+        // 先頭の要素を求めるため、まず委譲をε遷移で表したDFAを作る。
+        let graphs = rules
+            .iter()
+            .map(|(_, pattern)| {
+                let mut builder = Builder::new();
+                builder.set_max_states(max_states);
+                builder.add(0, pattern);
+                builder.build()
+            })
+            .collect::<syn::Result<Vec<_>>>()?;
+
+        for ((name, _), graph) in rules.iter().zip(&graphs) {
+            if !graph.states()[0].assoc().is_empty() {
+                return Err(syn::Error::new(
+                    name.span(),
+                    "Recursive declarations must not match an empty sequence.",
+                ));
+            }
+        }
+
+        let mut firsts = vec![None; rules.len()];
+        let mut visiting = vec![false; rules.len()];
+        for i in 0..rules.len() {
+            first(&graphs, i, &mut visiting, &mut firsts)?;
+        }
+        let firsts: Vec<_> = firsts.into_iter().map(Option::unwrap).collect();
+
+        let graphs = rules
+            .iter()
+            .map(|(_, pattern)| {
+                let mut builder = Builder::with_firsts(firsts.clone());
+                builder.set_max_states(max_states);
+                builder.add(0, pattern);
+                builder.build()
+            })
+            .collect::<syn::Result<_>>()?;
+
+        Ok(Self {
+            graphs,
+            firsts,
+            max_states,
+        })
+    }
+
+    pub fn graphs(&self) -> &[MatchGraph<T>] {
+        &self.graphs
+    }
+
+    // 宣言の先頭に現れうる要素。
+    pub fn first(&self, index: usize) -> &[(Option<T>, Option<T>)] {
+        &self.firsts[index]
+    }
+
+    // 宣言がマッチした後に、さらに読み進めうる要素。
+    // 受理状態からの遷移に加え、戻り先が受理状態である委譲では、委譲先が読み進めうる要素も含める。
+    pub fn continuations(&self, index: usize) -> Intervals<T> {
+        let mut visited = vec![false; self.graphs.len()];
+        let mut intervals = Vec::new();
+        self.collect_continuations(index, &mut visited, &mut intervals);
+        intervals
+    }
+
+    fn collect_continuations(
+        &self,
+        index: usize,
+        visited: &mut [bool],
+        intervals: &mut Intervals<T>,
+    ) {
+        if visited[index] {
+            return;
+        }
+        visited[index] = true;
+
+        let states = self.graphs[index].states();
+        for state in states {
+            if !state.assoc().is_empty() {
+                let branches = state.branches().iter().filter(|(_, _, t)| t.is_some());
+                intervals.extend(branches.map(|(start, end, _)| (start.copied(), end.copied())));
+            }
+
+            for (call, ret) in state.calls() {
+                if let CallTarget::Rule { index: callee, .. } = &call.target
+                    && !states[*ret].assoc().is_empty()
+                {
+                    self.collect_continuations(*callee, visited, intervals);
+                }
+            }
+        }
+    }
+
+    // 全ての宣言のステートを通し番号にしたときの番号。
+    pub fn state_id(&self, rule: usize, state: usize) -> usize {
+        let offset: usize = self.graphs[..rule].iter().map(|g| g.states().len()).sum();
+        offset + state
+    }
+
+    // 宣言への委譲を、先頭の要素による遷移として挿入するビルダー。
+    pub fn builder(&self) -> Builder<T> {
+        let mut builder = Builder::with_firsts(self.firsts.clone());
+        builder.set_max_states(self.max_states);
+        builder
+    }
+}
+
+fn first<T: PatternChar>(
+    graphs: &[MatchGraph<T>],
+    index: usize,
+    visiting: &mut [bool],
+    firsts: &mut [Option<Intervals<T>>],
+) -> syn::Result<Intervals<T>> {
+    if let Some(f) = &firsts[index] {
+        return Ok(f.clone());
+    }
+
+    visiting[index] = true;
+    let initial = &graphs[index].states()[0];
+    let mut intervals: Intervals<T> = initial
+        .branches()
+        .iter()
+        .filter(|(_, _, t)| t.is_some())
+        .map(|(start, end, _)| (start.copied(), end.copied()))
+        .collect();
+
+    for (call, _) in initial.calls() {
+        let CallTarget::Rule { index: callee, .. } = &call.target else {
+            continue;
+        };
+
+        if visiting[*callee] {
+            return Err(syn::Error::new(
+                call.span,
+                "Left recursion is not supported.",
+            ));
+        }
+
+        intervals.extend(first(graphs, *callee, visiting, firsts)?);
+    }
+
+    visiting[index] = false;
+    firsts[index] = Some(intervals.clone());
+    Ok(intervals)
+}
@@ -1,25 +1,57 @@
+mod assign;
 mod atom;
+mod call;
 mod cls;
 mod collect;
+mod count;
+mod difference;
+mod ignore_case;
+mod intersection;
 mod join;
+mod many;
+mod not;
+mod opt;
 mod or;
+mod rec;
 mod repeat;
+mod sep_by;
 mod seq;
+mod set;
+mod sub;
 
-use crate::declares::Declares;
+use crate::declares::{Declare, Declares};
+use crate::eval::is_const_range;
+use crate::util::edit_distance;
+use crate::warning::Warning;
 use crate::{match_graph::MatchPattern, pattern_char::PatternChar};
 use __internal::{BelongTo, PatternKind};
+use proc_macro2::Span;
 use std::collections::HashMap;
 use std::ops::Bound;
+use std::rc::Rc;
+use syn::punctuated::Punctuated;
 use syn::spanned::Spanned;
 
+pub use assign::PatternAssign;
 pub use atom::PatternAtom;
+pub use call::PatternCall;
 pub use cls::PatternClass;
 pub use collect::PatternCollect;
+pub use count::{CountElement, PatternCount, PatternCounted};
+pub use difference::PatternDifference;
+pub use ignore_case::PatternIgnoreCase;
+pub use intersection::PatternIntersection;
 pub use join::PatternJoin;
+pub use many::{PatternMany0, PatternMany1};
+pub use not::PatternNot;
+pub use opt::PatternOpt;
 pub use or::PatternOr;
+pub use rec::PatternRec;
 pub use repeat::PatternRepeat;
+pub use sep_by::PatternSepBy;
 pub use seq::PatternSeq;
+pub use set::PatternSet;
+pub use sub::PatternSub;
 
 #[derive(Debug, Clone)]
 pub struct PatternTag;
@@ -31,11 +63,23 @@ impl PatternKind for PatternTag {
 pub enum Pattern<T: PatternChar> {
     Atom(PatternAtom<T>),
     Class(PatternClass),
+    Call(PatternCall<T>),
     Seq(PatternSeq<T>),
     Join(Box<PatternJoin<T>>),
     Or(Box<PatternOr<T>>),
     Repeat(Box<PatternRepeat<T>>),
     Collect(Box<PatternCollect<T>>),
+    Not(Box<PatternNot<T>>),
+    Intersection(Box<PatternIntersection<T>>),
+    Difference(Box<PatternDifference<T>>),
+    IgnoreCase(Box<PatternIgnoreCase<T>>),
+    Opt(Box<PatternOpt<T>>),
+    Many0(Box<PatternMany0<T>>),
+    Many1(Box<PatternMany1<T>>),
+    SepBy(Box<PatternSepBy<T>>),
+    Sub(PatternSub),
+    Assign(PatternAssign),
+    Count(Box<PatternCount<T>>),
 }
 
 impl<T: PatternChar> BelongTo for Pattern<T> {
@@ -57,6 +101,9 @@ impl<T: PatternChar> Pattern<T> {
             syn::Expr::Path(e) => Pattern::Class(PatternClass {
                 path: e.path.clone(),
             }),
+            syn::Expr::Range(range) if is_const_range(expr) => {
+                Pattern::Count(Box::new(PatternCount::from_range(range)))
+            }
             syn::Expr::Range(range) => {
                 let start = match &range.start {
                     Some(e) => {
@@ -86,19 +133,34 @@ impl<T: PatternChar> Pattern<T> {
                 match &e.op {
                     syn::BinOp::Add(_) => Pattern::Join(Box::new(PatternJoin { lhs, rhs })),
                     syn::BinOp::BitOr(_) => Pattern::Or(Box::new(PatternOr { lhs, rhs })),
+                    syn::BinOp::BitAnd(_) => Pattern::Intersection(Box::new(PatternIntersection {
+                        lhs,
+                        rhs,
+                        span: e.span(),
+                    })),
+                    syn::BinOp::Sub(_) => Pattern::Difference(Box::new(PatternDifference {
+                        lhs,
+                        rhs,
+                        span: e.span(),
+                    })),
                     _ => {
                         return Err(syn::Error::new(
                             e.span(),
-                            "Unexpected operator. `+` or `|` was expected.",
+                            "Unexpected operator. `+`, `|`, `&` or `-` was expected.",
                         ));
                     }
                 }
             }
             syn::Expr::Array(e) => Pattern::Seq(PatternSeq::from_array(e)?),
+            syn::Expr::Call(e) => Pattern::Call(PatternCall::from_call(e)?),
             syn::Expr::Macro(e) => {
                 let ident = e.mac.path.require_ident()?;
 
-                if ident == "repeat" {
+                if ident == "repeat" && is_const_repeat(&e.mac) {
+                    PatternCount::from_mac(&e.mac)
+                        .map(Box::new)
+                        .map(Pattern::Count)?
+                } else if ident == "repeat" {
                     PatternRepeat::from_mac(&e.mac)
                         .map(Box::new)
                         .map(Pattern::Repeat)?
@@ -106,11 +168,51 @@ impl<T: PatternChar> Pattern<T> {
                     PatternCollect::from_mac(&e.mac)
                         .map(Box::new)
                         .map(Pattern::Collect)?
+                } else if ident == "not" {
+                    PatternNot::from_mac(&e.mac)
+                        .map(Box::new)
+                        .map(Pattern::Not)?
+                } else if ident == "opt" {
+                    PatternOpt::from_mac(&e.mac)
+                        .map(Box::new)
+                        .map(Pattern::Opt)?
+                } else if ident == "many0" {
+                    PatternMany0::from_mac(&e.mac)
+                        .map(Box::new)
+                        .map(Pattern::Many0)?
+                } else if ident == "many1" {
+                    PatternMany1::from_mac(&e.mac)
+                        .map(Box::new)
+                        .map(Pattern::Many1)?
+                } else if ident == "sep_by" {
+                    PatternSepBy::from_mac(&e.mac)
+                        .map(Box::new)
+                        .map(Pattern::SepBy)?
+                } else if ident == "sub" {
+                    PatternSub::from_mac(&e.mac).map(Pattern::Sub)?
+                } else if ident == "set" {
+                    PatternAssign::from_mac(&e.mac).map(Pattern::Assign)?
+                } else if ident == "ignore_case" {
+                    PatternIgnoreCase::from_mac(&e.mac)
+                        .map(Box::new)
+                        .map(Pattern::IgnoreCase)?
                 } else {
                     return Err(syn::Error::new(e.span(), "Unexpected pattern."));
                 }
             }
+            syn::Expr::Unary(e) => match &e.op {
+                syn::UnOp::Not(_) => PatternNot::from_unary(e).map(Box::new).map(Pattern::Not)?,
+                _ => {
+                    return Err(syn::Error::new(
+                        e.span(),
+                        "Unexpected operator. `!` was expected.",
+                    ));
+                }
+            },
             syn::Expr::Paren(e) => Pattern::new(&e.expr)?,
+            syn::Expr::Repeat(e) if is_const_range(&e.len) => PatternCount::from_repeat(e)
+                .map(Box::new)
+                .map(Pattern::Count)?,
             syn::Expr::Repeat(e) => PatternRepeat::from_repeat(e)
                 .map(Box::new)
                 .map(Pattern::Repeat)?,
@@ -119,6 +221,68 @@ impl<T: PatternChar> Pattern<T> {
 
         Ok(p)
     }
+
+    // パターンの中で変数として参照される名前。組み込みのクラスも含む。
+    pub fn references(&self) -> Vec<&syn::Ident> {
+        let mut names = Vec::new();
+        self.collect_references(&mut names);
+        names
+    }
+
+    fn collect_references<'a>(&'a self, names: &mut Vec<&'a syn::Ident>) {
+        match self {
+            Pattern::Atom(_) | Pattern::Sub(_) | Pattern::Assign(_) => {}
+            Pattern::Class(p) => names.extend(p.path.get_ident()),
+            Pattern::Call(p) => {
+                names.push(&p.name);
+                for arg in &p.args {
+                    arg.collect_references(names);
+                }
+            }
+            Pattern::Seq(p) => {
+                for p in &p.patterns {
+                    p.collect_references(names);
+                }
+            }
+            Pattern::Join(p) => {
+                p.lhs.collect_references(names);
+                p.rhs.collect_references(names);
+            }
+            Pattern::Or(p) => {
+                p.lhs.collect_references(names);
+                p.rhs.collect_references(names);
+            }
+            Pattern::Intersection(p) => {
+                p.lhs.collect_references(names);
+                p.rhs.collect_references(names);
+            }
+            Pattern::Difference(p) => {
+                p.lhs.collect_references(names);
+                p.rhs.collect_references(names);
+            }
+            Pattern::Repeat(p) => p.pattern.collect_references(names),
+            Pattern::Collect(p) => p.pattern.collect_references(names),
+            Pattern::Not(p) => p.pattern.collect_references(names),
+            Pattern::IgnoreCase(p) => p.pattern.collect_references(names),
+            Pattern::Opt(p) => p.pattern.collect_references(names),
+            Pattern::Many0(p) => p.pattern.collect_references(names),
+            Pattern::Many1(p) => p.pattern.collect_references(names),
+            Pattern::SepBy(p) => {
+                p.pattern.collect_references(names);
+                p.separator.collect_references(names);
+            }
+            Pattern::Count(p) => {
+                if let CountElement::Pattern(p) = &p.element {
+                    p.collect_references(names);
+                }
+            }
+        }
+    }
+}
+
+fn is_const_repeat(mac: &syn::Macro) -> bool {
+    let args = mac.parse_body_with(Punctuated::<syn::Expr, syn::Token![,]>::parse_terminated);
+    args.is_ok_and(|args| args.len() == 2 && is_const_range(&args[1]))
 }
 
 fn expect_lit(e: &syn::Expr) -> Result<&syn::Lit, syn::Error> {
@@ -141,17 +305,194 @@ impl PatternKind for ResolvedPatternTag {
 #[derive(Debug, Clone)]
 pub enum ResolvedPattern<T: PatternChar> {
     Atom(PatternAtom<T>),
+    Set(PatternSet<T>),
     Seq(PatternSeq<T, ResolvedPatternTag>),
     Join(Box<PatternJoin<T, ResolvedPatternTag>>),
     Or(Box<PatternOr<T, ResolvedPatternTag>>),
     Repeat(Box<PatternRepeat<T, ResolvedPatternTag>>),
     Collect(Box<PatternCollect<T, ResolvedPatternTag>>),
+    Intersection(Box<PatternIntersection<T, ResolvedPatternTag>>),
+    Difference(Box<PatternDifference<T, ResolvedPatternTag>>),
+    Opt(Box<PatternOpt<T, ResolvedPatternTag>>),
+    Many0(Box<PatternMany0<T, ResolvedPatternTag>>),
+    Many1(Box<PatternMany1<T, ResolvedPatternTag>>),
+    SepBy(Box<PatternSepBy<T, ResolvedPatternTag>>),
+    Sub(PatternSub),
+    Rec(PatternRec),
+    Assign(PatternAssign),
+    Counted(Box<PatternCounted<T>>),
 }
 
 impl<T: PatternChar> BelongTo for ResolvedPattern<T> {
     type Kind = ResolvedPatternTag;
 }
 
+impl<T: PatternChar> ResolvedPattern<T> {
+    // 一要素にのみマッチするパターンであれば、その要素の集合を返す。
+    pub fn to_set(&self) -> Option<PatternSet<T>> {
+        match self {
+            ResolvedPattern::Atom(p) => Some(PatternSet::from_atom(p)),
+            ResolvedPattern::Set(p) => Some(p.clone()),
+            ResolvedPattern::Seq(p) => match p.patterns.as_slice() {
+                [p] => p.to_set(),
+                _ => None,
+            },
+            ResolvedPattern::Or(p) => {
+                let lhs = p.lhs.to_set()?;
+                let rhs = p.rhs.to_set()?;
+                Some(lhs.union(&rhs))
+            }
+            ResolvedPattern::Join(_)
+            | ResolvedPattern::Repeat(_)
+            | ResolvedPattern::Collect(_)
+            | ResolvedPattern::Intersection(_)
+            | ResolvedPattern::Difference(_)
+            | ResolvedPattern::Opt(_)
+            | ResolvedPattern::Many0(_)
+            | ResolvedPattern::Many1(_)
+            | ResolvedPattern::SepBy(_)
+            | ResolvedPattern::Sub(_)
+            | ResolvedPattern::Rec(_)
+            | ResolvedPattern::Assign(_)
+            | ResolvedPattern::Counted(_) => None,
+        }
+    }
+
+    // リテラルと範囲を、大文字小文字を同一視した集合に置き換える。
+    pub fn ignore_case(&self) -> Self {
+        match self {
+            ResolvedPattern::Atom(p) => {
+                ResolvedPattern::Set(PatternSet::from_atom(p).ignore_case())
+            }
+            ResolvedPattern::Set(p) => ResolvedPattern::Set(p.ignore_case()),
+            ResolvedPattern::Seq(p) => ResolvedPattern::Seq(PatternSeq {
+                patterns: p.patterns.iter().map(|p| p.ignore_case()).collect(),
+            }),
+            ResolvedPattern::Join(p) => ResolvedPattern::Join(Box::new(PatternJoin {
+                lhs: p.lhs.ignore_case(),
+                rhs: p.rhs.ignore_case(),
+            })),
+            ResolvedPattern::Or(p) => ResolvedPattern::Or(Box::new(PatternOr {
+                lhs: p.lhs.ignore_case(),
+                rhs: p.rhs.ignore_case(),
+            })),
+            ResolvedPattern::Repeat(p) => ResolvedPattern::Repeat(Box::new(PatternRepeat {
+                pattern: p.pattern.ignore_case(),
+                start: p.start,
+                end: p.end,
+                span: p.span,
+            })),
+            ResolvedPattern::Collect(p) => ResolvedPattern::Collect(Box::new(PatternCollect {
+                pattern: p.pattern.ignore_case(),
+                field: p.field.clone(),
+            })),
+            ResolvedPattern::Intersection(p) => {
+                ResolvedPattern::Intersection(Box::new(PatternIntersection {
+                    lhs: p.lhs.ignore_case(),
+                    rhs: p.rhs.ignore_case(),
+                    span: p.span,
+                }))
+            }
+            ResolvedPattern::Difference(p) => {
+                ResolvedPattern::Difference(Box::new(PatternDifference {
+                    lhs: p.lhs.ignore_case(),
+                    rhs: p.rhs.ignore_case(),
+                    span: p.span,
+                }))
+            }
+            ResolvedPattern::Opt(p) => ResolvedPattern::Opt(Box::new(PatternOpt {
+                pattern: p.pattern.ignore_case(),
+            })),
+            ResolvedPattern::Many0(p) => ResolvedPattern::Many0(Box::new(PatternMany0 {
+                pattern: p.pattern.ignore_case(),
+            })),
+            ResolvedPattern::Many1(p) => ResolvedPattern::Many1(Box::new(PatternMany1 {
+                pattern: p.pattern.ignore_case(),
+            })),
+            ResolvedPattern::SepBy(p) => ResolvedPattern::SepBy(Box::new(PatternSepBy {
+                pattern: p.pattern.ignore_case(),
+                separator: p.separator.ignore_case(),
+                start: p.start,
+                end: p.end,
+                span: p.span,
+            })),
+            ResolvedPattern::Sub(p) => ResolvedPattern::Sub(p.clone()),
+            ResolvedPattern::Rec(p) => ResolvedPattern::Rec(p.clone()),
+            ResolvedPattern::Assign(p) => ResolvedPattern::Assign(p.clone()),
+            ResolvedPattern::Counted(p) => ResolvedPattern::Counted(Box::new(p.ignore_case())),
+        }
+    }
+
+    pub fn contains_collect(&self) -> bool {
+        match self {
+            ResolvedPattern::Atom(_)
+            | ResolvedPattern::Set(_)
+            | ResolvedPattern::Rec(_)
+            | ResolvedPattern::Counted(_) => false,
+            ResolvedPattern::Seq(p) => p.patterns.iter().any(|p| p.contains_collect()),
+            ResolvedPattern::Join(p) => p.lhs.contains_collect() || p.rhs.contains_collect(),
+            ResolvedPattern::Or(p) => p.lhs.contains_collect() || p.rhs.contains_collect(),
+            ResolvedPattern::Repeat(p) => p.pattern.contains_collect(),
+            ResolvedPattern::Collect(_) | ResolvedPattern::Sub(_) | ResolvedPattern::Assign(_) => {
+                true
+            }
+            ResolvedPattern::Intersection(p) => {
+                p.lhs.contains_collect() || p.rhs.contains_collect()
+            }
+            ResolvedPattern::Difference(p) => p.lhs.contains_collect() || p.rhs.contains_collect(),
+            ResolvedPattern::Opt(p) => p.pattern.contains_collect(),
+            ResolvedPattern::Many0(p) => p.pattern.contains_collect(),
+            ResolvedPattern::Many1(p) => p.pattern.contains_collect(),
+            ResolvedPattern::SepBy(p) => {
+                p.pattern.contains_collect() || p.separator.contains_collect()
+            }
+        }
+    }
+
+    pub fn contains_rec(&self) -> bool {
+        match self {
+            ResolvedPattern::Atom(_)
+            | ResolvedPattern::Set(_)
+            | ResolvedPattern::Sub(_)
+            | ResolvedPattern::Assign(_)
+            | ResolvedPattern::Counted(_) => false,
+            ResolvedPattern::Rec(_) => true,
+            ResolvedPattern::Seq(p) => p.patterns.iter().any(|p| p.contains_rec()),
+            ResolvedPattern::Join(p) => p.lhs.contains_rec() || p.rhs.contains_rec(),
+            ResolvedPattern::Or(p) => p.lhs.contains_rec() || p.rhs.contains_rec(),
+            ResolvedPattern::Repeat(p) => p.pattern.contains_rec(),
+            ResolvedPattern::Collect(p) => p.pattern.contains_rec(),
+            ResolvedPattern::Intersection(p) => p.lhs.contains_rec() || p.rhs.contains_rec(),
+            ResolvedPattern::Difference(p) => p.lhs.contains_rec() || p.rhs.contains_rec(),
+            ResolvedPattern::Opt(p) => p.pattern.contains_rec(),
+            ResolvedPattern::Many0(p) => p.pattern.contains_rec(),
+            ResolvedPattern::Many1(p) => p.pattern.contains_rec(),
+            ResolvedPattern::SepBy(p) => p.pattern.contains_rec() || p.separator.contains_rec(),
+        }
+    }
+    pub fn contains_count(&self) -> bool {
+        match self {
+            ResolvedPattern::Atom(_)
+            | ResolvedPattern::Set(_)
+            | ResolvedPattern::Sub(_)
+            | ResolvedPattern::Rec(_)
+            | ResolvedPattern::Assign(_) => false,
+            ResolvedPattern::Counted(_) => true,
+            ResolvedPattern::Seq(p) => p.patterns.iter().any(|p| p.contains_count()),
+            ResolvedPattern::Join(p) => p.lhs.contains_count() || p.rhs.contains_count(),
+            ResolvedPattern::Or(p) => p.lhs.contains_count() || p.rhs.contains_count(),
+            ResolvedPattern::Repeat(p) => p.pattern.contains_count(),
+            ResolvedPattern::Collect(p) => p.pattern.contains_count(),
+            ResolvedPattern::Intersection(p) => p.lhs.contains_count() || p.rhs.contains_count(),
+            ResolvedPattern::Difference(p) => p.lhs.contains_count() || p.rhs.contains_count(),
+            ResolvedPattern::Opt(p) => p.pattern.contains_count(),
+            ResolvedPattern::Many0(p) => p.pattern.contains_count(),
+            ResolvedPattern::Many1(p) => p.pattern.contains_count(),
+            ResolvedPattern::SepBy(p) => p.pattern.contains_count() || p.separator.contains_count(),
+        }
+    }
+}
+
 impl<T: PatternChar> MatchPattern<T> for ResolvedPattern<T> {
     fn insert(
         &self,
@@ -161,41 +502,148 @@ impl<T: PatternChar> MatchPattern<T> for ResolvedPattern<T> {
     ) -> crate::match_graph::StateId {
         match self {
             ResolvedPattern::Atom(p) => p.insert(builder, context, from),
+            ResolvedPattern::Set(p) => p.insert(builder, context, from),
             ResolvedPattern::Seq(p) => p.insert(builder, context, from),
             ResolvedPattern::Join(p) => p.insert(builder, context, from),
             ResolvedPattern::Or(p) => p.insert(builder, context, from),
             ResolvedPattern::Repeat(p) => p.insert(builder, context, from),
             ResolvedPattern::Collect(p) => p.insert(builder, context, from),
+            ResolvedPattern::Intersection(p) => p.insert(builder, context, from),
+            ResolvedPattern::Difference(p) => p.insert(builder, context, from),
+            ResolvedPattern::Opt(p) => p.insert(builder, context, from),
+            ResolvedPattern::Many0(p) => p.insert(builder, context, from),
+            ResolvedPattern::Many1(p) => p.insert(builder, context, from),
+            ResolvedPattern::SepBy(p) => p.insert(builder, context, from),
+            ResolvedPattern::Sub(p) => p.insert(builder, context, from),
+            ResolvedPattern::Rec(p) => p.insert(builder, context, from),
+            ResolvedPattern::Assign(p) => p.insert(builder, context, from),
+            ResolvedPattern::Counted(p) => p.insert(builder, context, from),
         }
     }
 }
 
 pub struct ResolveEnv<T: PatternChar> {
-    variables: HashMap<String, ResolvedPattern<T>>,
+    variables: HashMap<String, Variable<T>>,
+    rules: Vec<(syn::Ident, ResolvedPattern<T>)>,
+    warnings: Vec<Warning>,
+}
+
+#[derive(Debug, Clone)]
+enum Variable<T: PatternChar> {
+    Pattern(ResolvedPattern<T>),
+    Function(Rc<Function<T>>),
+}
+
+// 引数を取る宣言。本体は宣言された時点の変数で解決し、引数は使われる箇所の変数で解決する。
+#[derive(Debug)]
+struct Function<T: PatternChar> {
+    name: syn::Ident,
+    params: Vec<syn::Ident>,
+    body: Pattern<T>,
+    variables: HashMap<String, Variable<T>>,
 }
 
 impl<T: PatternChar> ResolveEnv<T> {
     pub fn empty() -> Self {
         Self {
             variables: HashMap::new(),
+            rules: Vec::new(),
+            warnings: Vec::new(),
         }
     }
 
     pub fn new(parent: &ResolveEnv<T>, declares: &Declares<T>) -> syn::Result<Self> {
         let mut env = Self {
             variables: parent.variables.clone(),
+            rules: parent.rules.clone(),
+            warnings: Vec::new(),
         };
 
-        for (name, pattern) in declares.variables() {
+        let names = declares
+            .variables()
+            .iter()
+            .map(|(name, _)| name)
+            .chain(declares.rules().iter().map(|(name, _)| name));
+        let mut declared: HashMap<String, &syn::Ident> = HashMap::new();
+        for name in names {
+            if let Some(first) = declared.insert(name.to_string(), name) {
+                let mut e =
+                    syn::Error::new(name.span(), format!("Duplicated declaration `{name}`."));
+                e.combine(syn::Error::new(
+                    first.span(),
+                    format!("`{name}` is first declared here."),
+                ));
+                return Err(e);
+            }
+
+            if parent.variables.contains_key(&name.to_string()) {
+                env.warnings.push(Warning::new(
+                    name.span(),
+                    format!("`{name}` shadows an outer declaration."),
+                ));
+            }
+        }
+
+        // 再帰的な宣言は互いに参照できるよう、本体より先に名前を登録する。
+        let offset = env.rules.len();
+        for (i, (name, _)) in declares.rules().iter().enumerate() {
+            let p = PatternRec {
+                index: offset + i,
+                name: name.to_string(),
+                span: name.span(),
+            };
+            env.variables
+                .insert(name.to_string(), Variable::Pattern(ResolvedPattern::Rec(p)));
+        }
+
+        for i in dependency_order(declares.variables(), &parent.variables)? {
+            let (name, declare) = &declares.variables()[i];
+            let v = match declare {
+                Declare::Pattern(pattern) => Variable::Pattern(env.resolve(pattern)?),
+                Declare::Function { params, body } => Variable::Function(Rc::new(Function {
+                    name: name.clone(),
+                    params: params.clone(),
+                    body: body.clone(),
+                    variables: env.variables.clone(),
+                })),
+            };
+            env.variables.insert(name.to_string(), v);
+        }
+
+        for (name, pattern) in declares.rules() {
             let p = env.resolve(pattern)?;
-            env.variables.insert(name.clone(), p);
+            if p.contains_collect() {
+                return Err(syn::Error::new(
+                    name.span(),
+                    "`collect!`, `sub!` and `set!` cannot be used in recursive declarations.",
+                ));
+            }
+            if p.contains_count() {
+                return Err(syn::Error::new(
+                    name.span(),
+                    "Constants cannot be used in recursive declarations.",
+                ));
+            }
+            env.rules.push((name.clone(), p));
         }
 
         Ok(env)
     }
 
+    // この環境の宣言で見つかった警告。外側の環境の警告は含まない。
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+
+    pub fn rules(&self) -> &[(syn::Ident, ResolvedPattern<T>)] {
+        &self.rules
+    }
+
     pub fn variable(&self, name: &str) -> Option<&ResolvedPattern<T>> {
-        self.variables.get(name)
+        match self.variables.get(name)? {
+            Variable::Pattern(p) => Some(p),
+            Variable::Function(_) => None,
+        }
     }
 
     pub fn resolve(&self, pattern: &Pattern<T>) -> syn::Result<ResolvedPattern<T>> {
@@ -228,6 +676,7 @@ impl<T: PatternChar> ResolveEnv<T> {
                     pattern,
                     start: p.start,
                     end: p.end,
+                    span: p.span,
                 };
                 ResolvedPattern::Repeat(Box::new(p))
             }
@@ -239,17 +688,312 @@ impl<T: PatternChar> ResolveEnv<T> {
                 };
                 ResolvedPattern::Collect(Box::new(p))
             }
+            Pattern::Not(p) => {
+                let pattern = self.resolve(&p.pattern)?;
+                let Some(set) = pattern.to_set() else {
+                    return Err(syn::Error::new(
+                        p.span,
+                        "Only patterns matching a single element can be negated.",
+                    ));
+                };
+                ResolvedPattern::Set(set.complement())
+            }
+            Pattern::Intersection(p) => {
+                let (lhs, rhs) = self.resolve_operands(&p.lhs, &p.rhs, p.span, "&")?;
+                match (lhs.to_set(), rhs.to_set()) {
+                    (Some(l), Some(r)) => ResolvedPattern::Set(l.intersection(&r)),
+                    _ => ResolvedPattern::Intersection(Box::new(PatternIntersection {
+                        lhs,
+                        rhs,
+                        span: p.span,
+                    })),
+                }
+            }
+            Pattern::Difference(p) => {
+                let (lhs, rhs) = self.resolve_operands(&p.lhs, &p.rhs, p.span, "-")?;
+                match (lhs.to_set(), rhs.to_set()) {
+                    (Some(l), Some(r)) => ResolvedPattern::Set(l.difference(&r)),
+                    _ => ResolvedPattern::Difference(Box::new(PatternDifference {
+                        lhs,
+                        rhs,
+                        span: p.span,
+                    })),
+                }
+            }
+            Pattern::Opt(p) => ResolvedPattern::Opt(Box::new(PatternOpt {
+                pattern: self.resolve(&p.pattern)?,
+            })),
+            Pattern::Many0(p) => ResolvedPattern::Many0(Box::new(PatternMany0 {
+                pattern: self.resolve(&p.pattern)?,
+            })),
+            Pattern::Many1(p) => ResolvedPattern::Many1(Box::new(PatternMany1 {
+                pattern: self.resolve(&p.pattern)?,
+            })),
+            Pattern::SepBy(p) => ResolvedPattern::SepBy(Box::new(PatternSepBy {
+                pattern: self.resolve(&p.pattern)?,
+                separator: self.resolve(&p.separator)?,
+                start: p.start,
+                end: p.end,
+                span: p.span,
+            })),
+            Pattern::Sub(p) => ResolvedPattern::Sub(p.clone()),
+            Pattern::Assign(p) => ResolvedPattern::Assign(p.clone()),
+            Pattern::Count(p) => {
+                let element = match &p.element {
+                    CountElement::Pattern(e) => {
+                        let Some(set) = self.resolve(e)?.to_set() else {
+                            return Err(syn::Error::new(
+                                p.span,
+                                "Only patterns matching a single element can be repeated a number of times given by constants.",
+                            ));
+                        };
+                        CountElement::Pattern(set)
+                    }
+                    CountElement::Range(range) => CountElement::Range(range.clone()),
+                };
+                ResolvedPattern::Counted(Box::new(PatternCounted {
+                    element,
+                    count: p.count.clone(),
+                    span: p.span,
+                }))
+            }
+            Pattern::IgnoreCase(p) => match p.distribute() {
+                Some(pattern) => self.resolve(&pattern)?,
+                None => self.resolve(&p.pattern)?.ignore_case(),
+            },
+            Pattern::Call(c) => self.expand(c)?,
             Pattern::Class(c) => {
                 let name = c.path.require_ident()?.to_string();
-                let Some(p) = self.variable(&name) else {
-                    return Err(syn::Error::new(c.path.span(), "Undeclared variable."));
-                };
-                p.clone()
+                if let Some(Variable::Function(f)) = self.variables.get(&name) {
+                    return Err(syn::Error::new(
+                        c.path.span(),
+                        format!("`{name}` takes {}.", arguments(f.params.len())),
+                    ));
+                } else if let Some(ResolvedPattern::Rec(p)) = self.variable(&name) {
+                    ResolvedPattern::Rec(PatternRec {
+                        span: c.path.span(),
+                        ..p.clone()
+                    })
+                } else if let Some(p) = self.variable(&name) {
+                    p.clone()
+                } else if let Some(set) = PatternClass::builtin(&name) {
+                    ResolvedPattern::Set(set)
+                } else if let Some(set) = PatternClass::unicode(&name) {
+                    let set = set.map_err(|m| syn::Error::new(c.path.span(), m))?;
+                    ResolvedPattern::Set(set)
+                } else {
+                    return Err(self.undeclared(c.path.span(), &name));
+                }
             }
         };
 
         Ok(p)
     }
+
+    fn expand(&self, call: &PatternCall<T>) -> syn::Result<ResolvedPattern<T>> {
+        let name = call.name.to_string();
+        let f = match self.variables.get(&name) {
+            Some(Variable::Function(f)) => f,
+            Some(Variable::Pattern(_)) => {
+                return Err(syn::Error::new(
+                    call.name.span(),
+                    format!("`{name}` does not take arguments."),
+                ));
+            }
+            None => return Err(self.undeclared(call.name.span(), &name)),
+        };
+
+        if f.params.len() != call.args.len() {
+            let mut e = syn::Error::new(
+                call.span,
+                format!(
+                    "`{name}` takes {}, but {} given.",
+                    arguments(f.params.len()),
+                    match call.args.len() {
+                        1 => String::from("1 was"),
+                        n => format!("{n} were"),
+                    }
+                ),
+            );
+            e.combine(syn::Error::new(
+                f.name.span(),
+                format!("`{name}` is declared here."),
+            ));
+            return Err(e);
+        }
+
+        // 引数を先に解決してから束縛するため、本体の変数が呼び出し側の変数を捕まえることはない。
+        let mut env = Self {
+            variables: f.variables.clone(),
+            rules: Vec::new(),
+            warnings: Vec::new(),
+        };
+        for (param, arg) in f.params.iter().zip(&call.args) {
+            let arg = self.resolve(arg)?;
+            env.variables
+                .insert(param.to_string(), Variable::Pattern(arg));
+        }
+
+        env.resolve(&f.body).map_err(|mut e| {
+            e.combine(syn::Error::new(
+                call.span,
+                format!("In this use of `{name}`."),
+            ));
+            e
+        })
+    }
+
+    // 似た名前の変数や組み込みのクラスがあれば、それを提案する。
+    fn undeclared(&self, span: Span, name: &str) -> syn::Error {
+        let builtins = ["any", "digit", "alpha", "alnum", "xdigit", "space", "word"];
+        let threshold = (name.chars().count() / 3).max(1);
+        let suggestion = self
+            .variables
+            .keys()
+            .map(String::as_str)
+            .chain(builtins)
+            .map(|candidate| (edit_distance(name, candidate), candidate))
+            .filter(|(d, _)| *d <= threshold)
+            .min();
+
+        match suggestion {
+            Some((_, candidate)) => syn::Error::new(
+                span,
+                format!("Undeclared variable. Did you mean `{candidate}`?"),
+            ),
+            None => syn::Error::new(span, "Undeclared variable."),
+        }
+    }
+
+    // `&` と `-` の各辺はDFAに変換してから直積をとるため、collectの位置を保てない。
+    fn resolve_operands(
+        &self,
+        lhs: &Pattern<T>,
+        rhs: &Pattern<T>,
+        span: Span,
+        op: &str,
+    ) -> syn::Result<(ResolvedPattern<T>, ResolvedPattern<T>)> {
+        let lhs = self.resolve(lhs)?;
+        let rhs = self.resolve(rhs)?;
+
+        if lhs.contains_collect() || rhs.contains_collect() {
+            return Err(syn::Error::new(
+                span,
+                format!("`collect!` and `set!` cannot be used in operands of `{op}`."),
+            ));
+        }
+
+        if lhs.contains_rec() || rhs.contains_rec() {
+            return Err(syn::Error::new(
+                span,
+                format!("Recursive declarations cannot be used in operands of `{op}`."),
+            ));
+        }
+
+        if lhs.contains_count() || rhs.contains_count() {
+            return Err(syn::Error::new(
+                span,
+                format!("Constants cannot be used in operands of `{op}`."),
+            ));
+        }
+
+        Ok((lhs, rhs))
+    }
+}
+
+// 同じ属性の範囲の宣言を、参照される側が先になるよう並べる。循環していればその経路を報告する。
+// 外側に同名の宣言があれば、自身への参照は外側の宣言を指すものとして循環に数えない。
+fn dependency_order<T: PatternChar>(
+    variables: &[(syn::Ident, Declare<T>)],
+    outer: &HashMap<String, Variable<T>>,
+) -> syn::Result<Vec<usize>> {
+    let indices: HashMap<String, usize> = variables
+        .iter()
+        .enumerate()
+        .map(|(i, (name, _))| (name.to_string(), i))
+        .collect();
+    let dependencies: Vec<Vec<usize>> = variables
+        .iter()
+        .enumerate()
+        .map(|(i, (name, declare))| {
+            let (params, body) = match declare {
+                Declare::Pattern(p) => (&[][..], p),
+                Declare::Function { params, body } => (params.as_slice(), body),
+            };
+            let refines_outer = outer.contains_key(&name.to_string());
+            body.references()
+                .into_iter()
+                .filter(|name| !params.contains(name))
+                .filter_map(|name| indices.get(&name.to_string()).copied())
+                .filter(|&j| !(j == i && refines_outer))
+                .collect()
+        })
+        .collect();
+
+    #[derive(Clone, Copy, PartialEq)]
+    enum Mark {
+        Unvisited,
+        Visiting,
+        Done,
+    }
+
+    fn visit<T: PatternChar>(
+        i: usize,
+        variables: &[(syn::Ident, Declare<T>)],
+        dependencies: &[Vec<usize>],
+        marks: &mut [Mark],
+        path: &mut Vec<usize>,
+        order: &mut Vec<usize>,
+    ) -> syn::Result<()> {
+        match marks[i] {
+            Mark::Done => return Ok(()),
+            Mark::Visiting => {
+                let start = path.iter().position(|&j| j == i).unwrap();
+                let chain: Vec<_> = path[start..]
+                    .iter()
+                    .chain([&i])
+                    .map(|&j| format!("`{}`", variables[j].0))
+                    .collect();
+                return Err(syn::Error::new(
+                    variables[i].0.span(),
+                    format!("Cyclic declarations: {}.", chain.join(" -> ")),
+                ));
+            }
+            Mark::Unvisited => {}
+        }
+
+        marks[i] = Mark::Visiting;
+        path.push(i);
+        for &j in &dependencies[i] {
+            visit(j, variables, dependencies, marks, path, order)?;
+        }
+        path.pop();
+        marks[i] = Mark::Done;
+        order.push(i);
+        Ok(())
+    }
+
+    let mut marks = vec![Mark::Unvisited; variables.len()];
+    let mut order = Vec::with_capacity(variables.len());
+    for i in 0..variables.len() {
+        visit(
+            i,
+            variables,
+            &dependencies,
+            &mut marks,
+            &mut Vec::new(),
+            &mut order,
+        )?;
+    }
+
+    Ok(order)
+}
+
+fn arguments(n: usize) -> String {
+    match n {
+        1 => String::from("1 argument"),
+        n => format!("{n} arguments"),
+    }
 }
 
 mod __internal {
@@ -0,0 +1,46 @@
+use crate::{
+    match_graph::{BuildContext, Builder, MatchPattern, StateId},
+    pattern_char::PatternChar,
+};
+use syn::parse::Parse;
+
+// PatternAssign ::= "set!(" + member + "=" + expr + ")"
+#[derive(Debug, Clone)]
+pub struct PatternAssign {
+    pub field: String,
+    pub value: syn::Expr,
+}
+
+impl PatternAssign {
+    pub fn from_mac(mac: &syn::Macro) -> syn::Result<Self> {
+        let (field, value) = mac.parse_body_with(|input: syn::parse::ParseStream| {
+            let member = syn::Member::parse(input)?;
+            let _ = <syn::Token![=]>::parse(input)?;
+            let value = syn::Expr::parse(input)?;
+
+            if !input.is_empty() {
+                return Err(input.error("Unexpected arguments of `set!`."));
+            }
+
+            let field = match member {
+                syn::Member::Named(ident) => ident.to_string(),
+                syn::Member::Unnamed(index) => index.index.to_string(),
+            };
+
+            Ok((field, value))
+        })?;
+
+        Ok(Self { field, value })
+    }
+}
+
+impl<T: PatternChar> MatchPattern<T> for PatternAssign {
+    fn insert(
+        &self,
+        builder: &mut Builder<T>,
+        context: &mut BuildContext,
+        from: StateId,
+    ) -> StateId {
+        builder.insert_assign(context, from, &self.field, &self.value)
+    }
+}
@@ -0,0 +1,33 @@
+use crate::pattern::Pattern;
+use crate::pattern_char::PatternChar;
+use proc_macro2::Span;
+use syn::spanned::Spanned;
+
+// PatternCall ::= ident + "(" + pattern + ("," + pattern)* + ")"
+#[derive(Debug, Clone)]
+pub struct PatternCall<T: PatternChar> {
+    pub name: syn::Ident,
+    pub args: Vec<Pattern<T>>,
+    pub span: Span,
+}
+
+impl<T: PatternChar> PatternCall<T> {
+    pub fn from_call(call: &syn::ExprCall) -> syn::Result<Self> {
+        let name = match call.func.as_ref() {
+            syn::Expr::Path(e) => e.path.require_ident()?.clone(),
+            e => return Err(syn::Error::new(e.span(), "Ident was expected.")),
+        };
+
+        let args = call
+            .args
+            .iter()
+            .map(Pattern::new)
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            name,
+            args,
+            span: call.span(),
+        })
+    }
+}
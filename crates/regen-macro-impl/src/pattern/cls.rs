@@ -1,4 +1,60 @@
+use crate::{pattern::PatternSet, pattern_char::PatternChar};
+
 #[derive(Debug, Clone)]
 pub struct PatternClass {
     pub path: syn::Path,
 }
+
+impl PatternClass {
+    // 組み込みのクラス。同名の変数が宣言されていない場合にのみ使われる。
+    pub fn builtin<T: PatternChar>(name: &str) -> Option<PatternSet<T>> {
+        if name == "any" {
+            return Some(PatternSet::from_intervals([(None, None)]));
+        }
+
+        let intervals = Self::builtin_ranges(name)?.iter().map(|&(start, end)| {
+            let start = T::from_ascii(start);
+            let end = T::from_ascii(end);
+            (Some(start), end.next_up())
+        });
+
+        Some(PatternSet::from_intervals(intervals))
+    }
+
+    // `any` 以外の組み込みのクラスはASCIIの範囲のみを含む。
+    pub fn builtin_ranges(name: &str) -> Option<&'static [(u8, u8)]> {
+        let ranges: &[(u8, u8)] = match name {
+            "digit" => &[(b'0', b'9')],
+            "alpha" => &[(b'A', b'Z'), (b'a', b'z')],
+            "alnum" => &[(b'0', b'9'), (b'A', b'Z'), (b'a', b'z')],
+            "xdigit" => &[(b'0', b'9'), (b'A', b'F'), (b'a', b'f')],
+            "space" => &[(b'\t', b'\r'), (b' ', b' ')],
+            "word" => &[(b'0', b'9'), (b'A', b'Z'), (b'_', b'_'), (b'a', b'z')],
+            _ => return None,
+        };
+
+        Some(ranges)
+    }
+
+    // Unicodeのプロパティによるクラス。`char` でのみ使える。
+    #[cfg(feature = "unicode")]
+    pub fn unicode<T: PatternChar>(name: &str) -> Option<Result<PatternSet<T>, &'static str>> {
+        let ranges = crate::unicode::class(name)?;
+        let intervals = ranges
+            .iter()
+            .map(|&(start, end)| {
+                let start = T::try_from_char(start)?;
+                let end = T::try_from_char(end)?;
+                Ok((Some(start), end.next_up()))
+            })
+            .collect::<Result<Vec<_>, &'static str>>()
+            .map_err(|_| "Unicode property classes can only be used with `char`.");
+
+        Some(intervals.map(PatternSet::from_intervals))
+    }
+
+    #[cfg(not(feature = "unicode"))]
+    pub fn unicode<T: PatternChar>(_: &str) -> Option<Result<PatternSet<T>, &'static str>> {
+        None
+    }
+}
@@ -0,0 +1,114 @@
+use crate::{
+    match_graph::{BuildContext, Builder, MatchPattern, StateId},
+    pattern::{Pattern, PatternSet},
+    pattern_char::PatternChar,
+};
+use proc_macro2::Span;
+use quote::{quote, quote_spanned};
+use syn::{punctuated::Punctuated, spanned::Spanned};
+
+// PatternCount ::= "[" + pattern + ";" + const_range + "]" | "repeat!(" + pattern + "," + const_range + ")" | const_range
+// 定数を参照する回数や範囲は展開時に分からないため、生成するコードの中で数える。
+// 展開して並べられないので、数えられるのは一要素にマッチするパターンに限る。
+#[derive(Debug, Clone)]
+pub struct PatternCount<T: PatternChar> {
+    pub element: CountElement<Pattern<T>>,
+    pub count: syn::ExprRange,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct PatternCounted<T: PatternChar> {
+    pub element: CountElement<PatternSet<T>>,
+    pub count: syn::ExprRange,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub enum CountElement<P> {
+    Pattern(P),
+    // 端点に定数を含む範囲。一要素を生成するコードで範囲と比較する。
+    Range(syn::ExprRange),
+}
+
+impl<T: PatternChar> PatternCount<T> {
+    pub fn from_repeat(e: &syn::ExprRepeat) -> syn::Result<Self> {
+        Ok(Self {
+            element: CountElement::Pattern(Pattern::new(&e.expr)?),
+            count: expect_range(&e.len)?,
+            span: e.span(),
+        })
+    }
+
+    pub fn from_mac(mac: &syn::Macro) -> syn::Result<Self> {
+        let e = mac.parse_body_with(Punctuated::<syn::Expr, syn::Token![,]>::parse_terminated)?;
+        if e.len() != 2 {
+            return Err(syn::Error::new(e.span(), "Two arguments were expected."));
+        }
+
+        Ok(Self {
+            element: CountElement::Pattern(Pattern::new(&e[0])?),
+            count: expect_range(&e[1])?,
+            span: mac.span(),
+        })
+    }
+
+    pub fn from_range(range: &syn::ExprRange) -> Self {
+        Self {
+            element: CountElement::Range(range.clone()),
+            count: syn::parse_quote!(1..=1),
+            span: range.span(),
+        }
+    }
+}
+
+fn expect_range(expr: &syn::Expr) -> syn::Result<syn::ExprRange> {
+    match expr {
+        syn::Expr::Range(e) => Ok(e.clone()),
+        syn::Expr::Paren(e) => expect_range(&e.expr),
+        e => Err(syn::Error::new(e.span(), "Range primitive was expected.")),
+    }
+}
+
+impl<T: PatternChar> PatternCounted<T> {
+    pub fn ignore_case(&self) -> Self {
+        let element = match &self.element {
+            CountElement::Pattern(set) => CountElement::Pattern(set.ignore_case()),
+            CountElement::Range(range) => CountElement::Range(range.clone()),
+        };
+
+        Self {
+            element,
+            count: self.count.clone(),
+            span: self.span,
+        }
+    }
+}
+
+impl<T: PatternChar> MatchPattern<T> for PatternCounted<T> {
+    fn insert(
+        &self,
+        builder: &mut Builder<T>,
+        context: &mut BuildContext,
+        from: StateId,
+    ) -> StateId {
+        // 範囲は一要素だけなので、数えずに範囲を比べる遷移にする。
+        let set = match &self.element {
+            CountElement::Pattern(set) => set,
+            CountElement::Range(range) => return builder.insert_guard(context, from, range),
+        };
+
+        let min = match &self.count.start {
+            Some(e) => quote_spanned!(e.span()=> #e),
+            None => quote!(0),
+        };
+        let max = match (&self.count.end, &self.count.limits) {
+            (Some(e), syn::RangeLimits::Closed(_)) => quote_spanned!(e.span()=> #e),
+            (Some(e), syn::RangeLimits::HalfOpen(_)) => quote_spanned!(e.span()=> (#e) - 1),
+            (None, _) => quote!(usize::MAX),
+        };
+
+        let intervals = set.intervals().to_vec();
+        builder.insert_count(context, from, intervals, min, max, self.span)
+    }
+}
@@ -0,0 +1,25 @@
+use crate::{
+    match_graph::{BuildContext, Builder, MatchPattern, ProductOp, StateId},
+    pattern::{PatternKind, PatternTag, ResolvedPatternTag},
+    pattern_char::PatternChar,
+};
+use proc_macro2::Span;
+
+// PatternDifference ::= pattern + "-" + pattern
+#[derive(Debug, Clone)]
+pub struct PatternDifference<T: PatternChar, K: PatternKind = PatternTag> {
+    pub lhs: K::Pattern<T>,
+    pub rhs: K::Pattern<T>,
+    pub span: Span,
+}
+
+impl<T: PatternChar> MatchPattern<T> for PatternDifference<T, ResolvedPatternTag> {
+    fn insert(
+        &self,
+        builder: &mut Builder<T>,
+        context: &mut BuildContext,
+        from: StateId,
+    ) -> StateId {
+        builder.insert_product(context, from, &self.lhs, &self.rhs, ProductOp::Difference)
+    }
+}
@@ -0,0 +1,103 @@
+use crate::{
+    pattern::{
+        CountElement, Pattern, PatternCollect, PatternCount, PatternDifference,
+        PatternIntersection, PatternJoin, PatternKind, PatternMany0, PatternMany1, PatternNot,
+        PatternOpt, PatternOr, PatternRepeat, PatternSepBy, PatternSeq, PatternTag,
+    },
+    pattern_char::PatternChar,
+};
+
+// PatternIgnoreCase ::= "ignore_case!(" + pattern + ")"
+#[derive(Debug, Clone)]
+pub struct PatternIgnoreCase<T: PatternChar, K: PatternKind = PatternTag> {
+    pub pattern: K::Pattern<T>,
+}
+
+impl<T: PatternChar> PatternIgnoreCase<T> {
+    pub fn from_mac(mac: &syn::Macro) -> syn::Result<Self> {
+        let e: syn::Expr = mac.parse_body()?;
+        Ok(Self {
+            pattern: Pattern::new(&e)?,
+        })
+    }
+
+    // 大文字小文字の同一視を一段内側のパターンに移す。
+    // 否定や差は同一視した被演算子から取るため、解決した後の集合を畳み込まないようにする。
+    // 要素そのものを表すパターンでは `None` を返し、解決した集合を畳み込む。
+    pub fn distribute(&self) -> Option<Pattern<T>> {
+        let fold = |pattern: &Pattern<T>| {
+            Pattern::IgnoreCase(Box::new(PatternIgnoreCase {
+                pattern: pattern.clone(),
+            }))
+        };
+
+        let p = match &self.pattern {
+            Pattern::Seq(p) => Pattern::Seq(PatternSeq {
+                patterns: p.patterns.iter().map(fold).collect(),
+            }),
+            Pattern::Join(p) => Pattern::Join(Box::new(PatternJoin {
+                lhs: fold(&p.lhs),
+                rhs: fold(&p.rhs),
+            })),
+            Pattern::Or(p) => Pattern::Or(Box::new(PatternOr {
+                lhs: fold(&p.lhs),
+                rhs: fold(&p.rhs),
+            })),
+            Pattern::Repeat(p) => Pattern::Repeat(Box::new(PatternRepeat {
+                pattern: fold(&p.pattern),
+                start: p.start,
+                end: p.end,
+                span: p.span,
+            })),
+            Pattern::Collect(p) => Pattern::Collect(Box::new(PatternCollect {
+                pattern: fold(&p.pattern),
+                field: p.field.clone(),
+            })),
+            Pattern::Not(p) => Pattern::Not(Box::new(PatternNot {
+                pattern: fold(&p.pattern),
+                span: p.span,
+            })),
+            Pattern::Intersection(p) => Pattern::Intersection(Box::new(PatternIntersection {
+                lhs: fold(&p.lhs),
+                rhs: fold(&p.rhs),
+                span: p.span,
+            })),
+            Pattern::Difference(p) => Pattern::Difference(Box::new(PatternDifference {
+                lhs: fold(&p.lhs),
+                rhs: fold(&p.rhs),
+                span: p.span,
+            })),
+            Pattern::IgnoreCase(p) => Pattern::IgnoreCase(p.clone()),
+            Pattern::Opt(p) => Pattern::Opt(Box::new(PatternOpt {
+                pattern: fold(&p.pattern),
+            })),
+            Pattern::Many0(p) => Pattern::Many0(Box::new(PatternMany0 {
+                pattern: fold(&p.pattern),
+            })),
+            Pattern::Many1(p) => Pattern::Many1(Box::new(PatternMany1 {
+                pattern: fold(&p.pattern),
+            })),
+            Pattern::SepBy(p) => Pattern::SepBy(Box::new(PatternSepBy {
+                pattern: fold(&p.pattern),
+                separator: fold(&p.separator),
+                start: p.start,
+                end: p.end,
+                span: p.span,
+            })),
+            Pattern::Count(p) => match &p.element {
+                CountElement::Pattern(e) => Pattern::Count(Box::new(PatternCount {
+                    element: CountElement::Pattern(fold(e)),
+                    count: p.count.clone(),
+                    span: p.span,
+                })),
+                CountElement::Range(_) => return None,
+            },
+            Pattern::Atom(_)
+            | Pattern::Class(_)
+            | Pattern::Call(_)
+            | Pattern::Sub(_)
+            | Pattern::Assign(_) => return None,
+        };
+        Some(p)
+    }
+}
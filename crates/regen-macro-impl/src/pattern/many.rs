@@ -0,0 +1,57 @@
+use crate::{
+    match_graph::{BuildContext, Builder, MatchPattern, StateId},
+    pattern::{Pattern, PatternKind, PatternTag, ResolvedPatternTag},
+    pattern_char::PatternChar,
+};
+
+// PatternMany0 ::= "many0!(" + pattern + ")"
+#[derive(Debug, Clone)]
+pub struct PatternMany0<T: PatternChar, K: PatternKind = PatternTag> {
+    pub pattern: K::Pattern<T>,
+}
+
+impl<T: PatternChar> PatternMany0<T> {
+    pub fn from_mac(mac: &syn::Macro) -> syn::Result<Self> {
+        let e: syn::Expr = mac.parse_body()?;
+        Ok(Self {
+            pattern: Pattern::new(&e)?,
+        })
+    }
+}
+
+impl<T: PatternChar> MatchPattern<T> for PatternMany0<T, ResolvedPatternTag> {
+    fn insert(
+        &self,
+        builder: &mut Builder<T>,
+        context: &mut BuildContext,
+        from: StateId,
+    ) -> StateId {
+        builder.insert_repeat(context, from, &self.pattern)
+    }
+}
+
+// PatternMany1 ::= "many1!(" + pattern + ")"
+#[derive(Debug, Clone)]
+pub struct PatternMany1<T: PatternChar, K: PatternKind = PatternTag> {
+    pub pattern: K::Pattern<T>,
+}
+
+impl<T: PatternChar> PatternMany1<T> {
+    pub fn from_mac(mac: &syn::Macro) -> syn::Result<Self> {
+        let e: syn::Expr = mac.parse_body()?;
+        Ok(Self {
+            pattern: Pattern::new(&e)?,
+        })
+    }
+}
+
+impl<T: PatternChar> MatchPattern<T> for PatternMany1<T, ResolvedPatternTag> {
+    fn insert(
+        &self,
+        builder: &mut Builder<T>,
+        context: &mut BuildContext,
+        from: StateId,
+    ) -> StateId {
+        builder.insert_repeat1(context, from, &self.pattern)
+    }
+}
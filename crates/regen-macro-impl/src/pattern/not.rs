@@ -0,0 +1,30 @@
+use crate::{
+    pattern::{Pattern, PatternKind, PatternTag},
+    pattern_char::PatternChar,
+};
+use proc_macro2::Span;
+use syn::spanned::Spanned;
+
+// PatternNot ::= "!" + pattern | "not!(" + pattern + ")"
+#[derive(Debug, Clone)]
+pub struct PatternNot<T: PatternChar, K: PatternKind = PatternTag> {
+    pub pattern: K::Pattern<T>,
+    pub span: Span,
+}
+
+impl<T: PatternChar> PatternNot<T> {
+    pub fn from_unary(e: &syn::ExprUnary) -> syn::Result<Self> {
+        Ok(Self {
+            pattern: Pattern::new(&e.expr)?,
+            span: e.span(),
+        })
+    }
+
+    pub fn from_mac(mac: &syn::Macro) -> syn::Result<Self> {
+        let e: syn::Expr = mac.parse_body()?;
+        Ok(Self {
+            pattern: Pattern::new(&e)?,
+            span: mac.span(),
+        })
+    }
+}
@@ -0,0 +1,31 @@
+use crate::{
+    match_graph::{BuildContext, Builder, MatchPattern, StateId},
+    pattern::{Pattern, PatternKind, PatternTag, ResolvedPatternTag},
+    pattern_char::PatternChar,
+};
+
+// PatternOpt ::= "opt!(" + pattern + ")"
+#[derive(Debug, Clone)]
+pub struct PatternOpt<T: PatternChar, K: PatternKind = PatternTag> {
+    pub pattern: K::Pattern<T>,
+}
+
+impl<T: PatternChar> PatternOpt<T> {
+    pub fn from_mac(mac: &syn::Macro) -> syn::Result<Self> {
+        let e: syn::Expr = mac.parse_body()?;
+        Ok(Self {
+            pattern: Pattern::new(&e)?,
+        })
+    }
+}
+
+impl<T: PatternChar> MatchPattern<T> for PatternOpt<T, ResolvedPatternTag> {
+    fn insert(
+        &self,
+        builder: &mut Builder<T>,
+        context: &mut BuildContext,
+        from: StateId,
+    ) -> StateId {
+        builder.insert_opt(context, from, &self.pattern)
+    }
+}
@@ -0,0 +1,24 @@
+use crate::{
+    match_graph::{BuildContext, Builder, MatchPattern, StateId},
+    pattern_char::PatternChar,
+};
+use proc_macro2::Span;
+
+// PatternRec ::= ident (declared with `rec`)
+#[derive(Debug, Clone)]
+pub struct PatternRec {
+    pub index: usize,
+    pub name: String,
+    pub span: Span,
+}
+
+impl<T: PatternChar> MatchPattern<T> for PatternRec {
+    fn insert(
+        &self,
+        builder: &mut Builder<T>,
+        context: &mut BuildContext,
+        from: StateId,
+    ) -> StateId {
+        builder.insert_rule(context, from, self.index, &self.name, self.span)
+    }
+}
@@ -5,15 +5,20 @@ use crate::{
     pattern_char::PatternChar,
     util::range::is_range_empty,
 };
+use proc_macro2::Span;
 use std::ops::Bound;
 use syn::{punctuated::Punctuated, spanned::Spanned as _};
 
+// 繰り返しを展開して複製する数の上限。
+const UNROLL_LIMIT: usize = 8;
+
 // PatternRepeat ::=  "[" + pattern + ";" + range "]" "repeat!(" + pattern ")"  | "repeat!(" + pattern + "," + range + ")"
 #[derive(Debug, Clone)]
 pub struct PatternRepeat<T: PatternChar, K: PatternKind = PatternTag> {
     pub pattern: K::Pattern<T>,
     pub start: Bound<usize>,
     pub end: Bound<usize>,
+    pub span: Span,
 }
 
 impl<T: PatternChar> PatternRepeat<T> {
@@ -24,6 +29,7 @@ impl<T: PatternChar> PatternRepeat<T> {
             pattern: Pattern::new(&e.expr)?,
             start,
             end,
+            span: e.span(),
         })
     }
 
@@ -37,6 +43,7 @@ impl<T: PatternChar> PatternRepeat<T> {
                     pattern,
                     start: Bound::Unbounded,
                     end: Bound::Unbounded,
+                    span: mac.span(),
                 }
             }
             2 => {
@@ -46,6 +53,7 @@ impl<T: PatternChar> PatternRepeat<T> {
                     pattern,
                     start,
                     end,
+                    span: mac.span(),
                 }
             }
             _ => {
@@ -71,36 +79,67 @@ impl<T: PatternChar> MatchPattern<T> for PatternRepeat<T, ResolvedPatternTag> {
             panic!("Range must not be empty.")
         }
 
-        let state = match self.start {
-            Bound::Included(n) if n > 0 => {
-                insert_repeat_n(builder, context, from, &self.pattern, n)
+        builder.insert_with_origin(self.span, |builder| match self.counted_set(builder) {
+            Some(set) => {
+                let (min, max) = self.bounds();
+                builder.insert_counter(context, from, set, min, max, self.span)
             }
-            Bound::Excluded(n) => insert_repeat_n(builder, context, from, &self.pattern, n + 1),
-            _ => from,
+            None => self.insert_unrolled(builder, context, from),
+        })
+    }
+}
+
+impl<T: PatternChar> PatternRepeat<T, ResolvedPatternTag> {
+    fn bounds(&self) -> (usize, Option<usize>) {
+        let min = match self.start {
+            Bound::Included(n) => n,
+            Bound::Excluded(n) => n + 1,
+            Bound::Unbounded => 0,
+        };
+        let max = match self.end {
+            Bound::Included(n) => Some(n),
+            Bound::Excluded(n) => Some(n - 1),
+            Bound::Unbounded => None,
         };
+        (min, max)
+    }
 
-        match &self.end {
-            Bound::Included(n) => {
-                let mut state = state;
-                let mut states = Vec::with_capacity(*n);
-                states.push(state);
-                for _ in 0..(*n) {
-                    state = self.pattern.insert(builder, context, state);
-                    states.push(state);
-                }
-                builder.insert_junction(context, states)
-            }
-            Bound::Excluded(n) => {
+    // 展開すると `UNROLL_LIMIT` 個より多く複製する一要素のパターンの繰り返しは、カウンタで数える。
+    fn counted_set(&self, builder: &Builder<T>) -> Option<Vec<(Option<T>, Option<T>)>> {
+        if !builder.counting() {
+            return None;
+        }
+
+        let (min, max) = self.bounds();
+        if max.unwrap_or(min) <= UNROLL_LIMIT {
+            return None;
+        }
+
+        self.pattern.to_set().map(|set| set.intervals().to_vec())
+    }
+
+    fn insert_unrolled(
+        &self,
+        builder: &mut Builder<T>,
+        context: &mut BuildContext,
+        from: StateId,
+    ) -> StateId {
+        let (min, max) = self.bounds();
+        let state = insert_repeat_n(builder, context, from, &self.pattern, min);
+
+        // 最小回数の後に、省略可能な残りの回数分を続ける。
+        match max {
+            Some(max) => {
                 let mut state = state;
-                let mut states = Vec::with_capacity(*n);
+                let mut states = Vec::with_capacity(max - min + 1);
                 states.push(state);
-                for _ in 1..(*n) {
+                for _ in min..max {
                     state = self.pattern.insert(builder, context, state);
                     states.push(state);
                 }
                 builder.insert_junction(context, states)
             }
-            Bound::Unbounded => builder.insert_repeat(context, state, &self.pattern),
+            None => builder.insert_repeat(context, state, &self.pattern),
         }
     }
 }
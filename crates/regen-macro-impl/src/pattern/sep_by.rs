@@ -0,0 +1,68 @@
+use crate::{
+    eval::eval_as_range,
+    match_graph::{BuildContext, Builder, MatchPattern, StateId},
+    pattern::{Pattern, PatternKind, PatternTag, ResolvedPatternTag},
+    pattern_char::PatternChar,
+};
+use proc_macro2::Span;
+use std::ops::Bound;
+use syn::{punctuated::Punctuated, spanned::Spanned as _};
+
+// PatternSepBy ::= "sep_by!(" + pattern + "," + pattern + ")" | "sep_by!(" + pattern + "," + pattern + "," + range + ")"
+#[derive(Debug, Clone)]
+pub struct PatternSepBy<T: PatternChar, K: PatternKind = PatternTag> {
+    pub pattern: K::Pattern<T>,
+    pub separator: K::Pattern<T>,
+    pub start: Bound<usize>,
+    pub end: Bound<usize>,
+    pub span: Span,
+}
+
+impl<T: PatternChar> PatternSepBy<T> {
+    pub fn from_mac(mac: &syn::Macro) -> syn::Result<Self> {
+        let e = mac.parse_body_with(Punctuated::<syn::Expr, syn::Token![,]>::parse_terminated)?;
+
+        let (start, end) = match e.len() {
+            2 => (Bound::Unbounded, Bound::Unbounded),
+            3 => eval_as_range(&e[2])?,
+            _ => {
+                return Err(syn::Error::new(
+                    e.span(),
+                    "Two or three arguments were expected.",
+                ));
+            }
+        };
+
+        Ok(Self {
+            pattern: Pattern::new(&e[0])?,
+            separator: Pattern::new(&e[1])?,
+            start,
+            end,
+            span: mac.span(),
+        })
+    }
+}
+
+impl<T: PatternChar> MatchPattern<T> for PatternSepBy<T, ResolvedPatternTag> {
+    fn insert(
+        &self,
+        builder: &mut Builder<T>,
+        context: &mut BuildContext,
+        from: StateId,
+    ) -> StateId {
+        let min = match self.start {
+            Bound::Included(n) => n,
+            Bound::Excluded(n) => n + 1,
+            Bound::Unbounded => 0,
+        };
+        let max = match self.end {
+            Bound::Included(n) => Some(n),
+            Bound::Excluded(n) => Some(n - 1),
+            Bound::Unbounded => None,
+        };
+
+        builder.insert_with_origin(self.span, |builder| {
+            builder.insert_sep_by(context, from, &self.pattern, &self.separator, min, max)
+        })
+    }
+}
@@ -0,0 +1,125 @@
+use crate::{
+    match_graph::{BuildContext, Builder, MatchPattern, StateId},
+    pattern::PatternAtom,
+    pattern_char::PatternChar,
+    util::{IntervalMap, interval_map::store::Set, range::to_ropen},
+};
+use std::{collections::BTreeMap, ops::Bound};
+
+// PatternSet ::= set of single elements (resolved from atoms, `|`, `&`, `-` and `!`)
+#[derive(Debug, Clone)]
+pub struct PatternSet<T: PatternChar> {
+    intervals: Vec<(Option<T>, Option<T>)>,
+}
+
+impl<T: PatternChar> PatternSet<T> {
+    pub fn from_intervals(intervals: impl IntoIterator<Item = (Option<T>, Option<T>)>) -> Self {
+        let set = Self {
+            intervals: intervals.into_iter().collect(),
+        };
+        set.combine(&Self::empty(), |a, _| a)
+    }
+
+    pub fn from_atom(atom: &PatternAtom<T>) -> Self {
+        let range = match atom {
+            PatternAtom::Primitive(p) => (Bound::Included(p), Bound::Included(p)),
+            PatternAtom::Range(s, e) => (s.as_ref(), e.as_ref()),
+        };
+
+        Self::from_intervals([to_ropen(range)])
+    }
+
+    pub fn intervals(&self) -> &[(Option<T>, Option<T>)] {
+        &self.intervals
+    }
+
+    pub fn empty() -> Self {
+        Self {
+            intervals: Vec::new(),
+        }
+    }
+
+    pub fn union(&self, other: &Self) -> Self {
+        self.combine(other, |a, b| a || b)
+    }
+
+    pub fn intersection(&self, other: &Self) -> Self {
+        self.combine(other, |a, b| a && b)
+    }
+
+    pub fn difference(&self, other: &Self) -> Self {
+        self.combine(other, |a, b| a && !b)
+    }
+
+    // 型の値域全体に対する補集合。
+    pub fn complement(&self) -> Self {
+        self.combine(&Self::empty(), |a, _| !a)
+    }
+
+    pub fn contains(&self, value: &T) -> bool {
+        self.intervals.iter().any(|(start, end)| {
+            start.as_ref().is_none_or(|s| s <= value) && end.as_ref().is_none_or(|e| value < e)
+        })
+    }
+
+    // 畳み込み先が同じ要素をひとまとまりとし、一つでも含まれていればまとまり全体を加える。
+    pub fn ignore_case(&self) -> Self {
+        let mut classes: BTreeMap<T, Vec<T>> = BTreeMap::new();
+        for (c, folded) in T::case_folding() {
+            classes
+                .entry(folded)
+                .or_insert_with(|| vec![folded])
+                .push(c);
+        }
+
+        let extra = classes
+            .into_values()
+            .filter(|class| class.iter().any(|c| self.contains(c)))
+            .flatten()
+            .map(|c| (Some(c), c.next_up()));
+
+        self.union(&Self::from_intervals(extra))
+    }
+
+    // 二つの集合で区間を分割し、各区間がそれぞれに含まれるかどうかから結果に含めるかを決める。
+    // 隣接する区間は結合する。
+    fn combine(&self, other: &Self, f: impl Fn(bool, bool) -> bool) -> Self {
+        let mut map = IntervalMap::<T, bool, Set>::new();
+        for (side, set) in [(false, self), (true, other)] {
+            for (start, end) in &set.intervals {
+                if let (Some(s), Some(e)) = (start, end)
+                    && s >= e
+                {
+                    continue;
+                }
+
+                map.insert_item((*start, *end), &side);
+            }
+        }
+
+        let mut intervals: Vec<(Option<T>, Option<T>)> = Vec::new();
+        for (start, end, sides) in map.iter() {
+            if !f(sides.contains(&false), sides.contains(&true)) {
+                continue;
+            }
+
+            match intervals.last_mut() {
+                Some((_, last_end)) if last_end.as_ref() == start => *last_end = end.copied(),
+                _ => intervals.push((start.copied(), end.copied())),
+            }
+        }
+
+        Self { intervals }
+    }
+}
+
+impl<T: PatternChar> MatchPattern<T> for PatternSet<T> {
+    fn insert(
+        &self,
+        builder: &mut Builder<T>,
+        context: &mut BuildContext,
+        from: StateId,
+    ) -> StateId {
+        builder.insert_set(context, from, self.intervals.iter().cloned())
+    }
+}
@@ -0,0 +1,52 @@
+use crate::{
+    match_graph::{BuildContext, Builder, MatchPattern, StateId},
+    pattern_char::PatternChar,
+};
+use proc_macro2::Span;
+use syn::{parse::Parse, spanned::Spanned};
+
+// PatternSub ::= "sub!(" + member + "<-" + path + ")"
+#[derive(Debug, Clone)]
+pub struct PatternSub {
+    pub field: String,
+    pub ty: syn::Path,
+    pub span: Span,
+}
+
+impl PatternSub {
+    pub fn from_mac(mac: &syn::Macro) -> syn::Result<Self> {
+        let (field, ty) = mac.parse_body_with(|input: syn::parse::ParseStream| {
+            let member = syn::Member::parse(input)?;
+            let _ = <syn::Token![<-]>::parse(input)?;
+            let ty = syn::Path::parse(input)?;
+
+            if !input.is_empty() {
+                return Err(input.error("Unexpected arguments of `sub!`."));
+            }
+
+            let field = match member {
+                syn::Member::Named(ident) => ident.to_string(),
+                syn::Member::Unnamed(index) => index.index.to_string(),
+            };
+
+            Ok((field, ty))
+        })?;
+
+        Ok(Self {
+            field,
+            ty,
+            span: mac.span(),
+        })
+    }
+}
+
+impl<T: PatternChar> MatchPattern<T> for PatternSub {
+    fn insert(
+        &self,
+        builder: &mut Builder<T>,
+        context: &mut BuildContext,
+        from: StateId,
+    ) -> StateId {
+        builder.insert_sub(context, from, &self.field, &self.ty, self.span)
+    }
+}
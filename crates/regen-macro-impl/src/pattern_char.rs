@@ -8,6 +8,13 @@ pub trait PatternChar: Ord + Copy + Eq + ToTokens + Discrete + Debug {
     fn try_from_lit(lit: &syn::Lit) -> Result<Self, syn::Error>;
     fn try_from_char(c: char) -> Result<Self, &'static str>;
     fn try_from_u8(b: u8) -> Result<Self, &'static str>;
+    fn from_ascii(b: u8) -> Self;
+    // 大文字小文字を区別しない場合に同一視する要素の組。(要素, 畳み込み先) の形で返す。
+    fn case_folding() -> impl Iterator<Item = (Self, Self)>;
+
+    // 要素の分類を表で引ける場合の、要素の番号の最大値。
+    const DENSE_MAX: Option<u64>;
+    fn to_index(self) -> u64;
 }
 
 impl PatternChar for char {
@@ -25,10 +32,24 @@ impl PatternChar for char {
     fn try_from_u8(_: u8) -> Result<Self, &'static str> {
         Err("char literal was expected.")
     }
+
+    fn from_ascii(b: u8) -> Self {
+        b.into()
+    }
+
+    fn case_folding() -> impl Iterator<Item = (Self, Self)> {
+        crate::unicode::CASE_FOLDING.iter().copied()
+    }
+
+    const DENSE_MAX: Option<u64> = Some(char::MAX as u64);
+
+    fn to_index(self) -> u64 {
+        self as u64
+    }
 }
 
 macro_rules! impl_pattern_primitive {
-    (@int $($ty: ty),*) => {
+    (@int $($ty: ty: $dense_max: expr),*) => {
         $(
             impl PatternChar for $ty {
                 fn try_from_lit(lit: &syn::Lit) -> Result<Self, syn::Error> {
@@ -47,9 +68,23 @@ macro_rules! impl_pattern_primitive {
                 fn try_from_u8(b: u8) -> Result<Self, &'static str> {
                     Ok(b.into())
                 }
+
+                fn from_ascii(b: u8) -> Self {
+                    b.into()
+                }
+
+                fn case_folding() -> impl Iterator<Item = (Self, Self)> {
+                    (b'A'..=b'Z').map(|b| (b.into(), b.to_ascii_lowercase().into()))
+                }
+
+                const DENSE_MAX: Option<u64> = $dense_max;
+
+                fn to_index(self) -> u64 {
+                    self as u64
+                }
             }
         )*
     };
 }
 
-impl_pattern_primitive!(@int usize, u8, u16, u32, u64);
+impl_pattern_primitive!(@int usize: None, u8: Some(0xff), u16: Some(0xffff), u32: None, u64: None);
@@ -1,38 +1,124 @@
 use crate::base_type::BaseType;
 
-pub enum RegenArgs {
-    BaseType(BaseType),
-    BaseTypeWithError(BaseType, syn::Path),
+// RegenArgs ::= base_type + ("," + (error_type | name + "=" + value))*
+// name ::= "codegen" | "max_states" | "max_depth"
+pub struct RegenArgs {
+    base_type: BaseType,
+    error_type: Option<syn::Path>,
+    codegen: Codegen,
+    max_states: usize,
+    max_depth: usize,
+}
+
+// DFAのステート数の上限の既定値。
+const DEFAULT_MAX_STATES: usize = 10000;
+
+// 再帰的な宣言の入れ子の深さの上限の既定値。
+const DEFAULT_MAX_DEPTH: usize = 256;
+
+// 状態機械の `advance` の生成方法。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Codegen {
+    // ステートと要素の範囲ごとの `match` で遷移する。
+    Match,
+    // 遷移表を引いて、遷移ごとの処理を呼び分ける。
+    Table,
 }
 
 impl RegenArgs {
     pub fn base_type(&self) -> &BaseType {
-        match self {
-            RegenArgs::BaseType(base_type) => base_type,
-            RegenArgs::BaseTypeWithError(base_type, _) => base_type,
-        }
+        &self.base_type
     }
 
     pub fn error_type(&self) -> Option<&syn::Path> {
-        match self {
-            RegenArgs::BaseType(_) => None,
-            RegenArgs::BaseTypeWithError(_, path) => Some(path),
-        }
+        self.error_type.as_ref()
+    }
+
+    pub fn codegen(&self) -> Codegen {
+        self.codegen
+    }
+
+    pub fn max_states(&self) -> usize {
+        self.max_states
+    }
+
+    pub fn max_depth(&self) -> usize {
+        self.max_depth
     }
 }
 
 impl syn::parse::Parse for RegenArgs {
     fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
         let base_type = input.parse::<BaseType>()?;
+        let mut error_type = None;
+        let mut codegen = None;
+        let mut max_states = None;
+        let mut max_depth = None;
 
-        if !input.peek(syn::Token![,]) {
-            return Ok(RegenArgs::BaseType(base_type));
-        }
-        input.parse::<syn::Token![,]>()?;
-        let error_type = input.parse::<syn::Path>().ok();
-        match error_type {
-            Some(v) => Ok(RegenArgs::BaseTypeWithError(base_type, v)),
-            None => Ok(RegenArgs::BaseType(base_type)),
+        while !input.is_empty() {
+            input.parse::<syn::Token![,]>()?;
+            if input.is_empty() {
+                break;
+            }
+
+            if !(input.peek(syn::Ident) && input.peek2(syn::Token![=])) {
+                let path = input.parse::<syn::Path>()?;
+                if error_type.is_some() {
+                    return Err(syn::Error::new_spanned(path, "Duplicated error types."));
+                }
+                error_type = Some(path);
+                continue;
+            }
+
+            let name = input.parse::<syn::Ident>()?;
+            input.parse::<syn::Token![=]>()?;
+            if name == "codegen" {
+                let value = input.parse::<syn::LitStr>()?;
+                if codegen.is_some() {
+                    return Err(syn::Error::new(name.span(), "Duplicated option `codegen`."));
+                }
+                codegen = Some(match value.value().as_str() {
+                    "match" => Codegen::Match,
+                    "table" => Codegen::Table,
+                    _ => {
+                        return Err(syn::Error::new(
+                            value.span(),
+                            "Unknown codegen. Expected `match` or `table`.",
+                        ));
+                    }
+                });
+            } else if name == "max_states" {
+                let value = input.parse::<syn::LitInt>()?;
+                if max_states.is_some() {
+                    return Err(syn::Error::new(
+                        name.span(),
+                        "Duplicated option `max_states`.",
+                    ));
+                }
+                max_states = Some(value.base10_parse::<usize>()?);
+            } else if name == "max_depth" {
+                let value = input.parse::<syn::LitInt>()?;
+                if max_depth.is_some() {
+                    return Err(syn::Error::new(
+                        name.span(),
+                        "Duplicated option `max_depth`.",
+                    ));
+                }
+                max_depth = Some(value.base10_parse::<usize>()?);
+            } else {
+                return Err(syn::Error::new(
+                    name.span(),
+                    format!("Unknown option `{}`.", name),
+                ));
+            }
         }
+
+        Ok(Self {
+            base_type,
+            error_type,
+            codegen: codegen.unwrap_or(Codegen::Match),
+            max_states: max_states.unwrap_or(DEFAULT_MAX_STATES),
+            max_depth: max_depth.unwrap_or(DEFAULT_MAX_DEPTH),
+        })
     }
 }
@@ -1,12 +1,20 @@
 use crate::{
-    base_type::BaseType, field_attibute::FieldAttribute, match_graph::MatchProp,
-    regen_args::RegenArgs,
+    base_type::BaseType,
+    field_attibute::FieldAttribute,
+    match_graph::MatchProp,
+    regen_args::{Codegen, RegenArgs},
+    struct_item::STRUCT_MARKER,
 };
 use quote::{ToTokens, format_ident, quote};
 use syn::{parse_quote, spanned::Spanned as _};
 
 pub struct RegenOptions {
     allow_conflict: bool,
+    ignore_case: bool,
+    max_depth: usize,
+    is_struct: bool,
+    codegen: Codegen,
+    max_states: usize,
     error_type: syn::Path,
     resolver: PathResolver,
 }
@@ -16,6 +24,29 @@ impl RegenOptions {
         self.allow_conflict
     }
 
+    pub fn ignore_case(&self) -> bool {
+        self.ignore_case
+    }
+
+    // 再帰的な宣言の入れ子の深さの上限。
+    pub fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    // `regen` が構造体に付けられていたかどうか。
+    pub fn is_struct(&self) -> bool {
+        self.is_struct
+    }
+
+    pub fn codegen(&self) -> Codegen {
+        self.codegen
+    }
+
+    // DFAのステート数の上限。
+    pub fn max_states(&self) -> usize {
+        self.max_states
+    }
+
     pub fn error_type(&self) -> &syn::Path {
         &self.error_type
     }
@@ -33,6 +64,8 @@ pub fn strip_options(
     let mut i = 0;
 
     let mut allow_conflict = false;
+    let mut ignore_case = false;
+    let mut is_struct = false;
     while i < attrs.len() {
         let Some(ident) = attrs[i].meta.path().get_ident() else {
             i += 1;
@@ -46,6 +79,19 @@ pub fn strip_options(
             continue;
         }
 
+        if ident == "ignore_case" {
+            let attr = attrs.swap_remove(i);
+            attr.meta.require_path_only()?.require_ident()?;
+            ignore_case = true;
+            continue;
+        }
+
+        if ident == STRUCT_MARKER {
+            attrs.swap_remove(i);
+            is_struct = true;
+            continue;
+        }
+
         i += 1;
     }
 
@@ -60,6 +106,11 @@ pub fn strip_options(
 
     Ok(RegenOptions {
         allow_conflict,
+        ignore_case,
+        max_depth: args.max_depth(),
+        is_struct,
+        codegen: args.codegen(),
+        max_states: args.max_states(),
         error_type,
         resolver,
     })
@@ -161,6 +212,35 @@ impl PathResolver {
         quote!(#lib::FromCharSequenceBuilder)
     }
 
+    pub fn sub_builder_type(&self) -> impl ToTokens {
+        let lib = self.regen_macro_lib();
+        quote!(#lib::SubBuilder)
+    }
+
+    pub fn sub_pattern_trait(&self) -> impl ToTokens {
+        let lib = self.regen_macro_lib();
+        quote!(#lib::SubPattern)
+    }
+
+    pub fn element_set_type(&self) -> impl ToTokens {
+        let lib = self.regen_macro_lib();
+        quote!(#lib::ElementSet)
+    }
+
+    pub fn validation_trait(&self) -> impl ToTokens {
+        let lib = self.regen_macro_lib();
+        quote!(#lib::Validation)
+    }
+
+    pub fn recursion_type_name(&self, item: &syn::ItemEnum) -> impl ToTokens {
+        format_ident!("__regen_macro_recursion_{}", item.ident)
+    }
+
+    pub fn vec_type(&self) -> impl ToTokens {
+        let lib = self.regen_macro_lib();
+        quote!(#lib::std::Vec)
+    }
+
     pub fn state_machine_type_name(&self, item: &syn::ItemEnum) -> impl ToTokens {
         format_ident!("__regen_macro_state_machine_{}", item.ident)
     }
@@ -177,22 +257,54 @@ impl PathResolver {
         quote::format_ident!("State_dead")
     }
 
+    // 列挙型の型引数を状態の型に現れさせるための、構築されない状態。
+    pub fn marker_state_variant_name(&self) -> impl ToTokens {
+        quote::format_ident!("State_marker")
+    }
+
+    pub fn phantom_data_type(&self) -> impl ToTokens {
+        let lib = self.regen_macro_lib();
+        quote!(#lib::std::PhantomData)
+    }
+
+    pub fn infallible_type(&self) -> impl ToTokens {
+        let lib = self.regen_macro_lib();
+        quote!(#lib::std::Infallible)
+    }
+
     pub fn state_field_name(&self, prop: &MatchProp) -> impl ToTokens {
         quote::format_ident!("_{}_{}", prop.assoc, prop.field)
     }
 
+    // 受理した経路でフィールドの値がどこから来たかを記録する状態機械のフィールド。
+    pub fn slot_field_name(&self, prop: &MatchProp) -> impl ToTokens {
+        quote::format_ident!("slot_{}_{}", prop.assoc, prop.field)
+    }
+
     pub fn state_field_type(
         &self,
         item: &syn::ItemEnum,
         attrs: &FieldAttribute,
         prop: &MatchProp,
+        sub: Option<&syn::Path>,
     ) -> impl ToTokens {
         if let Some(builder) = attrs.builder() {
             return quote! { #builder };
         }
 
         let base_type = self.base_type();
+        if let Some(ty) = sub {
+            let sub_builder_type = self.sub_builder_type();
+            return quote! { #sub_builder_type<#base_type, #ty> };
+        }
+
         let from_char_seq_trait = self.from_char_seq_trait();
+        // `#[map]` を持つフィールドは、文字列として集めてから変換する。
+        if attrs.map().is_some() {
+            let lib = self.regen_macro_lib();
+            return quote! { <#lib::std::String as #from_char_seq_trait<#base_type>>::Builder };
+        }
+
         let variant = &item.variants[prop.assoc];
         let ty = variant.fields.iter().enumerate().find_map(|(i, field)| {
             let field_name = field
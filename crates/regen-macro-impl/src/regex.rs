@@ -0,0 +1,550 @@
+use crate::{
+    pattern::{
+        Pattern, PatternAtom, PatternClass, PatternCollect, PatternIgnoreCase, PatternMany0,
+        PatternMany1, PatternNot, PatternOpt, PatternOr, PatternRepeat, PatternSeq,
+    },
+    pattern_char::PatternChar,
+};
+use proc_macro2::Span;
+use std::ops::Bound;
+
+// `#[regex = "..."]` の正規表現を `Pattern` に変換する。
+// パターンは常に入力全体にマッチするため、`^` と `$` は先頭と末尾にのみ書ける。
+//
+// Regex       ::= Alternation
+// Alternation ::= Concat ("|" Concat)*
+// Concat      ::= Repeat*
+// Repeat      ::= Atom ("*" | "+" | "?" | "{" n "}" | "{" n ",}" | "{" n "," m "}")*
+// Atom        ::= literal | "." | escape | "[" class "]" | "(" group ")"
+pub fn parse<T: PatternChar>(lit: &syn::LitStr) -> syn::Result<Pattern<T>> {
+    let value = lit.value();
+    let mut parser = Parser::new(lit, &value);
+
+    if parser.peek() == Some('^') {
+        parser.bump();
+    }
+
+    let pattern = parser.parse_alternation()?;
+    match parser.peek() {
+        None => Ok(pattern),
+        Some(')') => Err(parser.error_here("Unmatched `)`.")),
+        Some(_) => Err(parser.error_here("Unexpected character.")),
+    }
+}
+
+struct Parser<'a> {
+    lit: &'a syn::LitStr,
+    chars: Vec<(usize, char)>,
+    len: usize,
+    pos: usize,
+    // リテラルのソース上で、値の先頭が何バイト目にあるか。エスケープを含む場合は対応がとれないため `None`。
+    offset: Option<usize>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(lit: &'a syn::LitStr, value: &str) -> Self {
+        let source = lit.token().to_string();
+        let offset = if source.starts_with('r') {
+            source.find('"').map(|i| i + 1)
+        } else if source[1..source.len() - 1] == *value {
+            Some(1)
+        } else {
+            None
+        };
+
+        Self {
+            lit,
+            chars: value.char_indices().collect(),
+            len: value.len(),
+            pos: 0,
+            offset,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).map(|(_, c)| *c)
+    }
+
+    fn peek_nth(&self, n: usize) -> Option<char> {
+        self.chars.get(self.pos + n).map(|(_, c)| *c)
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        Some(c)
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn index(&self, pos: usize) -> usize {
+        self.chars.get(pos).map(|(i, _)| *i).unwrap_or(self.len)
+    }
+
+    // 正規表現の `start` 文字目から `end` 文字目までに対応するスパン。
+    // 部分的なスパンを作れない場合はリテラル全体を指す。
+    fn span(&self, start: usize, end: usize) -> Span {
+        let Some(offset) = self.offset else {
+            return self.lit.span();
+        };
+
+        let range = offset + self.index(start)..offset + self.index(end.max(start + 1));
+        self.lit
+            .token()
+            .subspan(range)
+            .unwrap_or_else(|| self.lit.span())
+    }
+
+    fn error(&self, start: usize, message: &str) -> syn::Error {
+        syn::Error::new(self.span(start, self.pos), message)
+    }
+
+    fn error_here(&self, message: &str) -> syn::Error {
+        syn::Error::new(self.span(self.pos, self.pos + 1), message)
+    }
+
+    fn parse_alternation<T: PatternChar>(&mut self) -> syn::Result<Pattern<T>> {
+        let mut pattern = self.parse_concat()?;
+        while self.eat('|') {
+            let rhs = self.parse_concat()?;
+            pattern = Pattern::Or(Box::new(PatternOr { lhs: pattern, rhs }));
+        }
+
+        Ok(pattern)
+    }
+
+    fn parse_concat<T: PatternChar>(&mut self) -> syn::Result<Pattern<T>> {
+        let mut patterns = Vec::new();
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+
+            if c == '$' && self.pos + 1 == self.chars.len() {
+                self.bump();
+                break;
+            }
+
+            patterns.push(self.parse_repeat()?);
+        }
+
+        if patterns.len() == 1 {
+            return Ok(patterns.pop().unwrap());
+        }
+
+        Ok(Pattern::Seq(PatternSeq { patterns }))
+    }
+
+    fn parse_repeat<T: PatternChar>(&mut self) -> syn::Result<Pattern<T>> {
+        let atom_start = self.pos;
+        let mut pattern = self.parse_atom()?;
+        loop {
+            let start = self.pos;
+            let repeat = match self.peek() {
+                Some('*') => {
+                    self.bump();
+                    Pattern::Many0(Box::new(PatternMany0 { pattern }))
+                }
+                Some('+') => {
+                    self.bump();
+                    Pattern::Many1(Box::new(PatternMany1 { pattern }))
+                }
+                Some('?') => {
+                    self.bump();
+                    Pattern::Opt(Box::new(PatternOpt { pattern }))
+                }
+                Some('{') => {
+                    let (start, end) = self.parse_counted()?;
+                    Pattern::Repeat(Box::new(PatternRepeat {
+                        pattern,
+                        start,
+                        end,
+                        span: self.span(atom_start, self.pos),
+                    }))
+                }
+                _ => break,
+            };
+
+            if self.peek() == Some('?') {
+                self.bump();
+                return Err(self.error(start, "Lazy quantifiers are not supported."));
+            }
+
+            pattern = repeat;
+        }
+
+        Ok(pattern)
+    }
+
+    fn parse_counted(&mut self) -> syn::Result<(Bound<usize>, Bound<usize>)> {
+        let start = self.pos;
+        self.bump();
+
+        let min = self.parse_number()?;
+        let max = if self.eat(',') {
+            self.parse_number()?
+        } else {
+            min
+        };
+
+        let (Some(min), true) = (min, self.eat('}')) else {
+            return Err(self.error(
+                start,
+                "Invalid repetition. `{n}`, `{n,}` or `{n,m}` was expected.",
+            ));
+        };
+
+        match max {
+            None => Ok((Bound::Included(min), Bound::Unbounded)),
+            Some(max) if max >= min => Ok((Bound::Included(min), Bound::Included(max))),
+            Some(_) => Err(self.error(start, "Range must not be empty.")),
+        }
+    }
+
+    fn parse_number(&mut self) -> syn::Result<Option<usize>> {
+        let start = self.pos;
+        let mut n: Option<usize> = None;
+        while let Some(d) = self.peek().and_then(|c| c.to_digit(10)) {
+            self.bump();
+            let next = n.unwrap_or(0).checked_mul(10).and_then(|n| n.checked_add(d as usize));
+            let Some(next) = next else {
+                while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+                    self.bump();
+                }
+                return Err(self.error(start, "Repetition count is too large."));
+            };
+            n = Some(next);
+        }
+        Ok(n)
+    }
+
+    fn parse_atom<T: PatternChar>(&mut self) -> syn::Result<Pattern<T>> {
+        let start = self.pos;
+        let Some(c) = self.bump() else {
+            return Err(self.error_here("Unexpected end of the pattern."));
+        };
+
+        match c {
+            '(' => self.parse_group(),
+            '[' => self.parse_class(),
+            '.' => {
+                let newline = Pattern::Atom(PatternAtom::Primitive(T::from_ascii(b'\n')));
+                Ok(Pattern::Not(Box::new(PatternNot {
+                    pattern: newline,
+                    span: self.span(start, self.pos),
+                })))
+            }
+            '\\' => self.parse_escape(false),
+            '*' | '+' | '?' | '{' => Err(self.error(start, "Nothing to repeat.")),
+            '^' | '$' => Err(self.error(
+                start,
+                "Anchors are only supported at the start and end of the pattern.",
+            )),
+            c => self.literal(start, c),
+        }
+    }
+
+    fn parse_group<T: PatternChar>(&mut self) -> syn::Result<Pattern<T>> {
+        let start = self.pos - 1;
+        let mut field = None;
+        let mut ignore_case = false;
+
+        if self.eat('?') {
+            match (self.peek(), self.peek_nth(1)) {
+                (Some(':'), _) => {
+                    self.bump();
+                }
+                (Some('i'), Some(':')) => {
+                    self.pos += 2;
+                    ignore_case = true;
+                }
+                (Some('=' | '!'), _) | (Some('<'), Some('=' | '!')) => {
+                    self.pos += 1;
+                    return Err(self.error(start, "Lookaround is not supported."));
+                }
+                (Some('<'), _) | (Some('P'), Some('<')) => {
+                    self.eat('P');
+                    self.bump();
+                    field = Some(self.parse_group_name(start)?);
+                }
+                _ => return Err(self.error(start, "Unsupported group.")),
+            }
+        }
+
+        let pattern = self.parse_alternation()?;
+        if !self.eat(')') {
+            return Err(self.error(start, "Unclosed group."));
+        }
+
+        let pattern = if ignore_case {
+            Pattern::IgnoreCase(Box::new(PatternIgnoreCase { pattern }))
+        } else {
+            pattern
+        };
+
+        Ok(match field {
+            Some(field) => Pattern::Collect(Box::new(PatternCollect { field, pattern })),
+            None => pattern,
+        })
+    }
+
+    fn parse_group_name(&mut self, start: usize) -> syn::Result<String> {
+        let name_start = self.pos;
+        let mut name = String::new();
+        loop {
+            match self.bump() {
+                Some('>') => break,
+                Some(c) => name.push(c),
+                None => return Err(self.error(start, "Unclosed group name.")),
+            }
+        }
+
+        if syn::parse_str::<syn::Ident>(&name).is_err() {
+            return Err(self.error(name_start, "Group names must be identifiers."));
+        }
+
+        Ok(name)
+    }
+
+    fn parse_class<T: PatternChar>(&mut self) -> syn::Result<Pattern<T>> {
+        let start = self.pos - 1;
+        let negated = self.eat('^');
+        let mut items: Vec<Pattern<T>> = Vec::new();
+
+        let mut first = true;
+        loop {
+            let item_start = self.pos;
+            let lower = match self.bump() {
+                None => return Err(self.error(start, "Unclosed character class.")),
+                Some(']') if !first => break,
+                Some('[') if self.peek() == Some(':') => {
+                    items.push(self.parse_posix_class(item_start)?);
+                    first = false;
+                    continue;
+                }
+                Some('\\') => match self.parse_escape(true)? {
+                    Pattern::Atom(PatternAtom::Primitive(c)) => c,
+                    p => {
+                        items.push(p);
+                        first = false;
+                        continue;
+                    }
+                },
+                Some(c) => self.element(item_start, c)?,
+            };
+            first = false;
+
+            if self.peek() != Some('-') || matches!(self.peek_nth(1), None | Some(']')) {
+                items.push(Pattern::Atom(PatternAtom::Primitive(lower)));
+                continue;
+            }
+
+            self.bump();
+            let upper_start = self.pos;
+            let upper = match self.bump() {
+                Some('\\') => match self.parse_escape(true)? {
+                    Pattern::Atom(PatternAtom::Primitive(c)) => c,
+                    _ => return Err(self.error(upper_start, "Invalid range in character class.")),
+                },
+                Some(c) => self.element(upper_start, c)?,
+                None => return Err(self.error(start, "Unclosed character class.")),
+            };
+
+            if upper < lower {
+                return Err(self.error(item_start, "Range must not be empty."));
+            }
+
+            items.push(Pattern::Atom(PatternAtom::Range(
+                Bound::Included(lower),
+                Bound::Included(upper),
+            )));
+        }
+
+        let Some(pattern) = items
+            .into_iter()
+            .reduce(|lhs, rhs| Pattern::Or(Box::new(PatternOr { lhs, rhs })))
+        else {
+            return Err(self.error(start, "Empty character class."));
+        };
+
+        if negated {
+            return Ok(Pattern::Not(Box::new(PatternNot {
+                pattern,
+                span: self.span(start, self.pos),
+            })));
+        }
+
+        Ok(pattern)
+    }
+
+    // `[:alpha:]` のようなPOSIXのクラス。組み込みのクラスに対応づける。
+    fn parse_posix_class<T: PatternChar>(&mut self, start: usize) -> syn::Result<Pattern<T>> {
+        self.bump();
+        let mut name = String::new();
+        loop {
+            match self.bump() {
+                Some(':') if self.eat(']') => break,
+                Some(c) => name.push(c),
+                None => return Err(self.error(start, "Unclosed character class.")),
+            }
+        }
+
+        match PatternClass::builtin_ranges(&name) {
+            Some(ranges) => Ok(ascii_ranges(ranges)),
+            None => Err(self.error(start, "Unknown character class.")),
+        }
+    }
+
+    // `\` の直後から読む。
+    fn parse_escape<T: PatternChar>(&mut self, in_class: bool) -> syn::Result<Pattern<T>> {
+        let start = self.pos - 1;
+        let Some(c) = self.bump() else {
+            return Err(self.error(start, "Unexpected end of the pattern."));
+        };
+
+        let class = match c {
+            'd' | 'D' => Some("digit"),
+            'w' | 'W' => Some("word"),
+            's' | 'S' => Some("space"),
+            _ => None,
+        };
+
+        if let Some(class) = class {
+            let pattern = ascii_ranges(PatternClass::builtin_ranges(class).unwrap());
+            if c.is_ascii_uppercase() {
+                return Ok(Pattern::Not(Box::new(PatternNot {
+                    pattern,
+                    span: self.span(start, self.pos),
+                })));
+            }
+            return Ok(pattern);
+        }
+
+        match c {
+            'n' => self.literal(start, '\n'),
+            't' => self.literal(start, '\t'),
+            'r' => self.literal(start, '\r'),
+            'f' => self.code_point(start, 0x0C),
+            'v' => self.code_point(start, 0x0B),
+            'a' => self.code_point(start, 0x07),
+            '0' => self.code_point(start, 0),
+            'x' | 'u' => {
+                let value = self.parse_hex(start, if c == 'x' { 2 } else { 4 })?;
+                self.code_point(start, value)
+            }
+            'p' | 'P' => self.parse_property(start, c == 'P'),
+            '1'..='9' | 'k' => Err(self.error(start, "Backreferences are not supported.")),
+            'b' if in_class => self.code_point(start, 0x08),
+            'b' | 'B' | 'A' | 'z' | 'Z' | 'G' | '<' | '>' => {
+                Err(self.error(start, "Assertions are not supported."))
+            }
+            c if c.is_ascii_punctuation() || c == ' ' => self.literal(start, c),
+            _ => Err(self.error(start, "Unknown escape sequence.")),
+        }
+    }
+
+    // `\xHH`、`\uHHHH`、`\x{H...}`、`\u{H...}`
+    fn parse_hex(&mut self, start: usize, digits: usize) -> syn::Result<u32> {
+        let braced = self.eat('{');
+        let mut text = String::new();
+        loop {
+            match self.peek() {
+                Some('}') if braced => {
+                    self.bump();
+                    break;
+                }
+                Some(c) if c.is_ascii_hexdigit() && (braced || text.len() < digits) => {
+                    self.bump();
+                    text.push(c);
+                }
+                _ if !braced && text.len() == digits => break,
+                _ => return Err(self.error(start, "Invalid hexadecimal escape.")),
+            }
+        }
+
+        u32::from_str_radix(&text, 16).map_err(|_| self.error(start, "Invalid hexadecimal escape."))
+    }
+
+    // `\p{Name}`、`\pL` はUnicodeのプロパティによるクラスとして解決する。
+    fn parse_property<T: PatternChar>(
+        &mut self,
+        start: usize,
+        negated: bool,
+    ) -> syn::Result<Pattern<T>> {
+        let name = if self.eat('{') {
+            let mut name = String::new();
+            loop {
+                match self.bump() {
+                    Some('}') => break,
+                    Some(c) => name.push(c),
+                    None => return Err(self.error(start, "Unclosed Unicode property.")),
+                }
+            }
+            name
+        } else {
+            match self.bump() {
+                Some(c) => c.to_string(),
+                None => return Err(self.error(start, "Unexpected end of the pattern.")),
+            }
+        };
+
+        let span = self.span(start, self.pos);
+        let Ok(ident) = syn::parse_str::<syn::Ident>(&name) else {
+            return Err(self.error(start, "Unsupported Unicode property."));
+        };
+        let ident = syn::Ident::new(&ident.to_string(), span);
+
+        let pattern = Pattern::Class(PatternClass { path: ident.into() });
+        if negated {
+            return Ok(Pattern::Not(Box::new(PatternNot { pattern, span })));
+        }
+
+        Ok(pattern)
+    }
+
+    fn literal<T: PatternChar>(&self, start: usize, c: char) -> syn::Result<Pattern<T>> {
+        let c = self.element(start, c)?;
+        Ok(Pattern::Atom(PatternAtom::Primitive(c)))
+    }
+
+    fn element<T: PatternChar>(&self, start: usize, c: char) -> syn::Result<T> {
+        if c.is_ascii() {
+            return Ok(T::from_ascii(c as u8));
+        }
+
+        T::try_from_char(c).map_err(|e| self.error(start, e))
+    }
+
+    // `\xFF` などのエスケープは、`char` ではその符号位置、整数型ではその値として扱う。
+    fn code_point<T: PatternChar>(&self, start: usize, value: u32) -> syn::Result<Pattern<T>> {
+        let c = match (char::from_u32(value), u8::try_from(value)) {
+            (Some(c), _) if c.is_ascii() => Ok(T::from_ascii(c as u8)),
+            (Some(c), Err(_)) => T::try_from_char(c),
+            (Some(c), Ok(b)) => T::try_from_char(c).or_else(|_| T::try_from_u8(b)),
+            (None, _) => Err("Invalid character."),
+        };
+
+        let c = c.map_err(|e| self.error(start, e))?;
+        Ok(Pattern::Atom(PatternAtom::Primitive(c)))
+    }
+}
+
+fn ascii_ranges<T: PatternChar>(ranges: &[(u8, u8)]) -> Pattern<T> {
+    ranges
+        .iter()
+        .map(|&(start, end)| {
+            Pattern::Atom(PatternAtom::Range(
+                Bound::Included(T::from_ascii(start)),
+                Bound::Included(T::from_ascii(end)),
+            ))
+        })
+        .reduce(|lhs, rhs| Pattern::Or(Box::new(PatternOr { lhs, rhs })))
+        .unwrap()
+}
@@ -0,0 +1,74 @@
+use syn::parse_quote;
+
+// 構造体は、構造体と同名の列挙子を一つだけ持つ列挙型として扱う。
+// 変換した列挙型には印を付け、生成の最後に構造体へ戻す。
+pub const STRUCT_MARKER: &str = "__regen_struct";
+
+// 構造体に付いていても、列挙子に付けたものとして扱う属性。
+const VARIANT_ATTRS: &[&str] = &["pattern", "regex", "validate"];
+
+pub fn parse_item(body: proc_macro2::TokenStream) -> syn::Result<syn::ItemEnum> {
+    match syn::parse2(body)? {
+        syn::Item::Enum(item) => Ok(item),
+        syn::Item::Struct(item) => to_enum(item),
+        item => Err(syn::Error::new_spanned(
+            item,
+            "`regen` can only be applied to enums and structs.",
+        )),
+    }
+}
+
+fn to_enum(item: syn::ItemStruct) -> syn::Result<syn::ItemEnum> {
+    let (variant_attrs, mut attrs): (Vec<_>, Vec<_>) = item.attrs.into_iter().partition(|a| {
+        a.path()
+            .get_ident()
+            .is_some_and(|ident| VARIANT_ATTRS.iter().any(|name| ident == name))
+    });
+    if !variant_attrs
+        .iter()
+        .any(|a| a.path().is_ident("pattern") || a.path().is_ident("regex"))
+    {
+        return Err(syn::Error::new(
+            item.ident.span(),
+            "Structs require a pattern attribute.",
+        ));
+    }
+
+    let marker = syn::Ident::new(STRUCT_MARKER, proc_macro2::Span::call_site());
+    attrs.push(parse_quote!(#[#marker]));
+
+    let variant = syn::Variant {
+        attrs: variant_attrs,
+        ident: item.ident.clone(),
+        fields: item.fields,
+        discriminant: None,
+    };
+
+    Ok(syn::ItemEnum {
+        attrs,
+        vis: item.vis,
+        enum_token: syn::Token![enum](item.struct_token.span),
+        ident: item.ident,
+        generics: item.generics,
+        brace_token: Default::default(),
+        variants: std::iter::once(variant).collect(),
+    })
+}
+
+pub fn to_struct(item: syn::ItemEnum) -> syn::ItemStruct {
+    let variant = item.variants.into_iter().next().unwrap();
+    let semi_token = match &variant.fields {
+        syn::Fields::Named(_) => None,
+        syn::Fields::Unnamed(_) | syn::Fields::Unit => Some(Default::default()),
+    };
+
+    syn::ItemStruct {
+        attrs: item.attrs,
+        vis: item.vis,
+        struct_token: syn::Token![struct](item.enum_token.span),
+        ident: item.ident,
+        generics: item.generics,
+        fields: variant.fields,
+        semi_token,
+    }
+}
@@ -0,0 +1,16 @@
+#[rustfmt::skip]
+mod case_folding;
+#[cfg(feature = "unicode")]
+#[rustfmt::skip]
+mod tables;
+
+pub use case_folding::CASE_FOLDING;
+
+// 一般カテゴリ、用字、派生プロパティの名前（短縮名を含む）から、その文字の範囲を引く。
+#[cfg(feature = "unicode")]
+pub fn class(name: &str) -> Option<&'static [(char, char)]> {
+    let i = tables::CLASSES
+        .binary_search_by_key(&name, |(n, _)| n)
+        .ok()?;
+    Some(tables::CLASSES[i].1)
+}
@@ -1,11 +1,17 @@
+pub mod bitset;
 pub mod discrete;
+pub mod edit_distance;
+pub mod interner;
 pub mod interval_map;
 pub mod iterable;
 pub mod linkedlist;
 pub mod range;
 pub mod sorted_vec;
 
+pub use bitset::BitSet;
 pub use discrete::Discrete;
+pub use edit_distance::edit_distance;
+pub use interner::Interner;
 pub use interval_map::IntervalMap;
 pub use iterable::Iterable;
 pub use linkedlist::LinkedList;
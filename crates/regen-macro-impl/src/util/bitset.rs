@@ -0,0 +1,69 @@
+// 非負整数の集合。最大の要素に比例する長さのビット列で表す。
+// 要素を取り除かないため、同じ要素を持つ集合は常に同じ表現になり、そのまま比較やハッシュに使える。
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BitSet {
+    words: Vec<u64>,
+}
+
+impl BitSet {
+    pub fn new() -> Self {
+        Self { words: Vec::new() }
+    }
+
+    // 要素を加え、新たに加わったかどうかを返す。
+    pub fn insert(&mut self, value: usize) -> bool {
+        let (word, bit) = (value / 64, 1 << (value % 64));
+        if self.words.len() <= word {
+            self.words.resize(word + 1, 0);
+        }
+
+        let is_new = self.words[word] & bit == 0;
+        self.words[word] |= bit;
+        is_new
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+
+    // 要素を昇順に列挙する。
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words.iter().enumerate().flat_map(|(i, &word)| {
+            let mut rest = word;
+            std::iter::from_fn(move || {
+                if rest == 0 {
+                    return None;
+                }
+                let bit = rest.trailing_zeros() as usize;
+                rest &= rest - 1;
+                Some(i * 64 + bit)
+            })
+        })
+    }
+}
+
+impl FromIterator<usize> for BitSet {
+    fn from_iter<I: IntoIterator<Item = usize>>(iter: I) -> Self {
+        let mut set = Self::new();
+        for value in iter {
+            set.insert(value);
+        }
+        set
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::BitSet;
+
+    #[test]
+    fn test_bitset() {
+        let mut set = BitSet::new();
+        assert!(set.is_empty());
+        assert!(set.insert(130));
+        assert!(set.insert(3));
+        assert!(!set.insert(3));
+        assert!(set.iter().eq([3, 130]));
+        assert_eq!(set, BitSet::from_iter([130, 3]));
+    }
+}
@@ -33,7 +33,10 @@ impl_discete!(@fp f64, f32);
 
 impl Discrete for char {
     fn next_up(&self) -> Option<Self> {
-        let n = (*self as u32).checked_add(1)?;
-        char::from_u32(n)
+        // サロゲート領域は char として表現できないため飛ばす。
+        match *self {
+            '\u{D7FF}' => Some('\u{E000}'),
+            c => char::from_u32((c as u32).checked_add(1)?),
+        }
     }
 }
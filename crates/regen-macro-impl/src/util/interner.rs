@@ -0,0 +1,37 @@
+use std::{collections::HashMap, hash::Hash};
+
+// 値に現れた順の通し番号を振る。同じ値には同じ番号を返す。
+#[derive(Debug)]
+pub struct Interner<T> {
+    ids: HashMap<T, usize>,
+    values: Vec<T>,
+}
+
+impl<T: Hash + Eq + Clone> Interner<T> {
+    pub fn new() -> Self {
+        Self {
+            ids: HashMap::new(),
+            values: Vec::new(),
+        }
+    }
+
+    // 値の番号と、値が新たに加わったかどうか。
+    pub fn intern(&mut self, value: T) -> (usize, bool) {
+        if let Some(&id) = self.ids.get(&value) {
+            return (id, false);
+        }
+
+        let id = self.values.len();
+        self.ids.insert(value.clone(), id);
+        self.values.push(value);
+        (id, true)
+    }
+
+    pub fn get(&self, id: usize) -> &T {
+        &self.values[id]
+    }
+
+    pub fn values(&self) -> &[T] {
+        &self.values
+    }
+}
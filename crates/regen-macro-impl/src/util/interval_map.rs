@@ -1,23 +1,22 @@
 pub mod store;
 
-use std::{
-    collections::BTreeMap,
-    ops::{Bound, Range, RangeFrom, RangeFull, RangeTo, RangeToInclusive},
-};
+use std::ops::{Range, RangeFrom, RangeFull, RangeTo, RangeToInclusive};
 
 use crate::util::{Discrete, Iterable, interval_map::store::Store};
 
+// 境界を昇順に並べたベクタと、境界で分けた区間ごとの値。
+// `values[0]` は最初の境界より前の区間、`values[i + 1]` は `bounds[i]` から始まる区間の値。
 #[derive(Debug)]
 pub struct IntervalMap<K: Ord + Clone, V: Clone, C: Store<V>> {
-    lower: C::Type,
-    divs: BTreeMap<K, C::Type>,
+    bounds: Vec<K>,
+    values: Vec<C::Type>,
 }
 
 impl<K: Ord + Clone, V: Clone, C: Store<V>> IntervalMap<K, V, C> {
     pub fn new() -> Self {
         Self {
-            lower: C::create(),
-            divs: Default::default(),
+            bounds: Vec::new(),
+            values: vec![C::create()],
         }
     }
 
@@ -40,76 +39,41 @@ impl<K: Ord + Clone, V: Clone, C: Store<V>> IntervalMap<K, V, C> {
         to: Option<K>,
         items: &I,
     ) {
-        let lower_bound = match &from {
-            Some(k) => {
-                if !self.divs.contains_key(k) {
-                    let vs = self
-                        .divs
-                        .range(..k)
-                        .last()
-                        .map(|e| e.1)
-                        .unwrap_or(&self.lower);
-
-                    let mut v: C::Type = C::create();
-                    C::extend(&mut v, vs.iter().cloned());
-
-                    self.divs.insert(k.clone(), v);
-                }
-                Bound::Included(k)
-            }
-            None => Bound::Unbounded,
+        let first = match from {
+            Some(k) => self.split(k),
+            None => 0,
         };
-
-        let upper_bound = match &to {
-            Some(k) => {
-                if !self.divs.contains_key(k) {
-                    let vs = self
-                        .divs
-                        .range(..k)
-                        .last()
-                        .map(|e| e.1)
-                        .unwrap_or(&self.lower);
-
-                    let mut v: C::Type = C::create();
-                    C::extend(&mut v, vs.iter().cloned());
-
-                    self.divs.insert(k.clone(), v);
-                }
-                Bound::Excluded(k)
-            }
-            None => Bound::Unbounded,
+        let last = match to {
+            Some(k) => self.split(k),
+            None => self.values.len(),
         };
 
-        if from.is_none() {
-            C::extend(&mut self.lower, items.iter().cloned());
-        }
-
-        for (_, interval) in self.divs.range_mut((lower_bound, upper_bound)) {
-            C::extend(interval, items.iter().cloned());
+        for value in &mut self.values[first..last] {
+            C::extend(value, items.iter().cloned());
         }
     }
 
-    pub fn combine(&mut self, other: &IntervalMap<K, V, C>) {
-        for (from, to, s) in other.iter() {
-            self.insert_ropen(from.cloned(), to.cloned(), s);
+    // `bound` を境界に加え、`bound` から始まる区間の番号を返す。
+    // 新しい区間の値は、分割した区間の値を引き継ぐ。
+    fn split(&mut self, bound: K) -> usize {
+        match self.bounds.binary_search(&bound) {
+            Ok(i) => i + 1,
+            Err(i) => {
+                let value = self.values[i].clone();
+                self.bounds.insert(i, bound);
+                self.values.insert(i + 1, value);
+                i + 1
+            }
         }
     }
 
     pub fn iter(&self) -> impl Iterator<Item = (Option<&K>, Option<&K>, &C::Type)> {
-        self.divs
-            .iter()
-            .map(Some)
-            .chain([None])
-            .scan(Some((None, &self.lower)), |state, upper| {
-                let (last_lower_bound, last_assoc) = state.take()?;
-                match upper {
-                    Some((upper_bound, assoc)) => {
-                        *state = Some((Some(upper_bound), assoc));
-                        Some((last_lower_bound, Some(upper_bound), last_assoc))
-                    }
-                    None => Some((last_lower_bound, None, last_assoc)),
-                }
-            })
+        let starts = std::iter::once(None).chain(self.bounds.iter().map(Some));
+        let ends = self.bounds.iter().map(Some).chain([None]);
+        starts
+            .zip(ends)
+            .zip(&self.values)
+            .map(|((start, end), value)| (start, end, value))
     }
 }
 
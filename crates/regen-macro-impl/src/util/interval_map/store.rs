@@ -1,9 +1,9 @@
 use std::collections::BTreeSet;
 
-use crate::util::Iterable;
+use crate::util::BitSet;
 
 pub trait Store<T> {
-    type Type: Iterable<Item = T>;
+    type Type: Clone;
 
     fn create() -> Self::Type;
     fn extend(store: &mut Self::Type, iter: impl IntoIterator<Item = T>);
@@ -12,7 +12,7 @@ pub trait Store<T> {
 #[derive(Debug)]
 pub struct Set;
 
-impl<T: Ord> Store<T> for Set {
+impl<T: Ord + Clone> Store<T> for Set {
     type Type = BTreeSet<T>;
     fn create() -> Self::Type {
         Default::default()
@@ -26,7 +26,7 @@ impl<T: Ord> Store<T> for Set {
 #[derive(Debug)]
 pub struct Unique;
 
-impl<T> Store<T> for Unique {
+impl<T: Clone> Store<T> for Unique {
     type Type = Option<T>;
 
     fn create() -> Self::Type {
@@ -37,3 +37,21 @@ impl<T> Store<T> for Unique {
         *store = iter.into_iter().last();
     }
 }
+
+// 非負整数の集合をビット列で持つ。
+#[derive(Debug)]
+pub struct Bits;
+
+impl Store<usize> for Bits {
+    type Type = BitSet;
+
+    fn create() -> Self::Type {
+        BitSet::new()
+    }
+
+    fn extend(store: &mut Self::Type, iter: impl IntoIterator<Item = usize>) {
+        for value in iter {
+            store.insert(value);
+        }
+    }
+}
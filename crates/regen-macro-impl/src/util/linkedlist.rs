@@ -35,11 +35,7 @@ impl<T> LinkedList<T> {
         let mut node = &self.node;
         let mut buf = Vec::new();
 
-        loop {
-            let Some(n) = node else {
-                break;
-            };
-
+        while let Some(n) = node {
             buf.push(n.item.clone());
 
             node = &n.prev;
@@ -5,12 +5,15 @@ use crate::{
     field_attibute::{FieldAttribute, strip_field_attribute},
     pattern::Pattern,
     pattern_char::PatternChar,
+    regex,
 };
 use syn::spanned::Spanned;
 
 pub struct VariantPattern<T: PatternChar> {
     pub pattern: Pattern<T>,
     pub declares: Declares<T>,
+    pub ignore_case: bool,
+    pub validate: Option<syn::Path>,
     pub field_attrs: HashMap<String, FieldAttribute>,
 }
 
@@ -25,6 +28,8 @@ pub fn strip_variant_attrs<T: PatternChar>(
         };
 
         let declares = strip_variant_declares(v)?;
+        let ignore_case = strip_variant_ignore_case(v)?;
+        let validate = strip_variant_validate(v)?;
         for (i, f) in v.fields.iter_mut().enumerate() {
             let a = strip_field_attribute(f)?;
             let name = f
@@ -39,6 +44,8 @@ pub fn strip_variant_attrs<T: PatternChar>(
         buf.push(VariantPattern {
             pattern,
             declares,
+            ignore_case,
+            validate,
             field_attrs,
         });
     }
@@ -53,7 +60,7 @@ fn strip_variant_patterns<T: PatternChar>(
             return false;
         };
 
-        ident == "pattern"
+        ident == "pattern" || ident == "regex"
     });
 
     let Some(attr) = attrs.next() else {
@@ -65,6 +72,20 @@ fn strip_variant_patterns<T: PatternChar>(
     }
 
     let name_value = attr.meta.require_name_value()?;
+    if name_value.path.is_ident("regex") {
+        let lit = match &name_value.value {
+            syn::Expr::Lit(syn::ExprLit {
+                lit: syn::Lit::Str(lit),
+                ..
+            }) => lit,
+            e => {
+                return Err(syn::Error::new(e.span(), "string literal was expected."));
+            }
+        };
+
+        return regex::parse(lit).map(Some);
+    }
+
     let pattern = Pattern::new(&name_value.value)?;
     Ok(Some(pattern))
 }
@@ -82,8 +103,62 @@ fn strip_variant_declares<T: PatternChar>(variant: &mut syn::Variant) -> syn::Re
     for attr in attrs {
         let meta = attr.meta.require_list()?;
         let decl: Declares<T> = syn::parse2(meta.tokens.clone())?;
+        if let Some((name, _)) = decl.rules().first() {
+            return Err(syn::Error::new(
+                name.span(),
+                "Recursive declarations must be placed on the enum.",
+            ));
+        }
         declares.append(decl);
     }
 
     Ok(declares)
 }
+
+fn strip_variant_ignore_case(variant: &mut syn::Variant) -> syn::Result<bool> {
+    let mut attrs = variant.attrs.extract_if(.., |a| {
+        let Some(ident) = a.meta.path().get_ident() else {
+            return false;
+        };
+
+        ident == "ignore_case"
+    });
+
+    let Some(attr) = attrs.next() else {
+        return Ok(false);
+    };
+
+    if let Some(a) = attrs.next() {
+        return Err(syn::Error::new(
+            a.span(),
+            "Duplicated ignore_case attributes.",
+        ));
+    }
+
+    attr.meta.require_path_only()?;
+    Ok(true)
+}
+
+fn strip_variant_validate(variant: &mut syn::Variant) -> syn::Result<Option<syn::Path>> {
+    let mut attrs = variant.attrs.extract_if(.., |a| {
+        let Some(ident) = a.meta.path().get_ident() else {
+            return false;
+        };
+
+        ident == "validate"
+    });
+
+    let Some(attr) = attrs.next() else {
+        return Ok(None);
+    };
+
+    if let Some(a) = attrs.next() {
+        return Err(syn::Error::new(a.span(), "Duplicated validate attributes."));
+    }
+
+    let name_value = attr.meta.require_name_value()?;
+    match &name_value.value {
+        syn::Expr::Path(p) => Ok(Some(p.path.clone())),
+        e => Err(syn::Error::new(e.span(), "validate path was expected.")),
+    }
+}
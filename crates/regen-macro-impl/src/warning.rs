@@ -0,0 +1,32 @@
+use proc_macro2::{Span, TokenStream};
+use quote::{ToTokens, quote_spanned};
+
+// 安定版のproc macroは警告を出せないため、非推奨の定数を参照させてコンパイラに警告させる。
+#[derive(Debug, Clone)]
+pub struct Warning {
+    span: Span,
+    message: String,
+}
+
+impl Warning {
+    pub fn new(span: Span, message: impl Into<String>) -> Self {
+        Self {
+            span,
+            message: message.into(),
+        }
+    }
+}
+
+impl ToTokens for Warning {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let message = &self.message;
+        tokens.extend(quote_spanned! {self.span=>
+            const _: () = {
+                #[deprecated(note = #message)]
+                #[allow(non_upper_case_globals)]
+                const regen_warning: () = ();
+                regen_warning
+            };
+        });
+    }
+}
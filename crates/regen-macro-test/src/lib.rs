@@ -1,4 +1,6 @@
-use std::num::ParseIntError;
+#![allow(dead_code)]
+
+use std::{borrow::Cow, num::ParseIntError};
 
 use regen::{regen, FromCharSequence, FromCharSequenceBuilder};
 
@@ -109,6 +111,500 @@ impl FromCharSequenceBuilder<char> for UsizeHexBuilder {
     }
 }
 
+#[derive(Debug, PartialEq)]
+#[regen(char)]
+enum Quoted {
+    #[pattern = '"' + collect!(_body <- [!('"' | '\\'); ..]) + '"']
+    Str { _body: String },
+    #[pattern = collect!(_c <- not!('"'))]
+    Other { _c: String },
+}
+
+#[derive(Debug, PartialEq)]
+#[regen(char)]
+#[declare(ident = ['a'..='z'; 1..]; keyword = "if" | "else")]
+enum Word {
+    #[pattern = keyword]
+    Keyword,
+    #[pattern = collect!(_name <- ((ident & [..; 3..=3]) - keyword))]
+    Ident3 { _name: String },
+    #[pattern = collect!(_name <- (ident - keyword - [..; 3..=3]))]
+    Ident { _name: String },
+}
+
+#[derive(Debug, PartialEq)]
+#[regen(u8)]
+enum ByteToken {
+    #[pattern = collect!(_num <- [digit; 1..])]
+    Number { _num: String },
+    #[pattern = collect!(_name <- (alpha | b'_') + [word; ..])]
+    Ident { _name: String },
+    #[pattern = [space; 1..]]
+    Space,
+    #[pattern = b"0x" + collect!(_hex <- [xdigit; 1..])]
+    Hex { _hex: String },
+    #[pattern = !(alnum | space | b'_')]
+    Other,
+}
+
+#[derive(Debug, PartialEq)]
+#[regen(char)]
+enum Intl {
+    #[pattern = collect!(_name <- XID_Start + [XID_Continue; ..])]
+    Ident { _name: String },
+    #[pattern = collect!(_num <- [Nd; 1..])]
+    Number { _num: String },
+    #[pattern = collect!(_text <- [Han; 1..]) + '。']
+    Sentence { _text: String },
+    #[pattern = [White_Space; 1..]]
+    Space,
+}
+
+#[derive(Debug, PartialEq)]
+#[regen(u8)]
+#[ignore_case]
+enum SqlKeyword {
+    #[pattern = b"select"]
+    Select,
+    #[pattern = b"from"]
+    From,
+    #[pattern = b'x'..=b'z']
+    Xyz,
+}
+
+#[derive(Debug, PartialEq)]
+#[regen(char)]
+enum Greeting {
+    #[pattern = ignore_case!("hello") + ", " + collect!(_name <- [alpha; 1..])]
+    Hello { _name: String },
+    #[ignore_case]
+    #[pattern = "σ"]
+    Sigma,
+}
+
+#[derive(Debug, PartialEq)]
+#[regen(char)]
+enum Folded {
+    #[pattern = 'n' + ignore_case!(!'s')]
+    NotS,
+    #[regex = r"m(?i:[^a-z])"]
+    NotLetter,
+    #[ignore_case]
+    #[pattern = 'o' + (alpha - 't')]
+    NotT,
+}
+
+#[derive(Debug, PartialEq)]
+#[regen(char)]
+enum Bounded {
+    #[pattern = 'x' + [digit; 2..=3]]
+    Inclusive,
+    #[pattern = 'y' + [digit; 2..4]]
+    Exclusive,
+}
+
+#[derive(Debug, PartialEq)]
+#[regen(char)]
+enum RegexToken {
+    #[regex = r"^0x(?<_hex>[0-9A-Fa-f]+)$"]
+    Hex { _hex: String },
+    #[regex = r"(?<_int>\d+)(\.(?<_frac>\d{1,3}))?"]
+    Number { _int: String, _frac: String },
+    #[regex = r"(?i:null)|\(\)"]
+    Null,
+    #[regex = r"\p{Greek}+|[^\w\s\p{Greek}()]"]
+    Other,
+}
+
+#[derive(Debug, PartialEq)]
+#[regen(char)]
+enum List {
+    #[pattern = '[' + collect!(_items <- sep_by!([digit; 1..], ", ")) + ']']
+    Numbers { _items: String },
+    #[pattern = '(' + collect!(_items <- sep_by!(alpha, ',', 2..=3)) + ')']
+    Tuple { _items: String },
+    #[pattern = opt!('-') + many1!(digit) + many0!('_' + many1!(digit))]
+    Integer,
+}
+
+#[derive(Debug, PartialEq)]
+#[regen(char)]
+enum Repeated {
+    #[pattern = sep_by!(collect!(_octets <- [digit; 1..=3]), '.', 4..=4)]
+    Ipv4 { _octets: Vec<usize> },
+    #[pattern = '#' + [collect!(_digits <- digit); 1..]]
+    Digits { _digits: Vec<usize> },
+    #[pattern = '"' + sep_by!(collect!(_words <- [alpha; 1..]), ' ') + '"']
+    Words { _words: Vec<String> },
+    #[pattern = '$' + [collect!(_text <- digit); 1..]]
+    Joined { _text: String },
+}
+
+#[derive(Debug, PartialEq)]
+#[regen(char)]
+enum Num {
+    #[pattern = collect!(_value <- [digit; 1..])]
+    Dec { _value: usize },
+    #[pattern = "0x" + collect!(_value <- [xdigit; 1..])]
+    Hex { _value: String },
+}
+
+#[derive(Debug, PartialEq)]
+#[regen(char)]
+enum Shape {
+    #[pattern = collect!(0 <- [alpha; 1..]) + ':' + collect!(1 <- [digit; 1..])]
+    Pair(String, usize),
+    #[pattern = '#' + collect!(value <- [digit; 1..])]
+    Named { value: usize },
+    #[pattern = ';']
+    Unit,
+    #[pattern = '@' + sub!(0 <- Num) + '/' + collect!(1 <- [alpha; 1..])]
+    Mixed(Num, String),
+}
+
+#[derive(Debug, PartialEq)]
+#[regen(char)]
+enum Statement {
+    #[pattern = collect!(_name <- [alpha; 1..]) + '=' + sub!(_value <- Num) + ';']
+    Assign { _name: String, _value: Num },
+    #[pattern = '@' + sub!(_target <- Num)]
+    Jump { _target: Num },
+    #[pattern = '(' + sep_by!(sub!(_items <- Num), ',', 1..) + ')']
+    Tuple { _items: Num },
+}
+
+#[derive(Debug, PartialEq)]
+#[regen(char, max_depth = 4)]
+#[declare(
+    rec group = '(' + many0!(group) + ')';
+    rec value = number | list;
+    number = [digit; 1..];
+    rec list = '[' + sep_by!(value, ',') + ']'
+)]
+enum Nested {
+    #[pattern = many1!(group)]
+    Parens,
+    #[pattern = collect!(_text <- list)]
+    List { _text: String },
+}
+
+// `Signed` は外側の `sign` を意図して覆い隠している。
+#[allow(deprecated)]
+#[derive(Debug, PartialEq)]
+#[regen(char)]
+#[declare(
+    list(p, sep) = p + [sep + p; ..];
+    p = 'x';
+    sign = '-';
+    signed(p) = opt!(sign) + p;
+    wrapped(p) = '(' + list(p, ',') + ')';
+    number = [digit; 1..]
+)]
+enum Params {
+    #[pattern = collect!(_items <- list(number, ';'))]
+    Numbers { _items: String },
+    #[pattern = '#' + wrapped(alpha)]
+    Letters,
+    #[declare(sign = '+')]
+    #[pattern = '=' + signed(number)]
+    Signed,
+    #[pattern = '!' + p]
+    X,
+}
+
+#[derive(Debug, PartialEq)]
+#[regen(char)]
+#[declare(
+    assign = ident + '=' + value;
+    value = number | ident;
+    ident = alpha + [alnum; ..]
+)]
+#[declare(number = [digit; 1..])]
+enum Unordered {
+    #[pattern = assign]
+    Assign,
+    #[declare(call = name + '(' + value + ')'; name = '@' + ident)]
+    #[pattern = call]
+    Call,
+}
+
+const CODE_LEN: usize = 3;
+const LAST_GRADE: char = 'c';
+
+struct Limits;
+
+impl Limits {
+    const MAX_DIGITS: usize = 4;
+}
+
+#[derive(Debug, PartialEq)]
+#[regen(char)]
+enum Counted {
+    #[pattern = '#' + [xdigit; CODE_LEN..=CODE_LEN]]
+    Code,
+    #[pattern = 'n' + collect!(_digits <- [digit; 1..=Limits::MAX_DIGITS]) + ';']
+    Number { _digits: String },
+    #[pattern = 'g' + ('a'..=LAST_GRADE)]
+    Grade,
+    #[pattern = 'o' + repeat!(alpha, ..CODE_LEN - 1)]
+    Short,
+}
+
+#[derive(Debug, PartialEq)]
+#[regen(char)]
+enum Graded {
+    #[pattern = collect!(_grade <- ('a'..=LAST_GRADE)) + opt!('+')]
+    Grade { _grade: String },
+    #[pattern = 'z' + opt!('+')]
+    Last,
+}
+
+#[derive(Debug, PartialEq)]
+#[regen(char)]
+enum Ranked {
+    #[pattern = sub!(0 <- Graded) + sub!(1 <- Num)]
+    Rank(Graded, Num),
+}
+
+#[derive(Debug, PartialEq)]
+#[regen(char)]
+enum Literal {
+    #[pattern = opt!("0x" + set!(_radix = Radix::Hexadecimal)) + collect!(_digits <- [xdigit; 1..])]
+    Number {
+        #[default = Radix::Decimal]
+        _radix: Radix,
+        _digits: String,
+    },
+    #[pattern = ("true" + set!(0 = true)) | ("false" + set!(0 = false))]
+    Bool(bool),
+}
+
+#[derive(Debug, PartialEq)]
+#[regen(char)]
+enum Checked {
+    #[validate = check_date]
+    #[pattern = collect!(_month <- [digit; 1..]) + '/' + collect!(_day <- [digit; 1..])]
+    Date { _month: usize, _day: usize },
+    #[validate = is_percent]
+    #[pattern = collect!(0 <- [digit; 1..]) + '%']
+    Percent(#[map = parse_percent] u32),
+}
+
+fn check_date(value: &Checked) -> Result<(), &'static str> {
+    match value {
+        Checked::Date { _month: 1..=12, _day: 1..=31 } => Ok(()),
+        Checked::Date { _month: 1..=12, .. } => Err("invalid day"),
+        _ => Err("invalid month"),
+    }
+}
+
+fn is_percent(value: &Checked) -> bool {
+    matches!(value, Checked::Percent(0..=100))
+}
+
+fn parse_percent(s: String) -> u32 {
+    s.parse().unwrap_or(u32::MAX)
+}
+
+#[derive(Debug, PartialEq)]
+#[regen(char)]
+enum Generic<'a, N: FromCharSequence<char>>
+where
+    N::Error: Into<Box<dyn std::error::Error>>,
+{
+    #[pattern = collect!(_value <- [digit; 1..])]
+    Number { _value: N },
+    #[pattern = '"' + collect!(0 <- [alpha; ..]) + '"']
+    Text(#[map = to_cow] Cow<'a, str>),
+}
+
+fn to_cow<'a>(s: String) -> Cow<'a, str> {
+    Cow::Owned(s)
+}
+
+#[derive(Debug, PartialEq)]
+#[regen(char)]
+#[declare(use common::number)]
+#[pattern = collect!(_major <- number) + '.' + collect!(_minor <- number) + '.' + collect!(_patch <- number)]
+struct Version {
+    _major: usize,
+    _minor: usize,
+    _patch: usize,
+}
+
+#[derive(Debug, PartialEq)]
+#[regen(char)]
+#[declare(hex = [xdigit; 2..=2])]
+#[pattern = '#' + collect!(0 <- hex) + collect!(1 <- hex) + collect!(2 <- hex)]
+struct Rgb(String, String, String);
+
+#[derive(Debug, PartialEq)]
+#[regen(char)]
+#[ignore_case]
+#[pattern = "null"]
+struct Null;
+
+#[derive(Debug, PartialEq)]
+#[regen(char, codegen = "table")]
+#[declare(
+    rec list = '[' + sep_by!(number, ',') + ']';
+    number = [digit; 1..]
+)]
+enum Tabled {
+    #[pattern = collect!(_name <- [alpha; 1..]) + '=' + sub!(_value <- Num) + ';']
+    Assign { _name: String, _value: Num },
+    #[pattern = opt!("0x" + set!(_radix = Radix::Hexadecimal)) + collect!(_digits <- [xdigit; 1..])]
+    Number {
+        #[default = Radix::Decimal]
+        _radix: Radix,
+        _digits: String,
+    },
+    #[pattern = '#' + collect!(_digits <- [digit; 1..=Limits::MAX_DIGITS]) + ';']
+    Counted { _digits: String },
+    #[pattern = collect!(_text <- list)]
+    List { _text: String },
+    #[pattern = '@' + collect!(_grade <- ('a'..=LAST_GRADE))]
+    Grade { _grade: String },
+}
+
+#[derive(Debug, PartialEq)]
+#[regen(u16)]
+enum Wide16 {
+    #[pattern = [0x30..=0x39; 1..]]
+    Digits,
+    #[pattern = 0x3042 + [0x3000..=0x30ff; ..]]
+    Kana,
+}
+
+#[derive(Debug, PartialEq)]
+#[regen(u32, codegen = "table")]
+enum Wide32 {
+    #[pattern = [0x30..=0x39; 1..]]
+    Digits,
+    #[pattern = 0x1f600 + [0x30..=0x39; ..]]
+    Emoji,
+}
+
+#[derive(Debug, PartialEq)]
+#[regen(char)]
+enum Digest {
+    #[pattern = "sha256:" + collect!(_hex <- [xdigit; 64..=64])]
+    Sha256 { _hex: String },
+    #[pattern = "id:" + collect!(_id <- [digit; 1..=20]) + '.' + collect!(_ext <- [alpha; ..=12])]
+    Id { _id: String, _ext: String },
+}
+
+// 繰り返しの後が同じ要素で始まるため、カウンタを使わずに展開する。
+#[derive(Debug, PartialEq)]
+#[regen(char)]
+enum Padded {
+    #[pattern = "pad:" + [digit; 0..=16] + "00"]
+    Padded,
+}
+
+regen::patterns! {
+    pub mod common {
+        ident = alpha + [alnum; ..];
+        number = [digit; 1..];
+        pair(p) = p + ',' + p
+    }
+}
+
+#[derive(Debug, PartialEq)]
+#[regen(char)]
+#[declare(use common::*)]
+enum Imported {
+    #[pattern = collect!(_name <- ident) + '=' + collect!(_value <- number)]
+    Assign { _name: String, _value: usize },
+    #[pattern = '(' + pair(number) + ')']
+    Pair,
+}
+
+#[derive(Debug, PartialEq)]
+#[regen(u8)]
+enum ImportedBytes {
+    #[declare(sign = b'-'; use crate::common::{number}; signed = opt!(sign) + number)]
+    #[pattern = signed]
+    Number,
+}
+
+#[allow(deprecated)]
+#[derive(Debug, PartialEq)]
+#[regen(char)]
+#[declare(number = [digit; 1..])]
+enum Refined {
+    #[declare(number = number + '.')]
+    #[pattern = 'f' + number]
+    Float,
+    #[pattern = 'i' + number]
+    Int,
+}
+
+mod digit_library {
+    regen::patterns! {
+        pub mod token {
+            token = [digit; 1..]
+        }
+    }
+}
+
+mod alpha_library {
+    regen::patterns! {
+        pub mod token {
+            token = [alpha; 1..]
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+#[regen(char)]
+#[declare(use digit_library::token::*)]
+enum DigitToken {
+    #[pattern = token]
+    Token,
+}
+
+#[derive(Debug, PartialEq)]
+#[regen(char)]
+#[declare(use alpha_library::token::*)]
+enum AlphaToken {
+    #[pattern = token]
+    Token,
+}
+
+mod hex_library {
+    regen::patterns! {
+        pub(crate) mod token {
+            token = [xdigit; 1..]
+        }
+    }
+}
+
+mod hex_library_copy {
+    regen::patterns! {
+        mod token {
+            token = [xdigit; 1..]
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+#[regen(char)]
+#[declare(use hex_library::token::*)]
+enum HexToken {
+    #[pattern = token]
+    Token,
+}
+
+#[derive(Debug, PartialEq)]
+#[regen(char)]
+enum Prefixed {
+    #[pattern = '#' + collect!(_name <- ['a'..='z'; ..]) + ';']
+    Tag { _name: String },
+    #[pattern = '@' + collect!(_name <- "x" | "") + ';']
+    Mark { _name: String },
+}
+
 #[regen(char)]
 pub enum PublicCase {
     #[pattern = ""]
@@ -120,7 +616,23 @@ pub enum PublicCase {
 #[cfg(test)]
 mod test {
     use super::*;
-    use regen::{AdvanceResult, MatchError, Parse, StateMachine};
+    use regen::{
+        AdvanceResult, CompleteResult, ElementSet, MatchError, Parse, StateMachine, SubPattern,
+    };
+
+    fn parse<P: Parse<char>>(s: &str) -> Option<P> {
+        parse_elements(&s.chars().collect::<Vec<_>>())
+    }
+
+    fn parse_elements<T: Copy, P: Parse<T>>(s: &[T]) -> Option<P> {
+        let mut machine = P::StateMachine::default();
+        for &c in s {
+            if machine.advance(c) == AdvanceResult::Error {
+                return None;
+            }
+        }
+        machine.current().ok()
+    }
 
     #[test]
     fn test_partial_match_x() {
@@ -202,7 +714,7 @@ mod test {
         match r {
             AdvanceResult::Match(c) => {
                 let v = machine.current().unwrap();
-                assert_eq!(v, Complex::Digits { _radix: Radix::Decimal, _digits: format!("0") });
+                assert_eq!(v, Complex::Digits { _radix: Radix::Decimal, _digits: String::from("0") });
                 assert_eq!(c, 1);
             }
             _ => unreachable!(),
@@ -212,7 +724,7 @@ mod test {
         match r {
             AdvanceResult::Match(c) => {
                 let v = machine.current().unwrap();
-                assert_eq!(v, Complex::Digits { _radix: Radix::Decimal, _digits: format!("01") });
+                assert_eq!(v, Complex::Digits { _radix: Radix::Decimal, _digits: String::from("01") });
                 assert_eq!(c, 1);
             }
             _ => unreachable!(),
@@ -236,7 +748,7 @@ mod test {
         match r {
             AdvanceResult::Match(c) => {
                 let v = machine.current().unwrap();
-                assert_eq!(v, Complex::Digits { _radix: Radix::Decimal, _digits: format!("0") });
+                assert_eq!(v, Complex::Digits { _radix: Radix::Decimal, _digits: String::from("0") });
                 assert_eq!(c, 1);
             }
             _ => unreachable!(),
@@ -256,7 +768,7 @@ mod test {
         match r {
             AdvanceResult::Match(c) => {
                 let v = machine.current().unwrap();
-                assert_eq!(v, Complex::Digits { _radix: Radix::Hexadecimal, _digits: format!("F") });
+                assert_eq!(v, Complex::Digits { _radix: Radix::Hexadecimal, _digits: String::from("F") });
                 assert_eq!(c, 1);
             }
             _ => unreachable!(),
@@ -266,13 +778,39 @@ mod test {
         match r {
             AdvanceResult::Match(c) => {
                 let v = machine.current().unwrap();
-                assert_eq!(v, Complex::Digits { _radix: Radix::Hexadecimal, _digits: format!("FE") });
+                assert_eq!(v, Complex::Digits { _radix: Radix::Hexadecimal, _digits: String::from("FE") });
                 assert_eq!(c, 1);
             }
             _ => unreachable!(),
         }
     }
 
+    #[test]
+    fn test_collect_after_element() {
+        // 空列にマッチしうる繰り返しや選択の手前で読んだ要素は集めない。
+        let mut machine = <Prefixed as Parse<char>>::StateMachine::default();
+        assert_eq!(machine.advance('#'), AdvanceResult::Partial(1));
+        assert_eq!(machine.advance('a'), AdvanceResult::Partial(1));
+        assert_eq!(machine.advance('b'), AdvanceResult::Partial(1));
+        assert_eq!(machine.advance(';'), AdvanceResult::Match(1));
+        assert_eq!(
+            machine.current().unwrap(),
+            Prefixed::Tag {
+                _name: String::from("ab")
+            }
+        );
+
+        let mut machine = <Prefixed as Parse<char>>::StateMachine::default();
+        assert_eq!(machine.advance('@'), AdvanceResult::Partial(1));
+        assert_eq!(machine.advance(';'), AdvanceResult::Match(1));
+        assert_eq!(
+            machine.current().unwrap(),
+            Prefixed::Mark {
+                _name: String::new()
+            }
+        );
+    }
+
     #[test]
     fn test_hex_usize() {
         let mut machine = <HexUsize as Parse<char>>::StateMachine::default();
@@ -297,4 +835,647 @@ mod test {
             _ => unreachable!(),
         }
     }
+
+    #[test]
+    fn test_quoted() {
+        let mut machine = <Quoted as Parse<char>>::StateMachine::default();
+
+        assert_eq!(machine.advance('"'), AdvanceResult::Partial(1));
+        assert_eq!(machine.advance('a'), AdvanceResult::Partial(1));
+        assert_eq!(machine.advance('\u{10FFFF}'), AdvanceResult::Partial(1));
+        assert_eq!(machine.advance('"'), AdvanceResult::Match(1));
+        assert_eq!(
+            machine.current().unwrap(),
+            Quoted::Str {
+                _body: String::from("a\u{10FFFF}")
+            }
+        );
+
+        let mut machine = <Quoted as Parse<char>>::StateMachine::default();
+        assert_eq!(machine.advance('"'), AdvanceResult::Partial(1));
+        assert_eq!(machine.advance('\\'), AdvanceResult::Error);
+
+        let mut machine = <Quoted as Parse<char>>::StateMachine::default();
+        assert_eq!(machine.advance('\u{E000}'), AdvanceResult::Match(1));
+        assert_eq!(
+            machine.current().unwrap(),
+            Quoted::Other {
+                _c: String::from("\u{E000}")
+            }
+        );
+    }
+
+    #[test]
+    fn test_word() {
+        assert_eq!(parse("if"), Some(Word::Keyword));
+        assert_eq!(parse("else"), Some(Word::Keyword));
+        assert_eq!(
+            parse("i"),
+            Some(Word::Ident {
+                _name: String::from("i")
+            })
+        );
+        assert_eq!(
+            parse("elsewhere"),
+            Some(Word::Ident {
+                _name: String::from("elsewhere")
+            })
+        );
+        assert_eq!(
+            parse("els"),
+            Some(Word::Ident3 {
+                _name: String::from("els")
+            })
+        );
+        assert_eq!(parse::<Word>("if0"), None);
+    }
+
+    #[test]
+    fn test_builtin_classes() {
+        assert_eq!(
+            parse_elements(b"019"),
+            Some(ByteToken::Number {
+                _num: String::from("019")
+            })
+        );
+        assert_eq!(
+            parse_elements(b"_a1"),
+            Some(ByteToken::Ident {
+                _name: String::from("_a1")
+            })
+        );
+        assert_eq!(parse_elements(b" \t\r\n"), Some(ByteToken::Space));
+        assert_eq!(
+            parse_elements(b"0xfF"),
+            Some(ByteToken::Hex {
+                _hex: String::from("fF")
+            })
+        );
+        assert_eq!(parse_elements::<_, ByteToken>(b"0xg"), None);
+        assert_eq!(parse_elements(b"\xFF"), Some(ByteToken::Other));
+    }
+
+    #[test]
+    fn test_unicode_classes() {
+        assert_eq!(
+            parse("変数x1"),
+            Some(Intl::Ident {
+                _name: String::from("変数x1")
+            })
+        );
+        assert_eq!(parse::<Intl>("1x"), None);
+        assert_eq!(
+            parse("١٢٣"),
+            Some(Intl::Number {
+                _num: String::from("١٢٣")
+            })
+        );
+        assert_eq!(
+            parse("漢字。"),
+            Some(Intl::Sentence {
+                _text: String::from("漢字")
+            })
+        );
+        assert_eq!(parse::<Intl>("かな。"), None);
+        assert_eq!(parse("\u{3000} \u{2028}"), Some(Intl::Space));
+    }
+
+    #[test]
+    fn test_ignore_case() {
+        assert_eq!(parse_elements(b"select"), Some(SqlKeyword::Select));
+        assert_eq!(parse_elements(b"SeLeCt"), Some(SqlKeyword::Select));
+        assert_eq!(parse_elements(b"FROM"), Some(SqlKeyword::From));
+        assert_eq!(parse_elements(b"Y"), Some(SqlKeyword::Xyz));
+        assert_eq!(parse_elements::<_, SqlKeyword>(b"W"), None);
+
+        assert_eq!(
+            parse("HeLLo, World"),
+            Some(Greeting::Hello {
+                _name: String::from("World")
+            })
+        );
+        assert_eq!(parse::<Greeting>("HeLLo; World"), None);
+        assert_eq!(parse("Σ"), Some(Greeting::Sigma));
+        assert_eq!(parse("ς"), Some(Greeting::Sigma));
+
+        // 否定や差は、同一視した要素から取る。
+        assert_eq!(parse("nx"), Some(Folded::NotS));
+        assert_eq!(parse::<Folded>("ns"), None);
+        assert_eq!(parse::<Folded>("nS"), None);
+        assert_eq!(parse::<Folded>("nſ"), None);
+        assert_eq!(parse("m1"), Some(Folded::NotLetter));
+        assert_eq!(parse::<Folded>("ms"), None);
+        assert_eq!(parse::<Folded>("mX"), None);
+        assert_eq!(parse::<Folded>("m\u{212a}"), None);
+        assert_eq!(parse("ob"), Some(Folded::NotT));
+        assert_eq!(parse("OB"), Some(Folded::NotT));
+        assert_eq!(parse::<Folded>("ot"), None);
+        assert_eq!(parse::<Folded>("oT"), None);
+    }
+
+    #[test]
+    fn test_bounded_repeat() {
+        // 最大回数は最小回数に足さず、そのまま上限になる。
+        assert_eq!(parse::<Bounded>("x1"), None);
+        assert_eq!(parse("x12"), Some(Bounded::Inclusive));
+        assert_eq!(parse("x123"), Some(Bounded::Inclusive));
+        assert_eq!(parse::<Bounded>("x1234"), None);
+        assert_eq!(parse::<Bounded>("y1"), None);
+        assert_eq!(parse("y123"), Some(Bounded::Exclusive));
+        assert_eq!(parse::<Bounded>("y1234"), None);
+    }
+
+    #[test]
+    fn test_regex() {
+        assert_eq!(
+            parse("0x1aF"),
+            Some(RegexToken::Hex {
+                _hex: String::from("1aF")
+            })
+        );
+        assert_eq!(
+            parse("12.5"),
+            Some(RegexToken::Number {
+                _int: String::from("12"),
+                _frac: String::from("5")
+            })
+        );
+        assert_eq!(
+            parse("12"),
+            Some(RegexToken::Number {
+                _int: String::from("12"),
+                _frac: String::new()
+            })
+        );
+        assert_eq!(parse::<RegexToken>("1.2345"), None);
+        assert_eq!(parse("NuLL"), Some(RegexToken::Null));
+        assert_eq!(parse("()"), Some(RegexToken::Null));
+        assert_eq!(parse("αβγ"), Some(RegexToken::Other));
+        assert_eq!(parse("+"), Some(RegexToken::Other));
+        assert_eq!(parse::<RegexToken>("a"), None);
+    }
+
+    #[test]
+    fn test_shorthand_operators() {
+        assert_eq!(
+            parse("[]"),
+            Some(List::Numbers {
+                _items: String::new()
+            })
+        );
+        assert_eq!(
+            parse("[1, 23, 456]"),
+            Some(List::Numbers {
+                _items: String::from("1, 23, 456")
+            })
+        );
+        assert_eq!(parse::<List>("[1, ]"), None);
+        assert_eq!(parse::<List>("(a)"), None);
+        assert_eq!(
+            parse("(a,b)"),
+            Some(List::Tuple {
+                _items: String::from("a,b")
+            })
+        );
+        assert_eq!(
+            parse("(a,b,c)"),
+            Some(List::Tuple {
+                _items: String::from("a,b,c")
+            })
+        );
+        assert_eq!(parse::<List>("(a,b,c,d)"), None);
+        assert_eq!(parse("-1_000"), Some(List::Integer));
+        assert_eq!(parse("42"), Some(List::Integer));
+        assert_eq!(parse::<List>("1__0"), None);
+        assert_eq!(parse::<List>("-"), None);
+    }
+
+    #[test]
+    fn test_repeated_collect() {
+        assert_eq!(
+            parse("192.168.0.1"),
+            Some(Repeated::Ipv4 {
+                _octets: vec![192, 168, 0, 1]
+            })
+        );
+        assert_eq!(parse::<Repeated>("192.168.0"), None);
+        // `Vec` 以外のフィールドには、繰り返すたびに続けて加える。
+        assert_eq!(
+            parse("$123"),
+            Some(Repeated::Joined {
+                _text: String::from("123")
+            })
+        );
+        assert_eq!(
+            parse("#123"),
+            Some(Repeated::Digits {
+                _digits: vec![1, 2, 3]
+            })
+        );
+        assert_eq!(
+            parse("\"\""),
+            Some(Repeated::Words { _words: vec![] })
+        );
+        assert_eq!(
+            parse("\"ab cd e\""),
+            Some(Repeated::Words {
+                _words: vec![String::from("ab"), String::from("cd"), String::from("e")]
+            })
+        );
+    }
+
+    #[test]
+    fn test_sub() {
+        assert_eq!(
+            parse("x=0x1f;"),
+            Some(Statement::Assign {
+                _name: String::from("x"),
+                _value: Num::Hex {
+                    _value: String::from("1f")
+                }
+            })
+        );
+        assert_eq!(
+            parse("x=12;"),
+            Some(Statement::Assign {
+                _name: String::from("x"),
+                _value: Num::Dec { _value: 12 }
+            })
+        );
+        assert_eq!(parse::<Statement>("x=;"), None);
+        assert_eq!(parse::<Statement>("x=0x;"), None);
+        assert_eq!(
+            parse("@42"),
+            Some(Statement::Jump {
+                _target: Num::Dec { _value: 42 }
+            })
+        );
+        assert_eq!(parse::<Statement>("@"), None);
+        assert_eq!(
+            parse("(1,0x2,3)"),
+            Some(Statement::Tuple {
+                _items: Num::Dec { _value: 3 }
+            })
+        );
+
+        let mut machine = <Statement as Parse<char>>::StateMachine::default();
+        for c in "@4".chars() {
+            machine.advance(c);
+        }
+        assert_eq!(machine.complete(), CompleteResult::Match(0));
+
+        // `Num` はマッチした後も数字や `x` を読み進めうるため、`sub!` に続くパターンはそれらで始まれない。
+        let element = |c: char| ElementSet::Range(c as u64, c as u64);
+        let continuations = <Num as SubPattern<char>>::CONTINUATIONS;
+        assert!(continuations.overlaps(element('7')));
+        assert!(continuations.overlaps(element('x')));
+        assert!(!continuations.overlaps(element(';')));
+        assert!(<Num as SubPattern<char>>::FIRST.overlaps(element('0')));
+        assert!(!<Num as SubPattern<char>>::FIRST.overlaps(element('x')));
+        const { assert!(!<Num as SubPattern<char>>::NULLABLE) };
+
+        assert_eq!(
+            parse("b+12"),
+            Some(Ranked::Rank(
+                Graded::Grade {
+                    _grade: String::from("b")
+                },
+                Num::Dec { _value: 12 }
+            ))
+        );
+        assert_eq!(
+            parse("z0x1f"),
+            Some(Ranked::Rank(
+                Graded::Last,
+                Num::Hex {
+                    _value: String::from("1f")
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn test_recursive_declares() {
+        assert_eq!(parse("()"), Some(Nested::Parens));
+        assert_eq!(parse("(()())"), Some(Nested::Parens));
+        assert_eq!(parse("()(())()"), Some(Nested::Parens));
+        assert_eq!(parse::<Nested>("(()"), None);
+        assert_eq!(parse::<Nested>("())"), None);
+        assert_eq!(parse::<Nested>(""), None);
+        assert_eq!(
+            parse("[1,[22,[]],[3]]"),
+            Some(Nested::List {
+                _text: String::from("[1,[22,[]],[3]]")
+            })
+        );
+        assert_eq!(parse("[]"), Some(Nested::List { _text: String::from("[]") }));
+        assert_eq!(parse::<Nested>("[1,]"), None);
+        assert_eq!(parse::<Nested>("[[1]"), None);
+
+        // 最上位の委譲を除いて、入れ子は `max_depth` 段まで。
+        assert_eq!(parse("((((()))))"), Some(Nested::Parens));
+        assert_eq!(parse::<Nested>("(((((())))))"), None);
+
+        let mut machine = <Nested as Parse<char>>::StateMachine::default();
+        for c in "(())".chars() {
+            machine.advance(c);
+        }
+        assert_eq!(machine.complete(), CompleteResult::Match(0));
+
+        let mut machine = <Nested as Parse<char>>::StateMachine::default();
+        for c in "(()".chars() {
+            machine.advance(c);
+        }
+        assert_eq!(machine.complete(), CompleteResult::Error);
+    }
+
+    #[test]
+    fn test_parameterised_declares() {
+        assert_eq!(
+            parse("1;22;333"),
+            Some(Params::Numbers {
+                _items: String::from("1;22;333")
+            })
+        );
+        assert_eq!(parse::<Params>("1;"), None);
+        assert_eq!(parse("#(a,b,c)"), Some(Params::Letters));
+        assert_eq!(parse::<Params>("#(a,1)"), None);
+        assert_eq!(parse("#(x)"), Some(Params::Letters));
+
+        // 本体の変数は宣言された時点のものが使われる。
+        assert_eq!(parse("=-5"), Some(Params::Signed));
+        assert_eq!(parse("=5"), Some(Params::Signed));
+        assert_eq!(parse::<Params>("=+5"), None);
+        assert_eq!(parse("!x"), Some(Params::X));
+    }
+
+    #[test]
+    fn test_pattern_library() {
+        assert_eq!(
+            parse("x1=42"),
+            Some(Imported::Assign {
+                _name: String::from("x1"),
+                _value: 42
+            })
+        );
+        assert_eq!(parse::<Imported>("1x=42"), None);
+        assert_eq!(parse("(1,23)"), Some(Imported::Pair));
+        assert_eq!(parse::<Imported>("(1)"), None);
+
+        assert_eq!(parse_elements(b"-12"), Some(ImportedBytes::Number));
+        assert_eq!(parse_elements(b"12"), Some(ImportedBytes::Number));
+        assert_eq!(parse_elements::<_, ImportedBytes>(b"a"), None);
+
+        // 別のモジュールにある同名のライブラリは、それぞれの宣言を持つ。
+        assert_eq!(parse("12"), Some(DigitToken::Token));
+        assert_eq!(parse::<DigitToken>("ab"), None);
+        assert_eq!(parse("ab"), Some(AlphaToken::Token));
+        assert_eq!(parse::<AlphaToken>("12"), None);
+
+        // 公開しないライブラリは、名前も宣言も同じでも重ならない。
+        assert_eq!(parse("1f"), Some(HexToken::Token));
+        assert_eq!(parse::<HexToken>("g"), None);
+    }
+
+    #[test]
+    fn test_refined_declares() {
+        assert_eq!(parse("f12."), Some(Refined::Float));
+        assert_eq!(parse::<Refined>("f12"), None);
+        assert_eq!(parse("i12"), Some(Refined::Int));
+        assert_eq!(parse::<Refined>("i12."), None);
+    }
+
+    #[test]
+    fn test_unordered_declares() {
+        assert_eq!(parse("a1=42"), Some(Unordered::Assign));
+        assert_eq!(parse("a=b"), Some(Unordered::Assign));
+        assert_eq!(parse("@f(x)"), Some(Unordered::Call));
+        assert_eq!(parse("@f(1)"), Some(Unordered::Call));
+        assert_eq!(parse::<Unordered>("f(1)"), None);
+    }
+
+    #[test]
+    fn test_assign_fields() {
+        assert_eq!(
+            parse("0x1f"),
+            Some(Literal::Number {
+                _radix: Radix::Hexadecimal,
+                _digits: String::from("1f")
+            })
+        );
+        assert_eq!(
+            parse("01"),
+            Some(Literal::Number {
+                _radix: Radix::Decimal,
+                _digits: String::from("01")
+            })
+        );
+        assert_eq!(parse("true"), Some(Literal::Bool(true)));
+        assert_eq!(parse("false"), Some(Literal::Bool(false)));
+        assert_eq!(parse::<Literal>("0x"), None);
+    }
+
+    #[test]
+    fn test_validate_and_map() {
+        fn parse(s: &str) -> Result<Checked, MatchError> {
+            let mut machine = <Checked as Parse<char>>::StateMachine::default();
+            for c in s.chars() {
+                machine.advance(c);
+            }
+            machine.current()
+        }
+
+        assert_eq!(
+            parse("12/31").ok(),
+            Some(Checked::Date {
+                _month: 12,
+                _day: 31
+            })
+        );
+        assert!(matches!(parse("13/1"), Err(MatchError::Collect(e)) if e.to_string() == "invalid month"));
+        assert!(matches!(parse("1/32"), Err(MatchError::Collect(e)) if e.to_string() == "invalid day"));
+        assert_eq!(parse("100%").ok(), Some(Checked::Percent(100)));
+        assert!(matches!(parse("101%"), Err(MatchError::NotMatched)));
+    }
+
+    #[test]
+    fn test_generic_enum() {
+        assert_eq!(parse::<Generic<usize>>("42"), Some(Generic::Number { _value: 42 }));
+        assert_eq!(
+            parse::<Generic<String>>("42"),
+            Some(Generic::Number {
+                _value: String::from("42")
+            })
+        );
+        assert_eq!(
+            parse::<Generic<usize>>("\"ab\""),
+            Some(Generic::Text(Cow::Borrowed("ab")))
+        );
+        assert_eq!(parse::<Generic<usize>>("\"a1\""), None);
+    }
+
+    #[test]
+    fn test_struct() {
+        assert_eq!(
+            parse("1.20.3"),
+            Some(Version {
+                _major: 1,
+                _minor: 20,
+                _patch: 3
+            })
+        );
+        assert_eq!(parse::<Version>("1.20"), None);
+        assert_eq!(
+            parse("#ff8000"),
+            Some(Rgb(
+                String::from("ff"),
+                String::from("80"),
+                String::from("00")
+            ))
+        );
+        assert_eq!(parse::<Rgb>("#ff800"), None);
+        assert_eq!(parse("NULL"), Some(Null));
+    }
+
+    #[test]
+    fn test_table_codegen() {
+        assert_eq!(
+            parse("xy=0x1f;"),
+            Some(Tabled::Assign {
+                _name: String::from("xy"),
+                _value: Num::Hex {
+                    _value: String::from("1f")
+                }
+            })
+        );
+        assert_eq!(
+            parse("0x1f"),
+            Some(Tabled::Number {
+                _radix: Radix::Hexadecimal,
+                _digits: String::from("1f")
+            })
+        );
+        assert_eq!(
+            parse("ab"),
+            Some(Tabled::Number {
+                _radix: Radix::Decimal,
+                _digits: String::from("ab")
+            })
+        );
+        assert_eq!(
+            parse("#1234;"),
+            Some(Tabled::Counted {
+                _digits: String::from("1234")
+            })
+        );
+        assert_eq!(parse::<Tabled>("#12345;"), None);
+        assert_eq!(
+            parse("[1,23]"),
+            Some(Tabled::List {
+                _text: String::from("[1,23]")
+            })
+        );
+        assert_eq!(parse::<Tabled>("[1,]"), None);
+        assert_eq!(parse::<Tabled>("xy=;"), None);
+        assert_eq!(
+            parse("@c"),
+            Some(Tabled::Grade {
+                _grade: String::from("c")
+            })
+        );
+        assert_eq!(parse::<Tabled>("@d"), None);
+    }
+
+    #[test]
+    fn test_wide_elements() {
+        assert_eq!(parse_elements(&[0x31u16, 0x32]), Some(Wide16::Digits));
+        assert_eq!(parse_elements(&[0x3042u16, 0x30ff, 0x3000]), Some(Wide16::Kana));
+        assert_eq!(parse_elements::<u16, Wide16>(&[0x3042, 0x3100]), None);
+        assert_eq!(parse_elements(&[0x31u32, 0x32]), Some(Wide32::Digits));
+        assert_eq!(parse_elements(&[0x1f600u32, 0x39]), Some(Wide32::Emoji));
+        assert_eq!(parse_elements::<u32, Wide32>(&[0x1f601]), None);
+    }
+
+    #[test]
+    fn test_counted_repeat() {
+        let hex = "0123456789abcdef".repeat(4);
+        assert_eq!(
+            parse(&format!("sha256:{hex}")),
+            Some(Digest::Sha256 { _hex: hex.clone() })
+        );
+        assert_eq!(parse::<Digest>(&format!("sha256:{}", &hex[1..])), None);
+        assert_eq!(parse::<Digest>(&format!("sha256:{hex}0")), None);
+        assert_eq!(
+            parse("id:12.tar"),
+            Some(Digest::Id {
+                _id: String::from("12"),
+                _ext: String::from("tar")
+            })
+        );
+        assert_eq!(
+            parse("id:12."),
+            Some(Digest::Id {
+                _id: String::from("12"),
+                _ext: String::new()
+            })
+        );
+        assert_eq!(parse::<Digest>("id:12"), None);
+        assert_eq!(parse::<Digest>(&format!("id:{}.", "1".repeat(21))), None);
+        assert_eq!(parse::<Digest>(&format!("id:1.{}", "a".repeat(13))), None);
+
+        assert_eq!(parse("pad:1200"), Some(Padded::Padded));
+        assert_eq!(parse("pad:00"), Some(Padded::Padded));
+        assert_eq!(parse::<Padded>("pad:0"), None);
+        assert_eq!(
+            parse(&format!("pad:{}00", "1".repeat(16))),
+            Some(Padded::Padded)
+        );
+        assert_eq!(parse::<Padded>(&format!("pad:{}00", "1".repeat(17))), None);
+    }
+
+    #[test]
+    fn test_const_bounds() {
+        assert_eq!(parse("#a0f"), Some(Counted::Code));
+        assert_eq!(parse::<Counted>("#a0"), None);
+        assert_eq!(parse::<Counted>("#a0f1"), None);
+        assert_eq!(
+            parse("n1234;"),
+            Some(Counted::Number {
+                _digits: String::from("1234")
+            })
+        );
+        assert_eq!(parse::<Counted>("n;"), None);
+        assert_eq!(parse::<Counted>("n12345;"), None);
+        assert_eq!(parse("gb"), Some(Counted::Grade));
+        assert_eq!(parse::<Counted>("gd"), None);
+        assert_eq!(parse("o"), Some(Counted::Short));
+        assert_eq!(parse("ox"), Some(Counted::Short));
+        assert_eq!(parse::<Counted>("oxy"), None);
+        assert_eq!(
+            parse("b+"),
+            Some(Graded::Grade {
+                _grade: String::from("b")
+            })
+        );
+        assert_eq!(parse("z+"), Some(Graded::Last));
+        assert_eq!(parse::<Graded>("d"), None);
+    }
+
+    #[test]
+    fn test_variant_shapes() {
+        assert_eq!(parse("abc:12"), Some(Shape::Pair(String::from("abc"), 12)));
+        assert_eq!(parse("#42"), Some(Shape::Named { value: 42 }));
+        assert_eq!(parse(";"), Some(Shape::Unit));
+        assert_eq!(
+            parse("@0x1f/ab"),
+            Some(Shape::Mixed(
+                Num::Hex {
+                    _value: String::from("1f")
+                },
+                String::from("ab")
+            ))
+        );
+        assert_eq!(parse::<Shape>("abc:"), None);
+        assert_eq!(parse::<Shape>(";;"), None);
+    }
 }
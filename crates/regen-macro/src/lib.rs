@@ -1,6 +1,31 @@
 use proc_macro::TokenStream;
 
+/// Generates a state machine that parses the annotated enum or struct from its `#[pattern]`s.
+///
+/// # Constants in bounds
+///
+/// Repeat bounds and range endpoints may name `const` items and associated constants, such as
+/// `[digit; 1..=MAX_DIGITS]` or `'a'..=LAST`. Their values are only known after expansion, so the
+/// generated code counts the repetitions instead of unrolling them. This comes with limitations:
+///
+/// - Only patterns matching a single element (literals, ranges, classes and their `|`, `&`, `-`
+///   and `!` combinations) can be repeated a constant number of times. `["ab"; 1..N]` is rejected;
+///   repeat such patterns with literal bounds.
+/// - A counted pattern cannot be followed by, or be an alternative to, a pattern that can also
+///   match the counted elements.
+/// - Constants cannot be used in recursive declarations.
 #[proc_macro_attribute]
 pub fn regen(attr: TokenStream, body: TokenStream) -> TokenStream {
     regen_macro_impl::regen(attr.into(), body.into()).into()
 }
+
+#[proc_macro]
+pub fn patterns(input: TokenStream) -> TokenStream {
+    regen_macro_impl::patterns(input.into()).into()
+}
+
+#[doc(hidden)]
+#[proc_macro]
+pub fn regen_import(input: TokenStream) -> TokenStream {
+    regen_macro_impl::regen_import(input.into()).into()
+}
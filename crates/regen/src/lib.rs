@@ -1,14 +1,19 @@
 pub use regen_core::*;
-pub use regen_macro::regen;
+pub use regen_macro::{patterns, regen};
 
 pub mod __internal_macro {
     pub use regen_core::*;
+    pub use regen_macro::regen_import;
     pub mod std {
         pub use Box;
         pub use Default;
         pub use Into;
         pub use Result;
+        pub use String;
+        pub use Vec;
+        pub use std::convert::Infallible;
         pub use std::error::Error;
+        pub use std::marker::PhantomData;
         pub use std::mem::replace;
         pub use {char, u8, u16, u32, u64};
     }